@@ -0,0 +1,409 @@
+//! Command line client for the core socket, for driving a headless core from
+//! shell scripts and editor plugins. Each command connects, exchanges its
+//! messages and exits, `watch` keeps the connection open. The core serves
+//! several clients, so it can run next to the shell.
+use clap::{Parser, Subcommand, ValueEnum};
+use socket_lib::discovery::{self, CoreAddress};
+use socket_lib::{
+    AudioCaptureMessage, CallStartMessage, CameraStartMessage, Content, ContentType, EventSocket,
+    Message, ScreenShareMessage, ScreenShareResolution, SocketOptions, SocketSender, Topic,
+};
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Tauri identifier of the shell, its app data dir is named after it.
+const SHELL_IDENTIFIER: &str = "com.hopp.app";
+
+/// Keeps the core from timing out the connection while `watch` runs.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Parser)]
+#[command(
+    name = "hoppctl",
+    author,
+    version,
+    about = "Drive hopp_core over its socket"
+)]
+struct Args {
+    /// Custom socket path (defaults to the core the shell is running, or
+    /// $TMPDIR/core-socket without one)
+    #[arg(long, global = true)]
+    socket_path: Option<String>,
+
+    /// Token the core was started with via --auth-token, overrides the
+    /// shell's
+    #[arg(long, global = true)]
+    auth_token: Option<String>,
+
+    /// Seconds to wait for the core to answer
+    #[arg(long, global = true, default_value_t = 10)]
+    timeout: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Start or end a call
+    Call {
+        #[command(subcommand)]
+        action: CallAction,
+    },
+    /// Mute the microphone
+    Mute,
+    /// Unmute the microphone
+    Unmute,
+    /// Start capturing from a microphone
+    Mic {
+        /// Device name as printed by `list mics`
+        name: String,
+    },
+    /// Share a display or a window
+    Share {
+        /// Display id to share
+        #[arg(long, conflicts_with = "window", required_unless_present = "window")]
+        display: Option<u32>,
+        /// Window id to share
+        #[arg(long)]
+        window: Option<u32>,
+        /// Resolution to stream at
+        #[arg(long, value_enum, default_value = "p4k")]
+        resolution: Resolution,
+    },
+    /// Stop sharing
+    StopShare,
+    /// Start or stop the camera
+    Camera {
+        #[command(subcommand)]
+        action: CameraAction,
+    },
+    /// List microphones or cameras
+    List {
+        #[arg(value_enum)]
+        what: ListTarget,
+        /// Print the devices as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Open the call stats window
+    Stats,
+    /// Print every message from the core as one JSON object per line
+    Watch {
+        /// Only print these broadcast topics, all of them if omitted
+        #[arg(long = "topic", value_enum)]
+        topics: Vec<WatchTopic>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CallAction {
+    /// Join the rooms of the given LiveKit tokens
+    Start {
+        /// LiveKit token for the audio participant
+        #[arg(long)]
+        token: String,
+        /// LiveKit token for the video participant
+        #[arg(long)]
+        video_token: String,
+        /// Microphone to capture from, the default one if empty
+        #[arg(long, default_value = "")]
+        mic: String,
+        /// LiveKit server URL, sent before starting if the core doesn't have it yet
+        #[arg(long)]
+        livekit_url: Option<String>,
+    },
+    /// Leave the call
+    End,
+}
+
+#[derive(Subcommand, Debug)]
+enum CameraAction {
+    /// Start the camera
+    Start {
+        /// Camera name as printed by `list cameras`, the preferred one if omitted
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Stop the camera
+    Stop,
+}
+
+#[derive(Clone, ValueEnum, Debug)]
+enum ListTarget {
+    Mics,
+    Cameras,
+}
+
+#[derive(Clone, Copy, ValueEnum, Debug)]
+enum WatchTopic {
+    Participants,
+    AudioLevel,
+    Devices,
+    Preferences,
+}
+
+impl From<WatchTopic> for Topic {
+    fn from(topic: WatchTopic) -> Self {
+        match topic {
+            WatchTopic::Participants => Topic::Participants,
+            WatchTopic::AudioLevel => Topic::AudioLevel,
+            WatchTopic::Devices => Topic::Devices,
+            WatchTopic::Preferences => Topic::Preferences,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum, Debug)]
+enum Resolution {
+    P1080,
+    P1440,
+    P4k,
+}
+
+impl From<Resolution> for ScreenShareResolution {
+    fn from(resolution: Resolution) -> Self {
+        match resolution {
+            Resolution::P1080 => ScreenShareResolution::P1080,
+            Resolution::P1440 => ScreenShareResolution::P1440,
+            Resolution::P4k => ScreenShareResolution::P4K,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("hoppctl: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> io::Result<()> {
+    let address = core_address(args.socket_path, args.auth_token)?;
+    /* One-shot commands only need their own responses, not the broadcasts. */
+    let subscriptions = match &args.command {
+        Command::Watch { topics } if topics.is_empty() => None,
+        Command::Watch { topics } => Some(topics.iter().map(|&t| Topic::from(t)).collect()),
+        _ => Some(Vec::new()),
+    };
+    let options = SocketOptions {
+        auth_token: address.auth_token,
+        subscriptions,
+        ..Default::default()
+    };
+    let (sender, events) = socket_lib::connect_with_options(&address.socket_path, &[], options)?;
+    let timeout = Duration::from_secs(args.timeout);
+
+    match args.command {
+        Command::Call { action } => match action {
+            CallAction::Start {
+                token,
+                video_token,
+                mic,
+                livekit_url,
+            } => {
+                if let Some(url) = livekit_url {
+                    sender.send(Message::LivekitServerUrl(url))?;
+                }
+                let request = Message::CallStart(CallStartMessage {
+                    audio_token: token,
+                    video_token,
+                    audio_device_name: mic,
+                    start_mic_on_call: None,
+                    start_camera_on_call: None,
+                });
+                match call(&sender, request, timeout)? {
+                    Message::CallStartResult(result) => check("call start", result),
+                    other => Err(unexpected(other)),
+                }
+            }
+            CallAction::End => sender.send(Message::CallEnd),
+        },
+        Command::Mute => sender.send(Message::MuteAudio),
+        Command::Unmute => sender.send(Message::UnmuteAudio),
+        Command::Mic { name } => {
+            let request = Message::StartAudioCapture(AudioCaptureMessage { device_name: name });
+            match call(&sender, request, timeout)? {
+                Message::StartAudioCaptureResult(result) => check("mic", result),
+                other => Err(unexpected(other)),
+            }
+        }
+        Command::Share {
+            display,
+            window,
+            resolution,
+        } => {
+            let content = match (display, window) {
+                (Some(id), _) => Content {
+                    content_type: ContentType::Display,
+                    id,
+                },
+                (None, Some(id)) => Content {
+                    content_type: ContentType::Window,
+                    id,
+                },
+                (None, None) => unreachable!("clap requires --display or --window"),
+            };
+            sender.send(Message::StartScreenShare(ScreenShareMessage {
+                content,
+                resolution: ScreenShareResolution::from(resolution).extent(),
+            }))?;
+            /* The result isn't a response to the request, it comes as an event. */
+            match wait_for_event(&events, timeout, |message| {
+                matches!(message, Message::StartScreenShareResult(_))
+            })? {
+                Message::StartScreenShareResult(result) => check("share", result),
+                other => Err(unexpected(other)),
+            }
+        }
+        Command::StopShare => sender.send(Message::StopScreenshare),
+        Command::Camera { action } => match action {
+            CameraAction::Start { name } => {
+                let request = Message::StartCamera(CameraStartMessage { device_name: name });
+                match call(&sender, request, timeout)? {
+                    Message::StartCameraResult(result) => check("camera start", result),
+                    other => Err(unexpected(other)),
+                }
+            }
+            CameraAction::Stop => sender.send(Message::StopCamera),
+        },
+        Command::List { what, json } => {
+            let devices: Vec<(String, bool)> = match what {
+                ListTarget::Mics => match call(&sender, Message::ListAudioDevices, timeout)? {
+                    Message::AudioDeviceList(devices) => {
+                        devices.into_iter().map(|d| (d.name, d.default)).collect()
+                    }
+                    other => return Err(unexpected(other)),
+                },
+                ListTarget::Cameras => match call(&sender, Message::ListCameras, timeout)? {
+                    Message::CameraList(cameras) => {
+                        cameras.into_iter().map(|c| (c.name, c.default)).collect()
+                    }
+                    other => return Err(unexpected(other)),
+                },
+            };
+            if json {
+                let devices: Vec<_> = devices
+                    .into_iter()
+                    .map(|(name, default)| serde_json::json!({ "name": name, "default": default }))
+                    .collect();
+                println!("{}", serde_json::Value::Array(devices));
+            } else {
+                for (name, default) in devices {
+                    println!("{}{name}", if default { "* " } else { "  " });
+                }
+            }
+            Ok(())
+        }
+        Command::Stats => sender.send(Message::OpenStatsWindow),
+        Command::Watch { .. } => watch(sender, events),
+    }
+}
+
+/// Where to connect: the given socket path, else the core the shell published
+/// in its app data dir, else the default path of a core started by hand.
+fn core_address(
+    socket_path: Option<String>,
+    auth_token: Option<String>,
+) -> io::Result<CoreAddress> {
+    if let Some(socket_path) = socket_path {
+        return Ok(CoreAddress {
+            socket_path,
+            auth_token,
+        });
+    }
+    let published = dirs::data_dir()
+        .map(|dir| dir.join(SHELL_IDENTIFIER).join(discovery::FILE_NAME))
+        .filter(|path| path.exists());
+    match published {
+        Some(path) => {
+            let shell = discovery::read(&path).map_err(|e| {
+                io::Error::new(e.kind(), format!("reading {}: {e}", path.display()))
+            })?;
+            Ok(CoreAddress {
+                socket_path: shell.socket_path,
+                auth_token: auth_token.or(shell.auth_token),
+            })
+        }
+        None => Ok(CoreAddress {
+            socket_path: format!("{}/core-socket", std::env::temp_dir().display()),
+            auth_token,
+        }),
+    }
+}
+
+fn call(sender: &SocketSender, request: Message, timeout: Duration) -> io::Result<Message> {
+    sender.call(request, timeout).map_err(io::Error::other)
+}
+
+/// Waits for the first event matching `wanted`, printing nothing for the rest.
+fn wait_for_event(
+    events: &EventSocket,
+    timeout: Duration,
+    wanted: impl Fn(&Message) -> bool,
+) -> io::Result<Message> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match events.events.recv_timeout(remaining) {
+            Ok(message) if wanted(&message) => return Ok(message),
+            Ok(_) => continue,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for the core",
+                ))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the core closed the connection",
+                ))
+            }
+        }
+    }
+}
+
+fn watch(sender: SocketSender, mut events: EventSocket) -> io::Result<()> {
+    std::thread::spawn(move || {
+        while sender.send(Message::Ping).is_ok() {
+            std::thread::sleep(PING_INTERVAL);
+        }
+    });
+    /* Responses sent without a request id are printed too. */
+    let responses = events.take_responses();
+    std::thread::spawn(move || {
+        for message in responses {
+            print_json(&message);
+        }
+    });
+    for message in events.take_events() {
+        print_json(&message);
+    }
+    Ok(())
+}
+
+fn print_json(message: &Message) {
+    let mut stdout = io::stdout().lock();
+    match serde_json::to_string(message) {
+        Ok(line) => {
+            let _ = writeln!(stdout, "{line}");
+            let _ = stdout.flush();
+        }
+        Err(e) => eprintln!("hoppctl: failed to serialize {message:?}: {e}"),
+    }
+}
+
+fn check(what: &str, result: Result<(), String>) -> io::Result<()> {
+    result.map_err(|e| io::Error::other(format!("{what} failed: {e}")))
+}
+
+fn unexpected(message: Message) -> io::Error {
+    io::Error::other(format!("unexpected response from the core: {message:?}"))
+}
@@ -0,0 +1,77 @@
+//! Where the running core listens, for clients that join the shell's core.
+//!
+//! The shell starts the core on a random socket path with a fresh auth token
+//! and writes both to `core-socket.json` in its app data dir:
+//!
+//! ```text
+//! {"socket_path":"/tmp/core-socket-AbC123","auth_token":"..."}
+//! ```
+//!
+//! The file is only readable by its owner since it carries the token. It is
+//! rewritten whenever the shell restarts the core and left behind when the
+//! shell exits, so connecting with a stale address just fails.
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the file in the shell's app data dir.
+pub const FILE_NAME: &str = "core-socket.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreAddress {
+    pub socket_path: String,
+    pub auth_token: Option<String>,
+}
+
+/// Writes `address` to `path`, replacing what was there.
+pub fn write(path: &Path, address: &CoreAddress) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        /* The mode only applies to new files, tighten one left by an older shell. */
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    serde_json::to_writer(&mut file, address)?;
+    file.write_all(b"\n")
+}
+
+/// Reads the address a shell wrote with `write`.
+pub fn read(path: &Path) -> io::Result<CoreAddress> {
+    let contents = std::fs::read(path)?;
+    serde_json::from_slice(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_round_trips_and_stays_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let address = CoreAddress {
+            socket_path: "/tmp/core-socket-abc".to_string(),
+            auth_token: Some("secret".to_string()),
+        };
+
+        /* An existing file, as if an older shell left it world readable. */
+        std::fs::write(&path, "old").unwrap();
+        write(&path, &address).unwrap();
+        assert_eq!(read(&path).unwrap(), address);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Wire format of a single message.
+//!
+//! Every message is sent as a frame, a fixed 12 byte header followed by the
+//! payload:
+//!
+//! | bytes   | field                                         |
+//! |---------|-----------------------------------------------|
+//! | 0..4    | magic, `b"HOPP"`                              |
+//! | 4       | frame format version, `FRAME_VERSION`         |
+//! | 5       | payload encoding, see `Encoding`              |
+//! | 6..8    | request id, u16 little endian, 0 for none     |
+//! | 8..12   | payload length, u32 little endian             |
+//!
+//! The header is the same on every platform and never changes size, so a
+//! frame of a newer version can still be skipped. A frame whose payload can't
+//! be decoded is skipped using its length, only a header without the magic
+//! means the stream is out of sync and has to be dropped.
+//!
+//! The request id lives in bytes that used to be reserved and always zero,
+//! a peer that predates it just sees frames without one.
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::Message;
+
+pub const FRAME_VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = 12;
+/// Largest payload accepted unless configured otherwise.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+
+const MAGIC: [u8; 4] = *b"HOPP";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Encoding {
+    Json = 0,
+    /// Only used for high-rate messages when both sides support it, see
+    /// `Capability::MessagePack`.
+    MessagePack = 1,
+}
+
+impl Encoding {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Encoding::Json),
+            1 => Some(Encoding::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+/// Correlates a request with its response, `NO_REQUEST_ID` for anything
+/// that isn't part of a `SocketSender::call`.
+pub type RequestId = u16;
+pub const NO_REQUEST_ID: RequestId = 0;
+
+#[derive(Debug)]
+pub struct Frame {
+    pub request_id: RequestId,
+    pub message: Message,
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    /// The stream failed, the connection is unusable.
+    Io(std::io::Error),
+    /// The header doesn't start with the magic, the stream is out of sync.
+    BadMagic([u8; 4]),
+    /// The frame was read and dropped, the next one can still be read.
+    Corrupt(String),
+}
+
+impl FrameError {
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, FrameError::Corrupt(_))
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(e) => write!(f, "IO error: {e}"),
+            FrameError::BadMagic(magic) => {
+                write!(
+                    f,
+                    "frame header has bad magic {magic:02x?}, stream is out of sync"
+                )
+            }
+            FrameError::Corrupt(reason) => write!(f, "corrupt frame: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<std::io::Error> for FrameError {
+    fn from(e: std::io::Error) -> Self {
+        FrameError::Io(e)
+    }
+}
+
+impl From<FrameError> for std::io::Error {
+    fn from(e: FrameError) -> Self {
+        match e {
+            FrameError::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+fn encode_payload(message: &Message, encoding: Encoding) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Json => Ok(serde_json::to_vec(message)?),
+        #[cfg(feature = "msgpack")]
+        Encoding::MessagePack => rmp_serde::to_vec(message).map_err(std::io::Error::other),
+        #[cfg(not(feature = "msgpack"))]
+        Encoding::MessagePack => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "socket_lib was built without the msgpack feature",
+        )),
+    }
+}
+
+fn decode_payload(payload: &[u8], encoding: Encoding) -> Result<Message, FrameError> {
+    match encoding {
+        Encoding::Json => serde_json::from_slice(payload)
+            .map_err(|e| FrameError::Corrupt(format!("invalid JSON payload: {e}"))),
+        #[cfg(feature = "msgpack")]
+        Encoding::MessagePack => rmp_serde::from_slice(payload)
+            .map_err(|e| FrameError::Corrupt(format!("invalid MessagePack payload: {e}"))),
+        #[cfg(not(feature = "msgpack"))]
+        Encoding::MessagePack => Err(FrameError::Corrupt(
+            "MessagePack payload, but socket_lib was built without the msgpack feature".to_string(),
+        )),
+    }
+}
+
+/// Serializes `message` into a complete frame, header included.
+pub fn encode(
+    message: &Message,
+    encoding: Encoding,
+    request_id: RequestId,
+    max_frame_size: u32,
+) -> std::io::Result<Vec<u8>> {
+    let payload = encode_payload(message, encoding)?;
+    let length = u32::try_from(payload.len())
+        .ok()
+        .filter(|length| *length <= max_frame_size)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "message of {} bytes exceeds the frame limit of {max_frame_size}",
+                    payload.len()
+                ),
+            )
+        })?;
+
+    let mut frame = Vec::with_capacity(HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&MAGIC);
+    frame.push(FRAME_VERSION);
+    frame.push(encoding as u8);
+    frame.extend_from_slice(&request_id.to_le_bytes());
+    frame.extend_from_slice(&length.to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+pub fn write_frame<W: Write>(
+    writer: &mut W,
+    message: &Message,
+    encoding: Encoding,
+    request_id: RequestId,
+    max_frame_size: u32,
+) -> std::io::Result<()> {
+    let frame = encode(message, encoding, request_id, max_frame_size)?;
+    writer.write_all(&frame)
+}
+
+/// A parsed header, the payload is read separately by the sync and async
+/// readers.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Header {
+    version: u8,
+    encoding: u8,
+    request_id: RequestId,
+    length: u32,
+}
+
+impl Header {
+    pub(crate) fn parse(header: &[u8; HEADER_SIZE]) -> Result<Self, FrameError> {
+        let magic = [header[0], header[1], header[2], header[3]];
+        if magic != MAGIC {
+            return Err(FrameError::BadMagic(magic));
+        }
+        Ok(Self {
+            version: header[4],
+            encoding: header[5],
+            request_id: u16::from_le_bytes([header[6], header[7]]),
+            length: u32::from_le_bytes([header[8], header[9], header[10], header[11]]),
+        })
+    }
+
+    pub(crate) fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// The error for a frame whose payload was skipped for being larger than
+    /// `max_frame_size`, `None` if the payload should be read.
+    pub(crate) fn oversized(&self, max_frame_size: u32) -> Option<FrameError> {
+        (self.length > max_frame_size).then(|| {
+            FrameError::Corrupt(format!(
+                "frame of {} bytes exceeds the limit of {max_frame_size}",
+                self.length
+            ))
+        })
+    }
+
+    pub(crate) fn decode(&self, payload: &[u8]) -> Result<Frame, FrameError> {
+        if self.version != FRAME_VERSION {
+            return Err(FrameError::Corrupt(format!(
+                "unsupported frame version {}, expected {FRAME_VERSION}",
+                self.version
+            )));
+        }
+        let encoding = Encoding::from_byte(self.encoding)
+            .ok_or_else(|| FrameError::Corrupt(format!("unknown encoding {}", self.encoding)))?;
+        Ok(Frame {
+            request_id: self.request_id,
+            message: decode_payload(payload, encoding)?,
+        })
+    }
+}
+
+/// Reads the next frame. Frames that are too large, of an unknown version or
+/// that fail to decode are consumed and reported as `FrameError::Corrupt`.
+pub fn read_frame<R: Read>(reader: &mut R, max_frame_size: u32) -> Result<Frame, FrameError> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    let header = Header::parse(&header)?;
+
+    /* Never allocate what the peer claims, drain oversized frames in chunks instead. */
+    if let Some(error) = header.oversized(max_frame_size) {
+        let length = u64::from(header.length());
+        let skipped = std::io::copy(&mut reader.by_ref().take(length), &mut std::io::sink())?;
+        if skipped < length {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        return Err(error);
+    }
+    let mut payload = vec![0u8; header.length() as usize];
+    reader.read_exact(&mut payload)?;
+    header.decode(&payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frames(messages: &[(Message, Encoding)]) -> Cursor<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for (message, encoding) in messages {
+            write_frame(
+                &mut bytes,
+                message,
+                *encoding,
+                NO_REQUEST_ID,
+                DEFAULT_MAX_FRAME_SIZE,
+            )
+            .unwrap();
+        }
+        Cursor::new(bytes)
+    }
+
+    fn raw_frame(version: u8, encoding: u8, payload: &[u8]) -> Vec<u8> {
+        raw_request_frame(version, encoding, NO_REQUEST_ID, payload)
+    }
+
+    fn raw_request_frame(version: u8, encoding: u8, request_id: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = MAGIC.to_vec();
+        frame.extend_from_slice(&[version, encoding]);
+        frame.extend_from_slice(&request_id.to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_header_layout() {
+        let frame = encode(&Message::Ping, Encoding::Json, 7, DEFAULT_MAX_FRAME_SIZE).unwrap();
+        let payload = br#""Ping""#;
+        assert_eq!(frame, raw_request_frame(FRAME_VERSION, 0, 7, payload));
+
+        let frame = read_frame(&mut Cursor::new(frame), DEFAULT_MAX_FRAME_SIZE).unwrap();
+        assert_eq!(frame.request_id, 7);
+        assert!(matches!(frame.message, Message::Ping));
+    }
+
+    #[test]
+    fn test_oversized_frame_is_skipped() {
+        let mut bytes = frames(&[(
+            Message::RoomConnectionFailed("x".repeat(64)),
+            Encoding::Json,
+        )])
+        .into_inner();
+        bytes.extend(frames(&[(Message::Ping, Encoding::Json)]).into_inner());
+        let mut reader = Cursor::new(bytes);
+
+        let error = read_frame(&mut reader, 32).unwrap_err();
+        assert!(error.is_recoverable(), "{error}");
+        assert!(matches!(
+            read_frame(&mut reader, 32),
+            Ok(Frame {
+                message: Message::Ping,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_undecodable_frames_are_skipped() {
+        let mut bytes = raw_frame(FRAME_VERSION, 0, &[0xff, 0xfe, b'{']);
+        bytes.extend(raw_frame(FRAME_VERSION, 7, br#""Ping""#));
+        bytes.extend(raw_frame(FRAME_VERSION + 1, 0, br#""Ping""#));
+        bytes.extend(frames(&[(Message::CallEnd, Encoding::Json)]).into_inner());
+        let mut reader = Cursor::new(bytes);
+
+        for _ in 0..3 {
+            let error = read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE).unwrap_err();
+            assert!(error.is_recoverable(), "{error}");
+        }
+        assert!(matches!(
+            read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE),
+            Ok(Frame {
+                message: Message::CallEnd,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bad_magic_is_fatal() {
+        /* A frame in the old usize length prefixed format. */
+        let payload = br#""GetAvailableContent""#;
+        let mut bytes = payload.len().to_le_bytes().to_vec();
+        bytes.extend_from_slice(payload);
+        let error = read_frame(&mut Cursor::new(bytes), DEFAULT_MAX_FRAME_SIZE).unwrap_err();
+        assert!(matches!(error, FrameError::BadMagic(_)));
+        assert!(!error.is_recoverable());
+    }
+
+    #[test]
+    fn test_send_rejects_oversized_message() {
+        let message = Message::RoomConnectionFailed("x".repeat(64));
+        let error = encode(&message, Encoding::Json, NO_REQUEST_ID, 32).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_message_pack_round_trip() {
+        let mut reader = frames(&[
+            (Message::MicrophoneAudioLevel(0.25), Encoding::MessagePack),
+            (
+                Message::CameraFailed("busy".to_string()),
+                Encoding::MessagePack,
+            ),
+        ]);
+        assert!(matches!(
+            read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE),
+            Ok(Frame { message: Message::MicrophoneAudioLevel(level), .. }) if level == 0.25
+        ));
+        assert!(matches!(
+            read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE),
+            Ok(Frame { message: Message::CameraFailed(reason), .. }) if reason == "busy"
+        ));
+    }
+}
@@ -1,8 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fs;
-use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -14,14 +13,29 @@ use std::os::unix::net::{UnixListener, UnixStream};
 #[cfg(windows)]
 use std::net::{TcpListener, TcpStream};
 
+pub mod discovery;
+pub mod frame;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+pub mod record;
+mod rpc;
+pub mod server;
+
+use frame::{Encoding, FrameError, RequestId, NO_REQUEST_ID};
+use record::{Direction, Recorder};
+pub use rpc::{CallError, Request};
+use rpc::{Correlation, Incoming};
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct Extent {
     pub width: f64,
     pub height: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub enum ScreenShareResolution {
     P1080,
     P1440,
@@ -29,6 +43,7 @@ pub enum ScreenShareResolution {
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub enum ScreenSharePickerMode {
     #[default]
     Screen,
@@ -55,6 +70,7 @@ impl ScreenShareResolution {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct WindowFrameMessage {
     pub origin_x: f64,
     pub origin_y: f64,
@@ -63,12 +79,14 @@ pub struct WindowFrameMessage {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CursorPositionMessage {
     pub x: f32,
     pub y: f32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct MouseClickMessage {
     pub x: f32,
     pub y: f32,
@@ -78,12 +96,14 @@ pub struct MouseClickMessage {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct ScrollMessage {
     pub x: f64,
     pub y: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct KeystrokeMessage {
     pub key: String,
     pub meta: bool,
@@ -94,12 +114,14 @@ pub struct KeystrokeMessage {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub enum ContentType {
     Display,
     Window,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct Content {
     pub content_type: ContentType,
     pub id: u32,
@@ -115,12 +137,14 @@ impl fmt::Display for Content {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct ScreenShareMessage {
     pub content: Content,
     pub resolution: Extent,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CallStartMessage {
     pub audio_token: String,
     pub video_token: String,
@@ -130,28 +154,33 @@ pub struct CallStartMessage {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct SentryMetadata {
     pub user_id: String,
     pub app_version: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct DrawingEnabled {
     pub permanent: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct AudioDevice {
     pub name: String,
     pub default: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct AudioCaptureMessage {
     pub device_name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CameraDevice {
     pub name: String,
     // Camera index now is not stable, the `stable_id` is not yet released.
@@ -163,11 +192,13 @@ pub struct CameraDevice {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CameraStartMessage {
     pub device_name: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CoreParticipantState {
     pub identity: String,
     pub name: String,
@@ -175,9 +206,14 @@ pub struct CoreParticipantState {
     pub muted: bool,
     pub has_camera: bool,
     pub is_screensharing: bool,
+    /// The participant's build speaks another in-room protocol version, so
+    /// features it doesn't support are off for everyone.
+    #[serde(default)]
+    pub protocol_mismatch: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub enum CoreRoleChange {
     Sharer,
     Controller,
@@ -185,6 +221,7 @@ pub enum CoreRoleChange {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 pub struct CoreRoleEvent {
     pub role: CoreRoleChange,
 }
@@ -192,6 +229,7 @@ pub struct CoreRoleEvent {
 /// Represents the user's preferred interaction mode for screen sharing sessions.
 /// This is stored persistently and restored when the user joins a new session.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum StoredMode {
     /// Remote control mode - mouse and keyboard events are forwarded to the sharer
@@ -205,8 +243,165 @@ pub enum StoredMode {
     },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a participant may do on the sharer's screen, each role allows
+/// everything the ones before it do.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
+pub enum ControlRole {
+    /// Only watches, their cursor isn't shown
+    ViewOnly,
+    /// Shows their cursor and click animations
+    Pointer,
+    Draw,
+    /// Clicks, scrolls, types and pastes
+    Full,
+}
+
+/// The sharer assigns `role` to a user, `None` puts them back on the default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct ControlRoleAssignment {
+    pub user_id: String,
+    pub role: Option<ControlRole>,
+}
+
+/// Version of the socket protocol, bump it on any change to `Message` that an
+/// older peer can't deserialize.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long `connect`/`listen` wait for the peer's `Hello`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Optional features a peer supports, exchanged in the `Hello` so commands
+/// for a missing feature aren't sent at all.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
+pub enum Capability {
+    WindowPicking,
+    NoiseCancellation,
+    Camera,
+    RemoteControl,
+    /// Frames may carry MessagePack payloads, added automatically when built
+    /// with the `msgpack` feature.
+    MessagePack,
+    /// A capability added by a newer peer, ignored.
+    #[serde(other)]
+    #[cfg_attr(feature = "bindings", ts(skip), schemars(skip))]
+    Unknown,
+}
+
+/// Groups of events the core broadcasts, a client lists the ones it wants in
+/// `Hello::subscriptions`. Events outside every topic, like `CallEnded`, go to
+/// all clients.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
+pub enum Topic {
+    /// `ParticipantsSnapshot` and `RoleChange`.
+    Participants,
+    /// `MicrophoneAudioLevel`.
+    AudioLevel,
+    /// `ActiveMicChanged`, `ActiveCameraChanged` and `CameraFailed`.
+    Devices,
+    /// Drawing and mode preferences changed from a core window.
+    Preferences,
+    /// A topic added by a newer peer, ignored.
+    #[serde(other)]
+    #[cfg_attr(feature = "bindings", ts(skip), schemars(skip))]
+    Unknown,
+}
+
+/// First message sent in both directions on a new connection.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema))]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub capabilities: Vec<Capability>,
+    /// The client's copy of `SocketOptions::auth_token`, never kept after
+    /// the handshake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "bindings", ts(optional))]
+    pub auth_token: Option<String>,
+    /// The topics a client wants broadcast to it, all of them if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "bindings", ts(optional))]
+    pub subscriptions: Option<Vec<Topic>>,
+}
+
+impl Hello {
+    pub fn new(capabilities: &[Capability]) -> Self {
+        let mut capabilities = capabilities.to_vec();
+        if cfg!(feature = "msgpack") && !capabilities.contains(&Capability::MessagePack) {
+            capabilities.push(Capability::MessagePack);
+        }
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities,
+            auth_token: None,
+            subscriptions: None,
+        }
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Whether a broadcast of `message` should reach this peer.
+    pub fn is_subscribed(&self, message: &Message) -> bool {
+        match (message.topic(), &self.subscriptions) {
+            (Some(topic), Some(subscriptions)) => subscriptions.contains(&topic),
+            _ => true,
+        }
+    }
+}
+
+/// Why the `Hello` exchange failed. Returned inside an `std::io::Error`, use
+/// `get_ref()`/`downcast` on it to match on the reason.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandshakeError {
+    VersionMismatch {
+        local: u32,
+        remote: u32,
+    },
+    /// The peer sent something else first, it predates the handshake.
+    MissingHello(String),
+    /// The peer's first frame has no header, it predates the frame format.
+    MissingFrameHeader,
+    /// The client didn't present the listener's `auth_token`.
+    Unauthenticated,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::VersionMismatch { local, remote } => write!(
+                f,
+                "socket protocol version mismatch: we speak {local}, the peer speaks {remote}. Reinstall the app so the core and the shell match."
+            ),
+            HandshakeError::MissingHello(first) => write!(
+                f,
+                "the peer didn't start with Hello (got {first}), it is older than protocol version {PROTOCOL_VERSION}"
+            ),
+            HandshakeError::MissingFrameHeader => write!(
+                f,
+                "the peer sent a message without a frame header, it is older than protocol version {PROTOCOL_VERSION}. Reinstall the app so the core and the shell match."
+            ),
+            HandshakeError::Unauthenticated => {
+                write!(f, "the client didn't present the expected auth token")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(
+    feature = "bindings",
+    derive(ts_rs::TS, schemars::JsonSchema),
+    ts(export)
+)]
 pub enum Message {
+    Hello(Hello),
     GetAvailableContent,
     CallStart(CallStartMessage),
     CallStartResult(Result<(), String>),
@@ -259,6 +454,9 @@ pub enum Message {
     SetScreenShareResolution(ScreenShareResolution),
     SetScreenSharePickerMode(ScreenSharePickerMode),
     SetTelemetryEnabled(bool),
+    /// User ids whose control requests the sharer grants without asking.
+    SetTrustedControllers(Vec<String>),
+    SetControlRole(ControlRoleAssignment),
     /// Microphone RMS level in [0.0, 1.0], emitted ~1 Hz from core capturer.
     MicrophoneAudioLevel(f32),
 }
@@ -276,6 +474,66 @@ impl Message {
                 | Message::PreferredCamera(_)
         )
     }
+
+    /// Messages sent often enough that the payload size matters, these use
+    /// MessagePack when both sides support it.
+    pub fn is_high_rate(&self) -> bool {
+        matches!(self, Message::MicrophoneAudioLevel(_))
+    }
+
+    /// The topic a client has to subscribe to for this event, `None` for
+    /// messages every client gets.
+    pub fn topic(&self) -> Option<Topic> {
+        match self {
+            Message::ParticipantsSnapshot(_) | Message::RoleChange(_) => Some(Topic::Participants),
+            Message::MicrophoneAudioLevel(_) => Some(Topic::AudioLevel),
+            Message::ActiveMicChanged(_)
+            | Message::ActiveCameraChanged(_)
+            | Message::CameraFailed(_) => Some(Topic::Devices),
+            Message::ControllerDrawPersistChanged(_)
+            | Message::SharerDrawPersistChanged(_)
+            | Message::LastModeChanged(_)
+            | Message::DrawingDisabled => Some(Topic::Preferences),
+            _ => None,
+        }
+    }
+}
+
+/// Connection settings, `connect`/`listen` use the defaults.
+#[derive(Debug, Clone)]
+pub struct SocketOptions {
+    /// Largest frame payload sent or accepted, in bytes. Larger incoming frames
+    /// are dropped, larger outgoing messages fail with `InvalidInput`.
+    pub max_frame_size: u32,
+    /// Per-launch secret shared by the two processes. The client presents it
+    /// in its `Hello`, the listener drops clients that don't and keeps waiting.
+    pub auth_token: Option<String>,
+    /// Appends every message sent and received to this JSONL file, see
+    /// `record`.
+    pub record_path: Option<PathBuf>,
+    /// Topics a client subscribes to on a `server`, all of them if `None`.
+    pub subscriptions: Option<Vec<Topic>>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+            auth_token: None,
+            record_path: None,
+            subscriptions: None,
+        }
+    }
+}
+
+/// MessagePack for high-rate messages if both sides support it, JSON otherwise.
+fn encoding_for(message: &Message, peer: &Hello) -> Encoding {
+    if cfg!(feature = "msgpack") && message.is_high_rate() && peer.supports(Capability::MessagePack)
+    {
+        Encoding::MessagePack
+    } else {
+        Encoding::Json
+    }
 }
 
 // Platform-specific stream type alias
@@ -288,83 +546,181 @@ type Stream = TcpStream;
 #[derive(Clone)]
 pub struct SocketSender {
     stream: Arc<Mutex<Stream>>,
+    peer: Arc<Hello>,
+    max_frame_size: u32,
+    correlation: Arc<Correlation>,
+    recorder: Option<Arc<Recorder>>,
 }
 
 impl SocketSender {
+    /// Sends `message` without waiting for anything.
     pub fn send(&self, message: Message) -> Result<(), std::io::Error> {
-        let serialized_message = serde_json::to_string(&message)?;
-        let serialized_message = serialized_message.as_bytes();
-        let size = serialized_message.len();
-        let mut message_bytes = size.to_le_bytes().to_vec();
-        message_bytes.extend_from_slice(serialized_message);
+        self.write(&message, NO_REQUEST_ID)
+    }
+
+    /// Answers the peer's `Request` with the id `request_id`, its `call`
+    /// returns `message`.
+    pub fn respond(&self, request_id: RequestId, message: Message) -> Result<(), std::io::Error> {
+        self.write(&message, request_id)
+    }
+
+    /// Sends the request `message` and waits up to `timeout` for its
+    /// response. Concurrent calls each get their own response, a response
+    /// arriving after the timeout is dropped.
+    pub fn call(&self, message: Message, timeout: Duration) -> Result<Message, CallError> {
+        let (tx, response) = mpsc::sync_channel(1);
+        let request_id = self.correlation.register(Box::new(move |message| {
+            tx.try_send(message).map_err(|e| match e {
+                mpsc::TrySendError::Full(message) | mpsc::TrySendError::Disconnected(message) => {
+                    message
+                }
+            })
+        }))?;
+        let result = self
+            .write(&message, request_id)
+            .map_err(CallError::Io)
+            .and_then(|()| {
+                response.recv_timeout(timeout).map_err(|e| match e {
+                    mpsc::RecvTimeoutError::Timeout => CallError::Timeout,
+                    mpsc::RecvTimeoutError::Disconnected => CallError::Disconnected,
+                })
+            });
+        if result.is_err() {
+            self.correlation.cancel(request_id);
+        }
+        result
+    }
+
+    fn write(&self, message: &Message, request_id: RequestId) -> Result<(), std::io::Error> {
         let mut stream = self.stream.lock().unwrap();
-        stream.write_all(&message_bytes)?;
+        frame::write_frame(
+            &mut *stream,
+            message,
+            encoding_for(message, &self.peer),
+            request_id,
+            self.max_frame_size,
+        )?;
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Direction::Sent, message);
+        }
         Ok(())
     }
+
+    /// The `Hello` the peer sent during the handshake.
+    pub fn peer(&self) -> &Hello {
+        &self.peer
+    }
+
+    pub fn peer_supports(&self, capability: Capability) -> bool {
+        self.peer.supports(capability)
+    }
+
+    /// Closes the connection in both directions, the reader then exits.
+    fn shutdown(&self) {
+        use std::net::Shutdown;
+        let _ = self.stream.lock().unwrap().shutdown(Shutdown::Both);
+    }
 }
 
-/// Dual-channel receiver that owns the background reader thread.
+/// Receiver that owns the background reader thread.
 /// Dropping this struct shuts down the underlying stream, which causes the
-/// reader thread to exit and closes both channels on the remote side.
+/// reader thread to exit and closes the channels on the remote side.
+/// Responses to `SocketSender::call` go to the caller, only responses sent
+/// without a request id end up in `responses`. Requests the peer made
+/// through `call` arrive in `requests`, to be answered with `respond`.
 pub struct EventSocket {
     pub events: mpsc::Receiver<Message>,
     pub responses: mpsc::Receiver<Message>,
+    pub requests: mpsc::Receiver<Request>,
     stream: Stream,
 }
 
 impl EventSocket {
-    fn new(mut read_stream: Stream, shutdown_stream: Stream) -> Self {
+    fn new(read_half: ReadHalf) -> Self {
         let (event_tx, event_rx) = mpsc::channel();
         let (response_tx, response_rx) = mpsc::channel();
+        let (request_tx, request_rx) = mpsc::channel();
+
+        let stream = read_half.spawn(Box::new(move |incoming| match incoming {
+            Incoming::Request(request) => request_tx.send(request).is_ok(),
+            Incoming::Message(message) if message.is_response() => {
+                response_tx.send(message).is_ok()
+            }
+            Incoming::Message(message) => event_tx.send(message).is_ok(),
+        }));
+
+        Self {
+            events: event_rx,
+            responses: response_rx,
+            requests: request_rx,
+            stream,
+        }
+    }
+}
+
+/// Takes what the reader of a connection read, returns `false` once nobody
+/// listens anymore.
+pub(crate) type Deliver = Box<dyn FnMut(Incoming) -> bool + Send>;
+
+/// Read side of a connection whose reader thread hasn't started yet.
+pub(crate) struct ReadHalf {
+    read_stream: Stream,
+    shutdown_stream: Stream,
+    max_frame_size: u32,
+    correlation: Arc<Correlation>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl ReadHalf {
+    /// Starts the background reader, which hands everything but the
+    /// responses to our calls to `deliver` in the order it arrived. Returns
+    /// the stream that shuts the connection down.
+    pub(crate) fn spawn(self, mut deliver: Deliver) -> Stream {
+        let ReadHalf {
+            mut read_stream,
+            shutdown_stream,
+            max_frame_size,
+            correlation,
+            recorder,
+        } = self;
 
         log::info!("EventSocket: spawning background reader thread");
         std::thread::spawn(move || {
             let _ = read_stream.set_read_timeout(Some(Duration::from_secs(1)));
             loop {
-                match Self::read_message(&mut read_stream) {
-                    Ok(message) => {
-                        let tx = if message.is_response() {
-                            &response_tx
-                        } else {
-                            &event_tx
+                match frame::read_frame(&mut read_stream, max_frame_size) {
+                    Ok(frame) => {
+                        if let Some(recorder) = &recorder {
+                            recorder.record(Direction::Received, &frame.message);
+                        }
+                        let Some(incoming) = correlation.route(frame) else {
+                            continue;
                         };
-                        if tx.send(message).is_err() {
+                        if !deliver(incoming) {
                             log::info!("Background reader: channel closed, stopping");
                             break;
                         }
                     }
-                    Err(e) => {
+                    Err(FrameError::Io(e))
                         if e.kind() == std::io::ErrorKind::WouldBlock
-                            || e.kind() == std::io::ErrorKind::TimedOut
-                        {
-                            continue;
-                        }
-                        log::error!("Background reader: IO error: {e:?}");
+                            || e.kind() == std::io::ErrorKind::TimedOut =>
+                    {
+                        continue;
+                    }
+                    Err(e) if e.is_recoverable() => {
+                        log::error!("Background reader: skipping frame: {e}");
+                    }
+                    Err(e) => {
+                        log::error!("Background reader: {e}");
                         break;
                     }
                 }
             }
+            correlation.close();
             log::info!("Background reader: thread exiting");
         });
 
-        Self {
-            events: event_rx,
-            responses: response_rx,
-            stream: shutdown_stream,
-        }
-    }
-
-    fn read_message(stream: &mut Stream) -> Result<Message, std::io::Error> {
-        let mut size_buffer = [0u8; std::mem::size_of::<usize>()];
-        stream.read_exact(&mut size_buffer)?;
-        let message_size = usize::from_le_bytes(size_buffer);
-
-        let mut message_buffer = vec![0u8; message_size];
-        stream.read_exact(&mut message_buffer)?;
-        let buffer_str =
-            String::from_utf8(message_buffer).expect("Failed to convert buffer to string");
-        let deserialized_message: Message = serde_json::from_str(&buffer_str)?;
-        Ok(deserialized_message)
+        shutdown_stream
     }
 }
 
@@ -378,6 +734,11 @@ impl EventSocket {
         let (_tx, dummy_rx) = mpsc::channel();
         std::mem::replace(&mut self.responses, dummy_rx)
     }
+
+    pub fn take_requests(&mut self) -> mpsc::Receiver<Request> {
+        let (_tx, dummy_rx) = mpsc::channel();
+        std::mem::replace(&mut self.requests, dummy_rx)
+    }
 }
 
 impl Drop for EventSocket {
@@ -389,22 +750,201 @@ impl Drop for EventSocket {
     }
 }
 
-fn build_pair(stream: Stream) -> std::io::Result<(SocketSender, EventSocket)> {
+/// Which end of the connection we are, only the server checks the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+impl Side {
+    fn hello(self, capabilities: &[Capability], options: &SocketOptions) -> Hello {
+        let mut hello = Hello::new(capabilities);
+        if self == Side::Client {
+            hello.auth_token = options.auth_token.clone();
+            hello.subscriptions = options.subscriptions.clone();
+        }
+        hello
+    }
+
+    fn expected_token(self, options: &SocketOptions) -> Option<&str> {
+        match self {
+            Side::Client => None,
+            Side::Server => options.auth_token.as_deref(),
+        }
+    }
+}
+
+/// Exchanges `Hello`s. Without a token both sides send first so neither waits
+/// on the other, with one the server only answers a client that presented it.
+/// The `Hello` is always JSON, the encodings the peer accepts are only known
+/// after it.
+fn handshake(
+    stream: &mut Stream,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+    side: Side,
+) -> std::io::Result<Hello> {
+    let hello = Message::Hello(side.hello(capabilities, options));
+    let expected_token = side.expected_token(options);
+    let send_hello = |stream: &mut Stream| {
+        frame::write_frame(
+            stream,
+            &hello,
+            Encoding::Json,
+            NO_REQUEST_ID,
+            options.max_frame_size,
+        )
+    };
+    if expected_token.is_none() {
+        send_hello(stream)?;
+    }
+
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let frame = frame::read_frame(stream, options.max_frame_size);
+    stream.set_read_timeout(None)?;
+
+    let peer = check_hello(hello_frame(frame)?.message, expected_token)?;
+    if expected_token.is_some() {
+        send_hello(stream)?;
+    }
+    Ok(peer)
+}
+
+/// The first frame of the handshake. Without the magic the peer isn't out of
+/// sync, it is an old one that doesn't send frame headers at all.
+fn hello_frame(frame: Result<frame::Frame, FrameError>) -> std::io::Result<frame::Frame> {
+    match frame {
+        Ok(frame) => Ok(frame),
+        Err(FrameError::BadMagic(_)) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            HandshakeError::MissingFrameHeader,
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Accepts the peer's first message if it is a compatible `Hello` carrying
+/// `expected_token`, if any.
+fn check_hello(message: Message, expected_token: Option<&str>) -> std::io::Result<Hello> {
+    let mut peer = match message {
+        Message::Hello(peer) => peer,
+        other => {
+            let first = format!("{other:?}");
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                HandshakeError::MissingHello(first),
+            ));
+        }
+    };
+    if let Some(expected) = expected_token {
+        let presented = peer.auth_token.take().unwrap_or_default();
+        if !tokens_match(presented.as_bytes(), expected.as_bytes()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                HandshakeError::Unauthenticated,
+            ));
+        }
+    }
+    peer.auth_token = None;
+    if peer.protocol_version != PROTOCOL_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            HandshakeError::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote: peer.protocol_version,
+            },
+        ));
+    }
+    log::info!(
+        "Handshake complete, protocol version {}, peer capabilities: {:?}",
+        peer.protocol_version,
+        peer.capabilities
+    );
+    Ok(peer)
+}
+
+/// Compares in constant time so the token can't be guessed byte by byte.
+/// Also used by the shell's other local endpoints.
+pub fn tokens_match(presented: &[u8], expected: &[u8]) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn open_recorder(options: &SocketOptions) -> std::io::Result<Option<Arc<Recorder>>> {
+    options
+        .record_path
+        .as_deref()
+        .map(|path| Recorder::create(path).map(Arc::new))
+        .transpose()
+}
+
+fn build_pair(
+    stream: Stream,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+    side: Side,
+) -> std::io::Result<(SocketSender, EventSocket)> {
+    let (sender, read_half) = handshake_pair(stream, capabilities, options, side)?;
+    Ok((sender, EventSocket::new(read_half)))
+}
+
+/// Exchanges `Hello`s and splits the connection, the caller decides where
+/// incoming messages go.
+fn handshake_pair(
+    mut stream: Stream,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+    side: Side,
+) -> std::io::Result<(SocketSender, ReadHalf)> {
+    let peer = handshake(&mut stream, capabilities, options, side).inspect_err(|e| {
+        log::error!("Socket handshake failed: {e}");
+    })?;
+
     let write_stream = stream.try_clone()?;
     let read_stream = stream.try_clone()?;
     let shutdown_stream = stream;
 
+    let correlation = Arc::new(Correlation::new());
+    let recorder = open_recorder(options)?;
     let sender = SocketSender {
         stream: Arc::new(Mutex::new(write_stream)),
+        peer: Arc::new(peer),
+        max_frame_size: options.max_frame_size,
+        correlation: correlation.clone(),
+        recorder: recorder.clone(),
+    };
+
+    let read_half = ReadHalf {
+        read_stream,
+        shutdown_stream,
+        max_frame_size: options.max_frame_size,
+        correlation,
+        recorder,
     };
 
-    let event_socket = EventSocket::new(read_stream, shutdown_stream);
+    Ok((sender, read_half))
+}
 
-    Ok((sender, event_socket))
+/// Connect to an existing socket (client side) and exchange `Hello`s,
+/// advertising `capabilities` to the peer.
+pub fn connect(
+    socket_path: &str,
+    capabilities: &[Capability],
+) -> Result<(SocketSender, EventSocket), std::io::Error> {
+    connect_with_options(socket_path, capabilities, SocketOptions::default())
 }
 
-/// Connect to an existing socket (client side).
-pub fn connect(socket_path: &str) -> Result<(SocketSender, EventSocket), std::io::Error> {
+/// Like `connect`, with non-default `options`.
+pub fn connect_with_options(
+    socket_path: &str,
+    capabilities: &[Capability],
+    options: SocketOptions,
+) -> Result<(SocketSender, EventSocket), std::io::Error> {
     log::info!("Connecting to socket at {socket_path}");
     #[cfg(unix)]
     let stream = {
@@ -422,45 +962,51 @@ pub fn connect(socket_path: &str) -> Result<(SocketSender, EventSocket), std::io
         stream
     };
 
-    build_pair(stream)
+    build_pair(stream, capabilities, &options, Side::Client)
+}
+
+/// Create a socket, wait for a client to connect (server side) and exchange
+/// `Hello`s, advertising `capabilities` to the peer.
+pub fn listen(
+    socket_path: &str,
+    capabilities: &[Capability],
+) -> Result<(SocketSender, EventSocket), std::io::Error> {
+    listen_with_options(socket_path, capabilities, SocketOptions::default())
 }
 
-/// Create a socket and wait for a client to connect (server side).
-pub fn listen(socket_path: &str) -> Result<(SocketSender, EventSocket), std::io::Error> {
+/// Like `listen`, with non-default `options`.
+pub fn listen_with_options(
+    socket_path: &str,
+    capabilities: &[Capability],
+    options: SocketOptions,
+) -> Result<(SocketSender, EventSocket), std::io::Error> {
+    let (listener, times) = bind(socket_path)?;
+    let (sender, read_half) = wait_for_client(&listener, times, capabilities, &options)?;
+    Ok((sender, EventSocket::new(read_half)))
+}
+
+#[cfg(unix)]
+type Listener = UnixListener;
+#[cfg(windows)]
+type Listener = TcpListener;
+
+/// Creates the socket, returns a non-blocking listener and how many seconds
+/// to wait for the first client.
+fn bind(socket_path: &str) -> std::io::Result<(Listener, u32)> {
     log::info!("Creating socket at {socket_path}");
     #[cfg(unix)]
-    let stream = {
+    let (listener, times) = {
         if Path::new(socket_path).exists() {
             fs::remove_file(socket_path)?;
         }
 
         let listener = UnixListener::bind(socket_path)?;
-        listener.set_nonblocking(true)?;
-        let mut stream = None;
-        let times = if cfg!(debug_assertions) { 100 } else { 10 };
-        for i in 0..times {
-            log::info!("Waiting for client {i}/{times}");
-            match listener.accept() {
-                Ok((s, _)) => {
-                    stream = Some(s);
-                    break;
-                }
-                Err(_) => {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                }
-            }
-        }
-        let stream = stream.ok_or_else(|| {
-            std::io::Error::other("Client did not connect after multiple attempts")
-        })?;
-        stream.set_nonblocking(false)?;
-        log::info!("Client connected");
-        stream.set_read_timeout(None)?;
-        stream
+        restrict_to_owner(socket_path)?;
+        (listener, if cfg!(debug_assertions) { 100 } else { 10 })
     };
 
     #[cfg(windows)]
-    let stream = {
+    let (listener, times) = {
         if Path::new(socket_path).exists() {
             fs::remove_file(socket_path)?;
         }
@@ -499,31 +1045,61 @@ pub fn listen(socket_path: &str) -> Result<(SocketSender, EventSocket), std::io:
         fs::write(socket_path, port.to_string())?;
 
         log::info!("Listening on port {port}, waiting for client");
-        listener.set_nonblocking(true)?;
-        let mut stream = None;
-        let times = if cfg!(debug_assertions) { 100 } else { 50 };
-        for i in 0..times {
-            log::info!("Waiting for client {i}/{times}");
-            match listener.accept() {
-                Ok((s, _)) => {
-                    stream = Some(s);
-                    break;
-                }
-                Err(_) => {
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                }
-            }
-        }
-        let stream = stream.ok_or_else(|| {
-            std::io::Error::other("Client did not connect after multiple attempts")
-        })?;
-        stream.set_nonblocking(false)?;
-        log::info!("Client connected");
-        stream.set_read_timeout(None)?;
-        stream
+        (listener, if cfg!(debug_assertions) { 100 } else { 50 })
+    };
+
+    listener.set_nonblocking(true)?;
+    Ok((listener, times))
+}
+
+/// Returns the next client waiting on `listener`, if any.
+fn accept_pending(listener: &Listener) -> std::io::Result<Option<Stream>> {
+    let Ok((stream, _)) = listener.accept() else {
+        return Ok(None);
     };
+    stream.set_nonblocking(false)?;
+    log::info!("Client connected");
+    stream.set_read_timeout(None)?;
+    Ok(Some(stream))
+}
+
+/// Waits up to `times` seconds for a client that completes the handshake.
+fn wait_for_client(
+    listener: &Listener,
+    times: u32,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+) -> std::io::Result<(SocketSender, ReadHalf)> {
+    for i in 0..times {
+        log::info!("Waiting for client {i}/{times}");
+        let Some(stream) = accept_pending(listener)? else {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            continue;
+        };
+        /* Keep waiting for the real client if someone else got here first. */
+        match handshake_pair(stream, capabilities, options, Side::Server) {
+            Ok(pair) => return Ok(pair),
+            Err(e) if is_unauthenticated(&e) => log::warn!("Rejected client: {e}"),
+            Err(e) => log::error!("Client handshake failed, still waiting: {e}"),
+        }
+    }
+    Err(std::io::Error::other(
+        "Client did not connect after multiple attempts",
+    ))
+}
 
-    build_pair(stream)
+/// Makes the socket file accessible to our user only.
+#[cfg(unix)]
+fn restrict_to_owner(socket_path: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))
+}
+
+fn is_unauthenticated(error: &std::io::Error) -> bool {
+    error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<HandshakeError>())
+        .is_some_and(|e| *e == HandshakeError::Unauthenticated)
 }
 
 #[cfg(windows)]
@@ -544,18 +1120,24 @@ fn socket_path_to_port(socket_path: &str) -> u16 {
 }
 
 #[cfg(windows)]
-fn calculate_port_from_hash(socket_path: &str) -> u16 {
+fn hash_socket_path(socket_path: &str) -> u32 {
     let mut hash: u32 = 5381;
     for byte in socket_path.bytes() {
         hash = ((hash << 5).wrapping_add(hash)).wrapping_add(byte as u32);
     }
+    hash
+}
+
+#[cfg(windows)]
+fn calculate_port_from_hash(socket_path: &str) -> u16 {
     // Use ports in range 49152-65535 (dynamic/private range)
-    (hash % 15900 + 49152) as u16
+    (hash_socket_path(socket_path) % 15900 + 49152) as u16
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Write;
     use std::time::Duration;
 
     #[cfg(unix)]
@@ -565,18 +1147,295 @@ mod tests {
         let socket_path_str = socket_path.to_str().unwrap().to_string();
         let socket_path_str2 = socket_path_str.clone();
 
-        let server_handle = std::thread::spawn(move || listen(&socket_path_str).unwrap());
+        let server_handle = std::thread::spawn(move || {
+            listen(&socket_path_str, &[Capability::NoiseCancellation]).unwrap()
+        });
 
         // Small delay to let server start listening
         std::thread::sleep(Duration::from_millis(100));
 
-        let client = connect(&socket_path_str2).unwrap();
+        let client = connect(&socket_path_str2, &[Capability::Camera]).unwrap();
         let server = server_handle.join().unwrap();
         // Keep tempdir alive by leaking it (tests are short-lived)
         std::mem::forget(dir);
         (server, client)
     }
 
+    /// Lets `peer` act as a raw client that doesn't go through `connect`.
+    /// Returns the result of the listener's side of the handshake.
+    #[cfg(unix)]
+    fn handshake_with_raw_peer(
+        peer: impl FnOnce(UnixStream) + Send + 'static,
+    ) -> std::io::Result<(SocketSender, ReadHalf)> {
+        let (server, client) = UnixStream::pair().unwrap();
+        let peer_handle = std::thread::spawn(move || peer(client));
+        let result = handshake_pair(server, &[], &SocketOptions::default(), Side::Server);
+        peer_handle.join().unwrap();
+        result
+    }
+
+    #[cfg(unix)]
+    fn write_raw(stream: &mut UnixStream, message: &Message) {
+        frame::write_frame(
+            stream,
+            message,
+            Encoding::Json,
+            NO_REQUEST_ID,
+            frame::DEFAULT_MAX_FRAME_SIZE,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_handshake_exchanges_capabilities() {
+        let ((server_sender, _server_events), (client_sender, _client_events)) = test_pair();
+
+        assert_eq!(server_sender.peer(), &Hello::new(&[Capability::Camera]));
+        assert!(client_sender.peer_supports(Capability::NoiseCancellation));
+        assert!(!client_sender.peer_supports(Capability::WindowPicking));
+    }
+
+    #[test]
+    fn test_handshake_version_mismatch() {
+        let result = handshake_with_raw_peer(|mut stream| {
+            let hello = Hello {
+                protocol_version: PROTOCOL_VERSION + 1,
+                ..Hello::new(&[])
+            };
+            write_raw(&mut stream, &Message::Hello(hello));
+            let _ = frame::read_frame(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE);
+        });
+
+        let error = result.err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+        let reason = error.get_ref().unwrap().downcast_ref::<HandshakeError>();
+        assert_eq!(
+            reason,
+            Some(&HandshakeError::VersionMismatch {
+                local: PROTOCOL_VERSION,
+                remote: PROTOCOL_VERSION + 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_handshake_peer_without_hello() {
+        let result = handshake_with_raw_peer(|mut stream| {
+            write_raw(&mut stream, &Message::Ping);
+            let _ = frame::read_frame(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE);
+        });
+
+        let error = result.err().unwrap();
+        let reason = error.get_ref().unwrap().downcast_ref::<HandshakeError>();
+        assert!(matches!(reason, Some(HandshakeError::MissingHello(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handshake_peer_without_frame_header() {
+        /* Before the frame header every message was prefixed with its usize length. */
+        let result = handshake_with_raw_peer(|mut stream| {
+            let payload = br#""GetAvailableContent""#;
+            stream.write_all(&payload.len().to_le_bytes()).unwrap();
+            stream.write_all(payload).unwrap();
+            let _ = frame::read_frame(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE);
+        });
+
+        let error = result.err().unwrap();
+        let reason = error.get_ref().unwrap().downcast_ref::<HandshakeError>();
+        assert_eq!(reason, Some(&HandshakeError::MissingFrameHeader));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_listener_keeps_waiting_after_failed_handshake() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("test.sock");
+        let socket_path_str = socket_path.to_str().unwrap().to_string();
+
+        let server_path = socket_path_str.clone();
+        let server_handle = std::thread::spawn(move || listen(&server_path, &[]).unwrap());
+        std::thread::sleep(Duration::from_millis(100));
+
+        let mut stray = UnixStream::connect(&socket_path).unwrap();
+        write_raw(&mut stray, &Message::Ping);
+        let _ = frame::read_frame(&mut stray, frame::DEFAULT_MAX_FRAME_SIZE);
+
+        let (client_sender, _client_events) = connect(&socket_path_str, &[]).unwrap();
+        let (_server_sender, server_events) = server_handle.join().unwrap();
+        client_sender.send(Message::Ping).unwrap();
+        let msg = server_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(msg, Message::Ping));
+    }
+
+    #[cfg(unix)]
+    fn with_token(token: &str) -> SocketOptions {
+        SocketOptions {
+            auth_token: Some(token.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_listener_only_accepts_client_with_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("test.sock");
+        let socket_path_str = socket_path.to_str().unwrap().to_string();
+
+        let server_path = socket_path_str.clone();
+        let server_handle = std::thread::spawn(move || {
+            listen_with_options(&server_path, &[], with_token("secret")).unwrap()
+        });
+        std::thread::sleep(Duration::from_millis(100));
+
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&socket_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        /* Neither gets the listener's Hello, the listener keeps waiting. */
+        let error = connect(&socket_path_str, &[]).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(connect_with_options(&socket_path_str, &[], with_token("guess")).is_err());
+
+        let (client_sender, _client_events) =
+            connect_with_options(&socket_path_str, &[], with_token("secret")).unwrap();
+        let (server_sender, server_events) = server_handle.join().unwrap();
+        assert_eq!(server_sender.peer().auth_token, None);
+
+        client_sender.send(Message::Ping).unwrap();
+        let msg = server_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(msg, Message::Ping));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recording_captures_both_directions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("test.sock").to_str().unwrap().to_string();
+        let recording = dir.path().join("ipc.jsonl");
+
+        let server_path = socket_path.clone();
+        let server_handle = std::thread::spawn(move || listen(&server_path, &[]).unwrap());
+        std::thread::sleep(Duration::from_millis(100));
+        let options = SocketOptions {
+            auth_token: Some("secret".to_string()),
+            record_path: Some(recording.clone()),
+            ..Default::default()
+        };
+        let (client_sender, client_events) =
+            connect_with_options(&socket_path, &[], options).unwrap();
+        let (server_sender, server_events) = server_handle.join().unwrap();
+
+        client_sender.send(Message::ListCameras).unwrap();
+        server_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        server_sender.send(Message::CameraList(vec![])).unwrap();
+        client_events
+            .responses
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        client_sender
+            .send(Message::CallStart(CallStartMessage {
+                audio_token: "secret-audio".to_string(),
+                video_token: "secret-video".to_string(),
+                audio_device_name: "mic".to_string(),
+                start_mic_on_call: None,
+                start_camera_on_call: None,
+            }))
+            .unwrap();
+
+        let entries = record::read_recording(&recording).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].direction, record::Direction::Sent);
+        assert!(matches!(entries[0].message, Message::ListCameras));
+        assert_eq!(entries[1].direction, record::Direction::Received);
+        assert!(matches!(entries[1].message, Message::CameraList(_)));
+        assert!(matches!(
+            &entries[2].message,
+            Message::CallStart(call_start) if call_start.audio_token == record::REDACTED
+        ));
+        assert!(!fs::read_to_string(&recording).unwrap().contains("secret"));
+        assert_eq!(
+            fs::metadata(&recording).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_corrupt_frame_keeps_reader_alive() {
+        let (peer_tx, peer_rx) = mpsc::channel();
+        let (_sender, read_half) = handshake_with_raw_peer(move |mut stream| {
+            write_raw(&mut stream, &Message::Hello(Hello::new(&[])));
+            let _ = frame::read_frame(&mut stream, frame::DEFAULT_MAX_FRAME_SIZE);
+            peer_tx.send(stream).unwrap();
+        })
+        .unwrap();
+        let events = EventSocket::new(read_half);
+        let mut stream = peer_rx.recv().unwrap();
+
+        /* A JSON frame whose payload isn't even UTF-8. */
+        let mut corrupt = b"HOPP".to_vec();
+        corrupt.extend_from_slice(&[frame::FRAME_VERSION, 0]);
+        corrupt.extend_from_slice(&NO_REQUEST_ID.to_le_bytes());
+        corrupt.extend_from_slice(&4u32.to_le_bytes());
+        corrupt.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        stream.write_all(&corrupt).unwrap();
+        write_raw(&mut stream, &Message::Ping);
+
+        let msg = events.events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(msg, Message::Ping));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_high_rate_message_over_message_pack() {
+        let ((server_sender, _server_events), (client_sender, client_events)) = test_pair();
+        assert!(client_sender.peer_supports(Capability::MessagePack));
+
+        server_sender
+            .send(Message::MicrophoneAudioLevel(0.5))
+            .unwrap();
+        let msg = client_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(msg, Message::MicrophoneAudioLevel(level) if level == 0.5));
+    }
+
+    #[test]
+    fn test_unknown_capability_is_tolerated() {
+        let hello: Hello = serde_json::from_str(
+            r#"{"protocol_version":1,"capabilities":["Camera","TeleportCursor"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            hello.capabilities,
+            vec![Capability::Camera, Capability::Unknown]
+        );
+    }
+
+    /// Writes the JSON Schema of `Message` next to the TypeScript bindings
+    /// that ts-rs exports from its generated `export_bindings_*` tests.
+    #[cfg(feature = "bindings")]
+    #[test]
+    fn export_bindings_json_schema() {
+        let dir = std::env::var("TS_RS_EXPORT_DIR").unwrap_or_else(|_| "./bindings".to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let schema = serde_json::to_string_pretty(&schemars::schema_for!(Message)).unwrap();
+        fs::write(Path::new(&dir).join("Message.schema.json"), schema + "\n").unwrap();
+    }
+
     #[test]
     fn test_send_recv_event() {
         let ((_server_sender, server_events), (client_sender, _client_events)) = test_pair();
@@ -750,6 +1609,132 @@ mod tests {
         }
     }
 
+    fn camera(name: &str) -> CameraDevice {
+        CameraDevice {
+            name: name.to_string(),
+            id: name.to_string(),
+            default: false,
+        }
+    }
+
+    #[test]
+    fn test_concurrent_calls_get_their_own_response() {
+        let ((server_sender, server_events), (client_sender, _client_events)) = test_pair();
+
+        let cameras_caller = client_sender.clone();
+        let cameras = std::thread::spawn(move || {
+            cameras_caller.call(Message::ListCameras, Duration::from_secs(5))
+        });
+        let devices_caller = client_sender.clone();
+        let devices = std::thread::spawn(move || {
+            devices_caller.call(Message::ListAudioDevices, Duration::from_secs(5))
+        });
+
+        /* Answer both, in the reverse order of arrival. */
+        let first = server_events
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        let second = server_events
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        for request in [second, first] {
+            let response = match request.message {
+                Message::ListCameras => Message::CameraList(vec![camera("front")]),
+                Message::ListAudioDevices => Message::AudioDeviceList(vec![]),
+                other => panic!("unexpected request {other:?}"),
+            };
+            server_sender.respond(request.id, response).unwrap();
+        }
+
+        let cameras = cameras.join().unwrap().unwrap();
+        assert!(matches!(cameras, Message::CameraList(list) if list[0].name == "front"));
+        let devices = devices.join().unwrap().unwrap();
+        assert!(matches!(devices, Message::AudioDeviceList(_)));
+        assert!(server_events.responses.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_late_response_is_not_mistaken_for_the_next() {
+        let ((server_sender, server_events), (client_sender, client_events)) = test_pair();
+
+        let result = client_sender.call(Message::ListCameras, Duration::from_millis(100));
+        assert!(matches!(result, Err(CallError::Timeout)));
+
+        let server = std::thread::spawn(move || {
+            for name in ["stale", "fresh"] {
+                let request = server_events
+                    .requests
+                    .recv_timeout(Duration::from_secs(5))
+                    .unwrap();
+                assert!(matches!(request.message, Message::ListCameras));
+                server_sender
+                    .respond(request.id, Message::CameraList(vec![camera(name)]))
+                    .unwrap();
+            }
+        });
+
+        let response = client_sender
+            .call(Message::ListCameras, Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(response, Message::CameraList(list) if list[0].name == "fresh"));
+        assert!(client_events.responses.try_recv().is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_unanswered_request_does_not_take_the_next_response() {
+        let ((server_sender, server_events), (client_sender, client_events)) = test_pair();
+
+        /* The first request is never answered, only the second one. */
+        let forgotten = client_sender.clone();
+        let forgotten = std::thread::spawn(move || {
+            forgotten.call(Message::ListCameras, Duration::from_millis(500))
+        });
+        server_events
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        let answered = std::thread::spawn(move || {
+            client_sender.call(Message::ListCameras, Duration::from_secs(5))
+        });
+        let request = server_events
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        server_sender
+            .respond(request.id, Message::CameraList(vec![camera("second")]))
+            .unwrap();
+
+        let response = answered.join().unwrap().unwrap();
+        assert!(matches!(response, Message::CameraList(list) if list[0].name == "second"));
+        assert!(matches!(forgotten.join().unwrap(), Err(CallError::Timeout)));
+        assert!(client_events.responses.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_call_fails_when_peer_disconnects() {
+        let ((server_sender, server_events), (client_sender, client_events)) = test_pair();
+
+        let caller = std::thread::spawn(move || {
+            let result = client_sender.call(Message::ListCameras, Duration::from_secs(5));
+            drop(client_events);
+            result
+        });
+        server_events
+            .requests
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        drop(server_sender);
+        drop(server_events);
+
+        assert!(matches!(
+            caller.join().unwrap(),
+            Err(CallError::Disconnected)
+        ));
+    }
+
     #[test]
     fn test_request_response_pattern() {
         let ((server_sender, server_events), (_client_sender, _client_events)) = test_pair();
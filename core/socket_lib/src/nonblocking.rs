@@ -0,0 +1,524 @@
+//! Async counterpart of `connect`/`listen` for tokio applications.
+//!
+//! Incoming messages are a `Stream` instead of `mpsc` receivers fed by a
+//! polling thread, so they can be awaited in a `select!` next to other
+//! futures. The wire format is the same as the blocking API, on unix an async
+//! end can talk to a blocking one. On Windows the async API uses a named pipe,
+//! both ends have to use it.
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio_stream::Stream;
+
+use crate::frame::{
+    self, Encoding, Frame, FrameError, Header, RequestId, HEADER_SIZE, NO_REQUEST_ID,
+};
+use crate::record::{Direction, Recorder};
+use crate::rpc::{Correlation, Incoming};
+use crate::{
+    check_hello, encoding_for, hello_frame, is_unauthenticated, open_recorder, CallError,
+    Capability, Hello, Message, Request, Side, SocketOptions, HANDSHAKE_TIMEOUT,
+};
+
+type Reader = Box<dyn AsyncRead + Send + Unpin>;
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Send half of the async socket, cheap to clone.
+#[derive(Clone)]
+pub struct AsyncSocketSender {
+    writer: Arc<Mutex<Writer>>,
+    peer: Arc<Hello>,
+    max_frame_size: u32,
+    correlation: Arc<Correlation>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+/// Removes a call from the pending ones if its future is dropped before the
+/// response arrives.
+struct PendingCall<'a> {
+    correlation: &'a Correlation,
+    request_id: RequestId,
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        self.correlation.cancel(self.request_id);
+    }
+}
+
+impl AsyncSocketSender {
+    /// See `SocketSender::send`.
+    pub async fn send(&self, message: Message) -> std::io::Result<()> {
+        self.write(&message, NO_REQUEST_ID).await
+    }
+
+    /// See `SocketSender::respond`.
+    pub async fn respond(&self, request_id: RequestId, message: Message) -> std::io::Result<()> {
+        self.write(&message, request_id).await
+    }
+
+    /// See `SocketSender::call`. Dropping the future cancels the call, its
+    /// response is then dropped when it arrives.
+    pub async fn call(&self, message: Message, timeout: Duration) -> Result<Message, CallError> {
+        let (tx, response) = oneshot::channel();
+        let request_id = self
+            .correlation
+            .register(Box::new(move |message| tx.send(message)))?;
+        let _pending = PendingCall {
+            correlation: &self.correlation,
+            request_id,
+        };
+
+        self.write(&message, request_id)
+            .await
+            .map_err(CallError::Io)?;
+        match tokio::time::timeout(timeout, response).await {
+            Ok(Ok(message)) => Ok(message),
+            Ok(Err(_)) => Err(CallError::Disconnected),
+            Err(_) => Err(CallError::Timeout),
+        }
+    }
+
+    async fn write(&self, message: &Message, request_id: RequestId) -> std::io::Result<()> {
+        let frame = frame::encode(
+            message,
+            encoding_for(message, &self.peer),
+            request_id,
+            self.max_frame_size,
+        )?;
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Direction::Sent, message);
+        }
+        Ok(())
+    }
+
+    /// The `Hello` the peer sent during the handshake.
+    pub fn peer(&self) -> &Hello {
+        &self.peer
+    }
+
+    pub fn peer_supports(&self, capability: Capability) -> bool {
+        self.peer.supports(capability)
+    }
+}
+
+/// Messages from the peer, except responses to our own calls and requests.
+/// Ends when the connection closes, dropping it stops the reader task.
+pub struct EventStream {
+    messages: mpsc::UnboundedReceiver<Message>,
+    requests: mpsc::UnboundedReceiver<Request>,
+    reader: JoinHandle<()>,
+}
+
+impl EventStream {
+    /// The next request the peer made through `call`, `None` once the
+    /// connection closed. Answer it with `AsyncSocketSender::respond`.
+    pub async fn next_request(&mut self) -> Option<Request> {
+        self.requests.recv().await
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        self.messages.poll_recv(cx)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_frame_size: u32,
+) -> Result<Frame, FrameError> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header).await?;
+    let header = Header::parse(&header)?;
+
+    if let Some(error) = header.oversized(max_frame_size) {
+        let length = u64::from(header.length());
+        let skipped =
+            tokio::io::copy(&mut (&mut *reader).take(length), &mut tokio::io::sink()).await?;
+        if skipped < length {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        return Err(error);
+    }
+    let mut payload = vec![0u8; header.length() as usize];
+    reader.read_exact(&mut payload).await?;
+    header.decode(&payload)
+}
+
+/// See the blocking `handshake`.
+async fn handshake(
+    reader: &mut Reader,
+    writer: &mut Writer,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+    side: Side,
+) -> std::io::Result<Hello> {
+    let max_frame_size = options.max_frame_size;
+    let hello = Message::Hello(side.hello(capabilities, options));
+    let hello = frame::encode(&hello, Encoding::Json, NO_REQUEST_ID, max_frame_size)?;
+    let expected_token = side.expected_token(options);
+    if expected_token.is_none() {
+        writer.write_all(&hello).await?;
+        writer.flush().await?;
+    }
+
+    let frame = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_frame(reader, max_frame_size))
+        .await
+        .map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::TimedOut, "peer didn't send its Hello")
+        })?;
+    let peer = check_hello(hello_frame(frame)?.message, expected_token)?;
+    if expected_token.is_some() {
+        writer.write_all(&hello).await?;
+        writer.flush().await?;
+    }
+    Ok(peer)
+}
+
+async fn build_pair(
+    mut reader: Reader,
+    mut writer: Writer,
+    capabilities: &[Capability],
+    options: &SocketOptions,
+    side: Side,
+) -> std::io::Result<(AsyncSocketSender, EventStream)> {
+    let max_frame_size = options.max_frame_size;
+    let peer = handshake(&mut reader, &mut writer, capabilities, options, side)
+        .await
+        .inspect_err(|e| log::error!("Socket handshake failed: {e}"))?;
+
+    let correlation = Arc::new(Correlation::new());
+    let recorder = open_recorder(options)?;
+    let (tx, messages) = mpsc::unbounded_channel();
+    let (request_tx, requests) = mpsc::unbounded_channel();
+    let reader_correlation = correlation.clone();
+    let reader_recorder = recorder.clone();
+    let reader = tokio::spawn(async move {
+        loop {
+            match read_frame(&mut reader, max_frame_size).await {
+                Ok(frame) => {
+                    if let Some(recorder) = &reader_recorder {
+                        recorder.record(Direction::Received, &frame.message);
+                    }
+                    let sent = match reader_correlation.route(frame) {
+                        None => continue,
+                        Some(Incoming::Request(request)) => request_tx.send(request).map_err(drop),
+                        Some(Incoming::Message(message)) => tx.send(message).map_err(drop),
+                    };
+                    if sent.is_err() {
+                        log::info!("Async reader: stream dropped, stopping");
+                        break;
+                    }
+                }
+                Err(e) if e.is_recoverable() => {
+                    log::error!("Async reader: skipping frame: {e}");
+                }
+                Err(e) => {
+                    log::error!("Async reader: {e}");
+                    break;
+                }
+            }
+        }
+        reader_correlation.close();
+        log::info!("Async reader: task exiting");
+    });
+
+    let sender = AsyncSocketSender {
+        writer: Arc::new(Mutex::new(writer)),
+        peer: Arc::new(peer),
+        max_frame_size,
+        correlation,
+        recorder,
+    };
+    Ok((
+        sender,
+        EventStream {
+            messages,
+            requests,
+            reader,
+        },
+    ))
+}
+
+/// Async `connect`.
+pub async fn connect(
+    socket_path: &str,
+    capabilities: &[Capability],
+) -> std::io::Result<(AsyncSocketSender, EventStream)> {
+    connect_with_options(socket_path, capabilities, SocketOptions::default()).await
+}
+
+/// Async `connect_with_options`.
+pub async fn connect_with_options(
+    socket_path: &str,
+    capabilities: &[Capability],
+    options: SocketOptions,
+) -> std::io::Result<(AsyncSocketSender, EventStream)> {
+    log::info!("Connecting to socket at {socket_path}");
+    let (reader, writer) = platform::connect(socket_path).await?;
+    build_pair(reader, writer, capabilities, &options, Side::Client).await
+}
+
+/// Async `listen`.
+pub async fn listen(
+    socket_path: &str,
+    capabilities: &[Capability],
+) -> std::io::Result<(AsyncSocketSender, EventStream)> {
+    listen_with_options(socket_path, capabilities, SocketOptions::default()).await
+}
+
+/// Async `listen_with_options`.
+pub async fn listen_with_options(
+    socket_path: &str,
+    capabilities: &[Capability],
+    options: SocketOptions,
+) -> std::io::Result<(AsyncSocketSender, EventStream)> {
+    log::info!("Creating socket at {socket_path}");
+    let mut listener = platform::Listener::bind(socket_path)?;
+    let deadline = tokio::time::Instant::now() + platform::ACCEPT_TIMEOUT;
+    loop {
+        let (reader, writer) = tokio::time::timeout_at(deadline, listener.accept())
+            .await
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Client did not connect in time",
+                )
+            })??;
+        log::info!("Client connected");
+        /* Keep waiting for the real client if someone else got here first. */
+        match build_pair(reader, writer, capabilities, &options, Side::Server).await {
+            Err(e) if is_unauthenticated(&e) => log::warn!("Rejected client: {e}"),
+            result => return result,
+        }
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::{Reader, Writer};
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::net::{UnixListener, UnixStream};
+
+    pub(super) const ACCEPT_TIMEOUT: Duration =
+        Duration::from_secs(if cfg!(debug_assertions) { 100 } else { 10 });
+
+    pub(super) async fn connect(socket_path: &str) -> std::io::Result<(Reader, Writer)> {
+        let (reader, writer) = UnixStream::connect(socket_path).await?.into_split();
+        Ok((Box::new(reader), Box::new(writer)))
+    }
+
+    pub(super) struct Listener(UnixListener);
+
+    impl Listener {
+        pub(super) fn bind(socket_path: &str) -> std::io::Result<Self> {
+            if Path::new(socket_path).exists() {
+                std::fs::remove_file(socket_path)?;
+            }
+            let listener = UnixListener::bind(socket_path)?;
+            crate::restrict_to_owner(socket_path)?;
+            Ok(Self(listener))
+        }
+
+        pub(super) async fn accept(&mut self) -> std::io::Result<(Reader, Writer)> {
+            let (stream, _) = self.0.accept().await?;
+            let (reader, writer) = stream.into_split();
+            Ok((Box::new(reader), Box::new(writer)))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{Reader, Writer};
+    use std::time::Duration;
+    use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions};
+
+    pub(super) const ACCEPT_TIMEOUT: Duration =
+        Duration::from_secs(if cfg!(debug_assertions) { 100 } else { 50 });
+
+    /// All pipe instances are busy, retry shortly.
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    fn pipe_name(socket_path: &str) -> String {
+        format!(
+            r"\\.\pipe\hopp-{:08x}",
+            crate::hash_socket_path(socket_path)
+        )
+    }
+
+    pub(super) async fn connect(socket_path: &str) -> std::io::Result<(Reader, Writer)> {
+        let name = pipe_name(socket_path);
+        let client = loop {
+            match ClientOptions::new().open(&name) {
+                Ok(client) => break client,
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let (reader, writer) = tokio::io::split(client);
+        Ok((Box::new(reader), Box::new(writer)))
+    }
+
+    /// The pipe instance the next client connects to.
+    pub(super) struct Listener {
+        name: String,
+        next: NamedPipeServer,
+    }
+
+    impl Listener {
+        pub(super) fn bind(socket_path: &str) -> std::io::Result<Self> {
+            let name = pipe_name(socket_path);
+            /* Fails if another process already owns a pipe with this name. */
+            let next = ServerOptions::new()
+                .first_pipe_instance(true)
+                .reject_remote_clients(true)
+                .create(&name)?;
+            Ok(Self { name, next })
+        }
+
+        pub(super) async fn accept(&mut self) -> std::io::Result<(Reader, Writer)> {
+            self.next.connect().await?;
+            let next = ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(&self.name)?;
+            let connected = std::mem::replace(&mut self.next, next);
+            let (reader, writer) = tokio::io::split(connected);
+            Ok((Box::new(reader), Box::new(writer)))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    fn socket_path() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.sock").to_str().unwrap().to_string();
+        (dir, path)
+    }
+
+    #[tokio::test]
+    async fn test_async_pair_round_trip() {
+        let (_dir, path) = socket_path();
+        let server_path = path.clone();
+        let server =
+            tokio::spawn(async move { listen(&server_path, &[Capability::Camera]).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (client_sender, mut client_events) = connect(&path, &[]).await.unwrap();
+        let (server_sender, mut server_events) = server.await.unwrap();
+        assert!(client_sender.peer_supports(Capability::Camera));
+
+        client_sender
+            .send(Message::GetAvailableContent)
+            .await
+            .unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(5), server_events.next())
+            .await
+            .unwrap();
+        assert!(matches!(msg, Some(Message::GetAvailableContent)));
+
+        /* Answer the call from inside a select! on the event stream. */
+        let responder = tokio::spawn(async move {
+            tokio::select! {
+                Some(request) = server_events.next_request() => {
+                    assert!(matches!(request.message, Message::ListCameras));
+                    server_sender.respond(request.id, Message::CameraList(vec![])).await.unwrap();
+                }
+                _ = tokio::time::sleep(Duration::from_secs(5)) => panic!("no request"),
+            }
+            server_events
+        });
+        let response = client_sender
+            .call(Message::ListCameras, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(matches!(response, Message::CameraList(_)));
+
+        drop(responder.await.unwrap());
+        let end = tokio::time::timeout(Duration::from_secs(5), client_events.next())
+            .await
+            .unwrap();
+        assert!(end.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_async_end_talks_to_blocking_end() {
+        let (_dir, path) = socket_path();
+        let server_path = path.clone();
+        let server = tokio::spawn(async move { listen(&server_path, &[]).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (client_sender, client_events) =
+            tokio::task::spawn_blocking(move || crate::connect(&path, &[]).unwrap())
+                .await
+                .unwrap();
+        let (server_sender, mut server_events) = server.await.unwrap();
+
+        let call = tokio::task::spawn_blocking(move || {
+            let response = client_sender.call(Message::ListAudioDevices, Duration::from_secs(5));
+            (response, client_sender, client_events)
+        });
+        /* The blocking side's request reaches the async side. */
+        let request = tokio::time::timeout(Duration::from_secs(5), server_events.next_request())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(request.message, Message::ListAudioDevices));
+        server_sender
+            .respond(request.id, Message::AudioDeviceList(vec![]))
+            .await
+            .unwrap();
+
+        let (response, _client_sender, _client_events) = call.await.unwrap();
+        assert!(matches!(response, Ok(Message::AudioDeviceList(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_call_is_cancelled() {
+        let (_dir, path) = socket_path();
+        let server_path = path.clone();
+        let server = tokio::spawn(async move { listen(&server_path, &[]).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (client_sender, mut client_events) = connect(&path, &[]).await.unwrap();
+        let (server_sender, mut server_events) = server.await.unwrap();
+
+        let request = tokio::select! {
+            _ = client_sender.call(Message::ListCameras, Duration::from_secs(5)) => {
+                panic!("call shouldn't complete");
+            }
+            request = server_events.next_request() => request.unwrap(),
+        };
+        /* The late response neither resolves anything nor leaks into the stream. */
+        server_sender
+            .respond(request.id, Message::CameraList(vec![]))
+            .await
+            .unwrap();
+        server_sender.send(Message::Ping).await.unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(5), client_events.next())
+            .await
+            .unwrap();
+        assert!(matches!(msg, Some(Message::Ping)));
+    }
+}
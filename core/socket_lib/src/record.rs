@@ -0,0 +1,132 @@
+//! Opt-in recording of a connection's traffic, for reproducing field reports.
+//!
+//! With `SocketOptions::record_path` set, every message sent or received after
+//! the handshake is appended to that file as one JSON object per line:
+//!
+//! ```text
+//! {"timestamp_ms":1718000000000,"elapsed_ms":12,"direction":"Sent","message":"ListCameras"}
+//! ```
+//!
+//! `timestamp_ms` is wall-clock time since the unix epoch, `elapsed_ms` is
+//! time since the connection was established and is what replay paces by.
+//! `direction` is relative to the recording side. The `Hello`s, and with them
+//! the auth token, are never recorded. The LiveKit tokens of a `CallStart` are
+//! replaced with `REDACTED`, so replaying a call needs fresh ones. The file is
+//! only readable by its owner.
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Message;
+
+/// Stands in for secrets in a recording.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// One line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp_ms: u64,
+    pub elapsed_ms: u64,
+    pub direction: Direction,
+    pub message: Message,
+}
+
+/// Borrowing twin of `Entry`, so recording doesn't clone every message.
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    timestamp_ms: u64,
+    elapsed_ms: u64,
+    direction: Direction,
+    message: &'a Message,
+}
+
+/// Appends a connection's messages to a JSONL file. Shared by the sender and
+/// the reader of the connection.
+pub(crate) struct Recorder {
+    start: Instant,
+    file: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    pub(crate) fn create(path: &Path) -> std::io::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(path)?;
+        log::info!("Recording socket traffic to {}", path.display());
+        Ok(Self {
+            start: Instant::now(),
+            file: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Writes `message` as the next line. A failing recording never fails
+    /// the connection, the error is only logged.
+    pub(crate) fn record(&self, direction: Direction, message: &Message) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let message = redact(message);
+        let entry = EntryRef {
+            timestamp_ms,
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            direction,
+            message: &message,
+        };
+        let mut file = self.file.lock().unwrap();
+        /* Flush per line so a crash doesn't lose the messages leading to it. */
+        let result = serde_json::to_writer(&mut *file, &entry)
+            .map_err(std::io::Error::from)
+            .and_then(|()| file.write_all(b"\n"))
+            .and_then(|()| file.flush());
+        if let Err(e) = result {
+            log::error!("Failed to record {direction:?} message: {e}");
+        }
+    }
+}
+
+/// `message` without the secrets it carries.
+fn redact(message: &Message) -> Cow<'_, Message> {
+    match message {
+        Message::CallStart(call_start) => {
+            let mut call_start = call_start.clone();
+            call_start.audio_token = REDACTED.to_string();
+            call_start.video_token = REDACTED.to_string();
+            Cow::Owned(Message::CallStart(call_start))
+        }
+        _ => Cow::Borrowed(message),
+    }
+}
+
+/// Reads a recording back. Lines that don't parse, e.g. a line cut short by a
+/// crash or a message this version doesn't know, are logged and skipped.
+pub fn read_recording(path: &Path) -> std::io::Result<Vec<Entry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!("Skipping line {} of {}: {e}", index + 1, path.display()),
+        }
+    }
+    Ok(entries)
+}
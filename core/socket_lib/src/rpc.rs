@@ -0,0 +1,136 @@
+//! Request/response correlation for `SocketSender::call`.
+//!
+//! A call sends its request with a fresh request id and waits for the frame
+//! carrying the same id. The answering side gets the request together with
+//! its id as a `Request`, and its handler echoes the id with `respond`.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Mutex;
+
+use crate::frame::{Frame, RequestId, NO_REQUEST_ID};
+use crate::Message;
+
+#[derive(Debug)]
+pub enum CallError {
+    /// Sending the request failed.
+    Io(std::io::Error),
+    /// No response arrived in time, a late one is dropped.
+    Timeout,
+    /// The connection closed before the response arrived.
+    Disconnected,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Io(e) => write!(f, "failed to send request: {e}"),
+            CallError::Timeout => write!(f, "timed out waiting for the response"),
+            CallError::Disconnected => write!(f, "connection closed before the response"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// A request the peer made through `call`, answer it by passing `id` to
+/// `respond` on the sender.
+#[derive(Debug)]
+pub struct Request {
+    pub id: RequestId,
+    pub message: Message,
+}
+
+/// An incoming frame that wasn't a response to one of our calls.
+pub(crate) enum Incoming {
+    /// Has to be answered with the request's id.
+    Request(Request),
+    /// Events, and responses sent without a request id.
+    Message(Message),
+}
+
+/// Delivers a response to a waiting call, gives the message back if the call
+/// is gone. Dropping it without calling tells the call the connection closed.
+pub(crate) type Waiter = Box<dyn FnOnce(Message) -> Result<(), Message> + Send>;
+
+/// State shared by the sender and the reader of one connection.
+#[derive(Default)]
+pub(crate) struct Correlation {
+    next_id: AtomicU16,
+    /// Our calls waiting for a response, `None` once the reader exited.
+    pending: Mutex<Option<HashMap<RequestId, Waiter>>>,
+}
+
+impl Correlation {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Mutex::new(Some(HashMap::new())),
+            ..Default::default()
+        }
+    }
+
+    /// Registers a call whose response is handed to `waiter`.
+    pub(crate) fn register(&self, waiter: Waiter) -> Result<RequestId, CallError> {
+        let mut id = self.next_id.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        if id == NO_REQUEST_ID {
+            id = self.next_id.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        }
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => {
+                pending.insert(id, waiter);
+                Ok(id)
+            }
+            None => Err(CallError::Disconnected),
+        }
+    }
+
+    pub(crate) fn cancel(&self, id: RequestId) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&id);
+        }
+    }
+
+    /// Hands a response to the call waiting for it. Returns the message back
+    /// if no call is waiting, e.g. because it timed out.
+    fn resolve(&self, id: RequestId, message: Message) -> Result<(), Message> {
+        let waiter = self
+            .pending
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|pending| pending.remove(&id));
+        match waiter {
+            Some(waiter) => waiter(message),
+            None => Err(message),
+        }
+    }
+
+    /// Fails all waiting calls with `CallError::Disconnected`.
+    pub(crate) fn close(&self) {
+        self.pending.lock().unwrap().take();
+    }
+
+    /// Handles an incoming frame. Responses to our calls go to the caller,
+    /// everything else is returned for the receiver.
+    pub(crate) fn route(&self, frame: Frame) -> Option<Incoming> {
+        let Frame {
+            request_id,
+            message,
+        } = frame;
+        if request_id == NO_REQUEST_ID {
+            return Some(Incoming::Message(message));
+        }
+        if message.is_response() {
+            if let Err(message) = self.resolve(request_id, message) {
+                log::warn!(
+                    "Dropping response to request {request_id}, nobody is waiting: {message:?}"
+                );
+            }
+            return None;
+        }
+        Some(Incoming::Request(Request {
+            id: request_id,
+            message,
+        }))
+    }
+}
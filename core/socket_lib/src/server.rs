@@ -0,0 +1,412 @@
+//! Listening end shared by several clients, e.g. the shell plus `hoppctl`.
+//!
+//! The first client to connect owns the session: calls go to it, and when it
+//! disconnects the server closes and drops the others. Later clients can come
+//! and go. Messages from all clients arrive on one channel, each with the
+//! `ReplyTo` that `respond` sends its response back with. Anything else is
+//! broadcast to the clients subscribed to its `Topic`.
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use crate::frame::{RequestId, NO_REQUEST_ID};
+use crate::rpc::Incoming;
+use crate::{
+    accept_pending, bind, handshake_pair, is_unauthenticated, wait_for_client, CallError,
+    Capability, Listener, Message, ReadHalf, Side, SocketOptions, SocketSender,
+};
+
+/// How often the accept thread looks for new clients.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub type ClientId = u64;
+
+/// Who sent a message and the id of their request, if it is one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplyTo {
+    client: ClientId,
+    request_id: RequestId,
+}
+
+/// A message from one of the clients.
+#[derive(Debug)]
+pub struct ClientMessage {
+    pub reply_to: ReplyTo,
+    pub message: Message,
+}
+
+struct Client {
+    id: ClientId,
+    sender: SocketSender,
+}
+
+#[derive(Default)]
+struct Shared {
+    next_id: AtomicU64,
+    /// Connected clients, the owner first.
+    clients: Mutex<Vec<Client>>,
+    closed: AtomicBool,
+}
+
+impl Shared {
+    fn add(
+        self: &Arc<Self>,
+        sender: SocketSender,
+        read_half: ReadHalf,
+        tx: mpsc::Sender<ClientMessage>,
+    ) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut clients = self.clients.lock().unwrap();
+            if self.closed.load(Ordering::Relaxed) {
+                return;
+            }
+            clients.push(Client { id, sender });
+        }
+        log::info!("Server: client {id} joined");
+
+        /* One channel for requests and events, so they keep the client's order. */
+        let (client_tx, client_rx) = mpsc::channel();
+        /* Closing goes through `Client::sender`, the returned handle isn't needed. */
+        drop(read_half.spawn(Box::new(move |incoming| client_tx.send(incoming).is_ok())));
+        let shared = self.clone();
+        std::thread::spawn(move || {
+            for incoming in client_rx.iter() {
+                let (request_id, message) = match incoming {
+                    Incoming::Request(request) => (request.id, request.message),
+                    Incoming::Message(message) => (NO_REQUEST_ID, message),
+                };
+                let reply_to = ReplyTo {
+                    client: id,
+                    request_id,
+                };
+                if tx.send(ClientMessage { reply_to, message }).is_err() {
+                    break;
+                }
+            }
+            shared.remove(id);
+        });
+    }
+
+    fn remove(&self, id: ClientId) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.first().is_some_and(|owner| owner.id == id) {
+            log::info!("Server: owner disconnected, closing");
+            self.closed.store(true, Ordering::Relaxed);
+            for client in clients.drain(..) {
+                client.sender.shutdown();
+            }
+        } else {
+            log::info!("Server: client {id} left");
+            clients.retain(|client| client.id != id);
+        }
+    }
+
+    fn owner(&self) -> Option<SocketSender> {
+        let clients = self.clients.lock().unwrap();
+        clients.first().map(|owner| owner.sender.clone())
+    }
+
+    fn client(&self, id: ClientId) -> Option<SocketSender> {
+        let clients = self.clients.lock().unwrap();
+        clients
+            .iter()
+            .find(|client| client.id == id)
+            .map(|client| client.sender.clone())
+    }
+}
+
+/// Send half of the server, cheap to clone.
+#[derive(Clone)]
+pub struct ServerSender {
+    shared: Arc<Shared>,
+}
+
+impl ServerSender {
+    /// Broadcasts `message` to the subscribed clients, answer requests with
+    /// `respond` instead. Only fails if sending to the owner does, other
+    /// clients are dropped when their connection breaks.
+    pub fn send(&self, message: Message) -> Result<(), std::io::Error> {
+        let clients: Vec<(ClientId, SocketSender)> = {
+            let clients = self.shared.clients.lock().unwrap();
+            clients
+                .iter()
+                .filter(|client| client.sender.peer().is_subscribed(&message))
+                .map(|client| (client.id, client.sender.clone()))
+                .collect()
+        };
+        let owner = self.shared.clients.lock().unwrap().first().map(|c| c.id);
+        let mut result = Ok(());
+        for (id, sender) in clients {
+            if let Err(e) = sender.send(message.clone()) {
+                if Some(id) == owner {
+                    result = Err(e);
+                } else {
+                    log::warn!("Server: failed to send to client {id}: {e}");
+                }
+            }
+        }
+        result
+    }
+
+    /// Sends `message` only to the client of `reply_to`, as the response to
+    /// its request.
+    pub fn respond(&self, reply_to: ReplyTo, message: Message) -> Result<(), std::io::Error> {
+        match self.shared.client(reply_to.client) {
+            Some(sender) => sender.respond(reply_to.request_id, message),
+            None => {
+                log::warn!("Server: dropping {message:?}, the client that asked is gone");
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends the request `message` to the owner and waits for its response,
+    /// see `SocketSender::call`.
+    pub fn call(&self, message: Message, timeout: Duration) -> Result<Message, CallError> {
+        match self.shared.owner() {
+            Some(owner) => owner.call(message, timeout),
+            None => Err(CallError::Disconnected),
+        }
+    }
+
+    /// Number of connected clients, the owner included.
+    pub fn client_count(&self) -> usize {
+        self.shared.clients.lock().unwrap().len()
+    }
+}
+
+/// Creates the socket and waits for the owner like `listen_with_options`,
+/// then keeps accepting clients in the background. The receiver gets the
+/// messages of all clients and closes once the owner disconnects.
+pub fn serve(
+    socket_path: &str,
+    capabilities: &[Capability],
+    options: SocketOptions,
+) -> std::io::Result<(ServerSender, mpsc::Receiver<ClientMessage>)> {
+    let (listener, times) = bind(socket_path)?;
+    let (owner, owner_reader) = wait_for_client(&listener, times, capabilities, &options)?;
+
+    let shared = Arc::new(Shared::default());
+    let (tx, rx) = mpsc::channel();
+    shared.add(owner, owner_reader, tx.clone());
+
+    let accept_shared = shared.clone();
+    let capabilities = capabilities.to_vec();
+    std::thread::spawn(move || accept_loop(listener, accept_shared, capabilities, options, tx));
+
+    Ok((ServerSender { shared }, rx))
+}
+
+fn accept_loop(
+    listener: Listener,
+    shared: Arc<Shared>,
+    capabilities: Vec<Capability>,
+    options: SocketOptions,
+    tx: mpsc::Sender<ClientMessage>,
+) {
+    while !shared.closed.load(Ordering::Relaxed) {
+        let stream = match accept_pending(&listener) {
+            Ok(Some(stream)) => stream,
+            Ok(None) => {
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                log::error!("Server: failed to accept client: {e}");
+                continue;
+            }
+        };
+        /* Handshake off the accept thread so a silent client can't stall it. */
+        let shared = shared.clone();
+        let capabilities = capabilities.clone();
+        let options = options.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            match handshake_pair(stream, &capabilities, &options, Side::Server) {
+                Ok((sender, read_half)) => shared.add(sender, read_half, tx),
+                Err(e) if is_unauthenticated(&e) => log::warn!("Server: rejected client: {e}"),
+                Err(e) => log::error!("Server: client handshake failed: {e}"),
+            }
+        });
+    }
+    log::info!("Server: accept thread exiting");
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{connect, connect_with_options, CameraDevice, EventSocket, Topic};
+
+    fn socket_path() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.sock").to_str().unwrap().to_string();
+        (dir, path)
+    }
+
+    fn serve_with_owner(
+        socket_path: &str,
+    ) -> (
+        (ServerSender, mpsc::Receiver<ClientMessage>),
+        (SocketSender, EventSocket),
+    ) {
+        let path = socket_path.to_string();
+        let server = std::thread::spawn(move || serve(&path, &[], SocketOptions::default()));
+        std::thread::sleep(Duration::from_millis(100));
+        let owner = connect(socket_path, &[]).unwrap();
+        (server.join().unwrap().unwrap(), owner)
+    }
+
+    fn camera(name: &str) -> CameraDevice {
+        CameraDevice {
+            name: name.to_string(),
+            id: name.to_string(),
+            default: true,
+        }
+    }
+
+    fn join(
+        socket_path: &str,
+        server: &ServerSender,
+        options: SocketOptions,
+    ) -> (SocketSender, EventSocket) {
+        let count = server.client_count();
+        let client = connect_with_options(socket_path, &[], options).unwrap();
+        while server.client_count() == count {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        client
+    }
+
+    #[test]
+    fn test_response_goes_to_the_client_that_asked() {
+        let (_dir, path) = socket_path();
+        let ((server, requests), (_owner, owner_events)) = serve_with_owner(&path);
+        let (cli, cli_events) = join(&path, &server, SocketOptions::default());
+
+        let asked =
+            std::thread::spawn(move || cli.call(Message::ListCameras, Duration::from_secs(5)));
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(request.message, Message::ListCameras));
+        server
+            .respond(request.reply_to, Message::CameraList(vec![camera("cam")]))
+            .unwrap();
+
+        let response = asked.join().unwrap().unwrap();
+        assert!(matches!(response, Message::CameraList(list) if list.len() == 1));
+        assert!(owner_events
+            .responses
+            .recv_timeout(Duration::from_millis(200))
+            .is_err());
+        drop(cli_events);
+    }
+
+    #[test]
+    fn test_unanswered_request_does_not_take_another_clients_response() {
+        let (_dir, path) = socket_path();
+        let ((server, requests), (owner, owner_events)) = serve_with_owner(&path);
+        let (cli, _cli_events) = join(&path, &server, SocketOptions::default());
+
+        /* The owner's request is never answered, the later one from cli is. */
+        let forgotten = std::thread::spawn(move || {
+            owner.call(Message::ListCameras, Duration::from_millis(500))
+        });
+        requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        let asked =
+            std::thread::spawn(move || cli.call(Message::ListCameras, Duration::from_secs(5)));
+        let request = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        server
+            .respond(request.reply_to, Message::CameraList(vec![camera("cli")]))
+            .unwrap();
+
+        let response = asked.join().unwrap().unwrap();
+        assert!(matches!(response, Message::CameraList(list) if list[0].name == "cli"));
+        assert!(matches!(forgotten.join().unwrap(), Err(CallError::Timeout)));
+        assert!(owner_events.responses.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_client_messages_keep_their_order() {
+        let (_dir, path) = socket_path();
+        let ((server, requests), (_owner, _owner_events)) = serve_with_owner(&path);
+        let (cli, _cli_events) = join(&path, &server, SocketOptions::default());
+
+        /* An event followed by a request, like `hoppctl call start --livekit-url`. */
+        cli.send(Message::LivekitServerUrl("wss://example".to_string()))
+            .unwrap();
+        std::thread::spawn(move || cli.call(Message::ListCameras, Duration::from_secs(1)));
+        assert!(matches!(
+            requests
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .message,
+            Message::LivekitServerUrl(_)
+        ));
+        assert!(matches!(
+            requests
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .message,
+            Message::ListCameras
+        ));
+    }
+
+    #[test]
+    fn test_broadcast_respects_subscriptions() {
+        let (_dir, path) = socket_path();
+        let ((server, _requests), (_owner, owner_events)) = serve_with_owner(&path);
+        let quiet = SocketOptions {
+            subscriptions: Some(vec![Topic::Participants]),
+            ..Default::default()
+        };
+        let (_cli, cli_events) = join(&path, &server, quiet);
+
+        server.send(Message::MicrophoneAudioLevel(0.5)).unwrap();
+        server.send(Message::CallEnded).unwrap();
+
+        let owner_got = owner_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(owner_got, Message::MicrophoneAudioLevel(_)));
+        let owner_got = owner_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(owner_got, Message::CallEnded));
+        /* The audio level is filtered out, the untopical event still arrives. */
+        let cli_got = cli_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert!(matches!(cli_got, Message::CallEnded));
+    }
+
+    #[test]
+    fn test_owner_leaving_closes_the_server() {
+        let (_dir, path) = socket_path();
+        let ((server, requests), (owner, owner_events)) = serve_with_owner(&path);
+        let (cli, cli_events) = join(&path, &server, SocketOptions::default());
+
+        cli.send(Message::Ping).unwrap();
+        assert!(matches!(
+            requests
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .message,
+            Message::Ping
+        ));
+
+        drop(owner_events);
+        drop(owner);
+        assert!(matches!(
+            requests.recv_timeout(Duration::from_secs(5)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        ));
+        assert_eq!(server.client_count(), 0);
+        assert!(cli_events
+            .events
+            .recv_timeout(Duration::from_secs(5))
+            .is_err());
+    }
+}
@@ -68,7 +68,7 @@ struct AudioDevice {
 
 /// Accumulates RMS over captured samples and emits mic level at a fixed cadence.
 struct LevelEmitter {
-    socket: socket_lib::SocketSender,
+    socket: socket_lib::server::ServerSender,
     sum_sq: f64,
     sample_count: usize,
     last_emit: std::time::Instant,
@@ -77,7 +77,7 @@ struct LevelEmitter {
 impl LevelEmitter {
     const EMIT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
 
-    fn new(socket: socket_lib::SocketSender) -> Self {
+    fn new(socket: socket_lib::server::ServerSender) -> Self {
         Self {
             socket,
             sum_sq: 0.0,
@@ -115,14 +115,14 @@ pub struct Capturer {
     sample_tx: Option<mpsc::UnboundedSender<Vec<i16>>>,
     active_device_name: Option<String>,
     _device_monitor: super::device_monitor::DeviceMonitor,
-    socket: socket_lib::SocketSender,
+    socket: socket_lib::server::ServerSender,
     proxy: winit::event_loop::EventLoopProxy<crate::UserEvent>,
 }
 
 impl Capturer {
     pub fn new(
         proxy: winit::event_loop::EventLoopProxy<crate::UserEvent>,
-        socket: socket_lib::SocketSender,
+        socket: socket_lib::server::ServerSender,
     ) -> Self {
         let proxy_for_monitor = proxy.clone();
         Self {
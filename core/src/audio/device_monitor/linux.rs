@@ -0,0 +1,247 @@
+use log::{info, warn};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use libpulse_binding::context::subscribe::{Facility, InterestMaskSet, Operation};
+use libpulse_binding::context::{Context, FlagSet, State};
+use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
+use winit::event_loop::EventLoopProxy;
+
+use super::DeviceKind;
+use crate::UserEvent;
+
+/* How long the monitor threads sleep when idle, also bounds how long Drop waits for them. */
+const IDLE_INTERVAL: Duration = Duration::from_millis(100);
+/* cpal has no change notifications, the fallback compares snapshots at this rate. */
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn label(kind: DeviceKind) -> &'static str {
+    match kind {
+        DeviceKind::Input => "input",
+        DeviceKind::Output => "output",
+    }
+}
+
+fn changed_event(kind: DeviceKind) -> UserEvent {
+    match kind {
+        DeviceKind::Output => UserEvent::DefaultOutputDeviceChanged,
+        DeviceKind::Input => UserEvent::DefaultInputDeviceChanged,
+    }
+}
+
+/// Watches for Linux audio device changes (input OR output).
+///
+/// Subscribes to the PulseAudio server, which PipeWire also provides through
+/// pipewire-pulse. Systems running bare ALSA fall back to polling cpal.
+pub struct DeviceMonitor {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceMonitor {
+    pub fn new(kind: DeviceKind, proxy: EventLoopProxy<UserEvent>) -> Result<Self, String> {
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let pulse_shutdown = shutdown.clone();
+        let pulse_proxy = proxy.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("device-monitor-{}", label(kind)))
+            .spawn(move || run_pulse(kind, pulse_proxy, pulse_shutdown, ready_tx))
+            .map_err(|e| format!("Failed to spawn device monitor thread: {e}"))?;
+
+        let started = ready_rx
+            .recv()
+            .unwrap_or_else(|_| Err("pulse thread exited".to_string()));
+        let thread = match started {
+            Ok(()) => {
+                info!("Device monitor started ({}, pulse)", label(kind));
+                thread
+            }
+            Err(e) => {
+                let _ = thread.join();
+                warn!(
+                    "Device monitor ({}): {e}, polling cpal instead",
+                    label(kind)
+                );
+                let poll_shutdown = shutdown.clone();
+                let thread = std::thread::Builder::new()
+                    .name(format!("device-monitor-{}", label(kind)))
+                    .spawn(move || run_cpal_poll(kind, proxy, poll_shutdown))
+                    .map_err(|e| format!("Failed to spawn device monitor thread: {e}"))?;
+                info!("Device monitor started ({}, cpal)", label(kind));
+                thread
+            }
+        };
+
+        Ok(Self {
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Connects the pulse context, blocking until it is ready or has failed.
+fn connect_pulse() -> Result<(Mainloop, Context), String> {
+    let mut mainloop = Mainloop::new().ok_or("Failed to create pulse mainloop")?;
+    let mut context =
+        Context::new(&mainloop, "hopp-device-monitor").ok_or("Failed to create pulse context")?;
+    context
+        .connect(None, FlagSet::NOAUTOSPAWN, None)
+        .map_err(|e| format!("Failed to connect to pulse: {e}"))?;
+
+    loop {
+        match mainloop.iterate(true) {
+            IterateResult::Success(_) => {}
+            IterateResult::Quit(_) => return Err("pulse mainloop quit".to_string()),
+            IterateResult::Err(e) => return Err(format!("pulse mainloop failed: {e}")),
+        }
+        match context.get_state() {
+            State::Ready => return Ok((mainloop, context)),
+            State::Failed | State::Terminated => {
+                return Err("pulse connection failed".to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn run_pulse(
+    kind: DeviceKind,
+    proxy: EventLoopProxy<UserEvent>,
+    shutdown: Arc<AtomicBool>,
+    ready_tx: mpsc::Sender<Result<(), String>>,
+) {
+    let (mut mainloop, mut context) = match connect_pulse() {
+        Ok(connected) => connected,
+        Err(e) => {
+            let _ = ready_tx.send(Err(e));
+            return;
+        }
+    };
+
+    let (facility, mask) = match kind {
+        DeviceKind::Input => (Facility::Source, InterestMaskSet::SOURCE),
+        DeviceKind::Output => (Facility::Sink, InterestMaskSet::SINK),
+    };
+
+    /*
+     * A server event is raised for any server property, so the default device
+     * name is compared on the next iteration instead of reacting to each one.
+     */
+    let check_default = Rc::new(Cell::new(true));
+    let default_name: Rc<RefCell<Option<Option<String>>>> = Rc::new(RefCell::new(None));
+
+    let callback_check_default = check_default.clone();
+    let callback_proxy = proxy.clone();
+    context.set_subscribe_callback(Some(Box::new(move |event_facility, operation, _index| {
+        if event_facility == Some(Facility::Server) {
+            callback_check_default.set(true);
+        } else if event_facility == Some(facility) && operation == Some(Operation::Removed) {
+            /* The device in use may have been unplugged without the default changing. */
+            let _ = callback_proxy.send_event(changed_event(kind));
+        }
+    })));
+    let _ = context.subscribe(InterestMaskSet::SERVER | mask, |success| {
+        if !success {
+            log::error!("Device monitor: pulse subscribe failed");
+        }
+    });
+    let _ = ready_tx.send(Ok(()));
+
+    while !shutdown.load(Ordering::Relaxed) {
+        if check_default.replace(false) {
+            let default_name = default_name.clone();
+            let proxy = proxy.clone();
+            let _ = context.introspect().get_server_info(move |server_info| {
+                let name = match kind {
+                    DeviceKind::Input => server_info.default_source_name.as_deref(),
+                    DeviceKind::Output => server_info.default_sink_name.as_deref(),
+                }
+                .map(str::to_string);
+                let previous = default_name.borrow_mut().replace(name.clone());
+                /* The first answer only records the initial default. */
+                if let Some(previous) = previous {
+                    if previous != name {
+                        info!("Default {} device changed to {name:?}", label(kind));
+                        let _ = proxy.send_event(changed_event(kind));
+                    }
+                }
+            });
+        }
+
+        match mainloop.iterate(false) {
+            IterateResult::Success(0) => std::thread::sleep(IDLE_INTERVAL),
+            IterateResult::Success(_) => {}
+            IterateResult::Quit(_) | IterateResult::Err(_) => {
+                log::error!("Device monitor ({}): pulse mainloop stopped", label(kind));
+                break;
+            }
+        }
+        if matches!(context.get_state(), State::Failed | State::Terminated) {
+            log::error!("Device monitor ({}): pulse connection lost", label(kind));
+            break;
+        }
+    }
+
+    context.disconnect();
+}
+
+/// The parts of the cpal device list a change is detected from.
+#[derive(PartialEq, Eq)]
+struct DeviceSnapshot {
+    default: Option<String>,
+    devices: Vec<String>,
+}
+
+impl DeviceSnapshot {
+    #[allow(deprecated)]
+    fn current(kind: DeviceKind) -> Self {
+        use cpal::traits::{DeviceTrait, HostTrait};
+        let host = cpal::default_host();
+        let (default, devices) = match kind {
+            DeviceKind::Input => (host.default_input_device(), host.input_devices()),
+            DeviceKind::Output => (host.default_output_device(), host.output_devices()),
+        };
+        let mut devices: Vec<String> = devices
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default();
+        devices.sort();
+        Self {
+            default: default.and_then(|d| d.name().ok()),
+            devices,
+        }
+    }
+}
+
+fn run_cpal_poll(kind: DeviceKind, proxy: EventLoopProxy<UserEvent>, shutdown: Arc<AtomicBool>) {
+    let mut snapshot = DeviceSnapshot::current(kind);
+    let mut last_poll = std::time::Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        std::thread::sleep(IDLE_INTERVAL);
+        if last_poll.elapsed() < POLL_INTERVAL {
+            continue;
+        }
+        last_poll = std::time::Instant::now();
+
+        let current = DeviceSnapshot::current(kind);
+        if current != snapshot {
+            info!("{} devices changed", label(kind));
+            snapshot = current;
+            let _ = proxy.send_event(changed_event(kind));
+        }
+    }
+}
@@ -13,3 +13,8 @@ pub use macos::DeviceMonitor;
 mod windows;
 #[cfg(target_os = "windows")]
 pub use windows::DeviceMonitor;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::DeviceMonitor;
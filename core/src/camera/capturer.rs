@@ -1,5 +1,6 @@
 use livekit::webrtc::video_source::native::NativeVideoSource;
-use socket_lib::{CameraDevice, Message, SocketSender};
+use socket_lib::server::ServerSender;
+use socket_lib::{CameraDevice, Message};
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::Duration;
 
@@ -14,7 +15,7 @@ pub struct CameraCapturer {
     stream: Option<CameraStream>,
     rx: Arc<Mutex<mpsc::Receiver<CameraStreamMessage>>>,
     tx: mpsc::Sender<CameraStreamMessage>,
-    socket: Option<SocketSender>,
+    socket: Option<ServerSender>,
     stream_config: Arc<CameraStreamConfig>,
 }
 
@@ -47,7 +48,7 @@ impl CameraCapturer {
     pub fn start_capture(
         &mut self,
         device_name: Option<&str>,
-        socket: SocketSender,
+        socket: ServerSender,
         video_buffer_manager: Arc<VideoBufferManager>,
         buffer_source: NativeVideoSource,
     ) -> Result<(), String> {
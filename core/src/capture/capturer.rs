@@ -13,14 +13,14 @@ use crate::{
 /// Different platforms use different types of identifiers for monitors:
 /// - macOS uses numeric CGDirectDisplayID
 /// - Windows uses device name strings like "\\.\DISPLAY1"
-/// - Linux falls back to position-based identification
+/// - Linux uses the output name (e.g. "DP-1"), falling back to the position
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MonitorId {
     /// Numeric identifier (macOS CGDirectDisplayID)
     Numeric(u32),
-    /// Named identifier (Windows device name)
+    /// Named identifier (Windows device name, Linux output name)
     Named(String),
-    /// Position-based identifier (Linux fallback)
+    /// Position-based identifier (Linux fallback for unnamed monitors)
     Position(PhysicalPosition<i32>),
 }
 use std::sync::{mpsc, Arc, Mutex};
@@ -374,6 +374,12 @@ impl Capturer {
         }
         #[cfg(target_os = "linux")]
         {
+            if let Some(monitor) = ScreenshareFunctions::monitor_for_content_id(monitors, input_id)
+            {
+                return monitor;
+            }
+
+            /* Portal captures don't use XRandR ids, match the captured rect instead. */
             if self.active_stream.is_none() {
                 log::warn!("get_selected_monitor: no active stream");
                 return monitors[0].clone();
@@ -1,21 +1,86 @@
+use x11rb::protocol::xproto::Atom;
+
 use crate::capture::capturer::{MonitorId, ScreenshareExt};
+use crate::input::x11::{self, X11Error};
+
+/// A monitor as reported by XRandR.
+///
+/// On X11 the `DesktopCapturer` source id of a screen is the atom naming its
+/// XRandR monitor, and for monitors created automatically by the server that
+/// name is the output name winit reports for the same screen (e.g. `DP-1`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RandrMonitor {
+    atom: Atom,
+    name: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl RandrMonitor {
+    fn matches(&self, monitor: &winit::monitor::MonitorHandle) -> bool {
+        if let Some(name) = monitor.name() {
+            if name == self.name {
+                return true;
+            }
+        }
+        /* Monitors defined with `xrandr --setmonitor` have arbitrary names, fall back to geometry. */
+        let position = monitor.position();
+        let size = monitor.size();
+        position.x == self.x
+            && position.y == self.y
+            && size.width == self.width
+            && size.height == self.height
+    }
+}
+
+fn randr_monitors() -> Result<Vec<RandrMonitor>, X11Error> {
+    let connection = x11::connection().ok_or(X11Error::NotConnected)?;
+    let monitors = connection
+        .randr_monitors()?
+        .into_iter()
+        .map(|(info, name)| RandrMonitor {
+            atom: info.name,
+            name,
+            x: info.x.into(),
+            y: info.y.into(),
+            width: info.width.into(),
+            height: info.height.into(),
+        })
+        .collect();
+    Ok(monitors)
+}
 
 pub struct ScreenshareFunctions {}
 
 impl ScreenshareExt for ScreenshareFunctions {
     fn get_selected_monitor(
         monitors: &[winit::monitor::MonitorHandle],
-        _input_id: u32,
+        input_id: u32,
     ) -> winit::monitor::MonitorHandle {
-        monitors[0].clone()
+        Self::monitor_for_content_id(monitors, input_id).unwrap_or_else(|| monitors[0].clone())
     }
 
     fn get_monitor_id(monitor: &winit::monitor::MonitorHandle) -> MonitorId {
-        MonitorId::Position(monitor.position())
+        match monitor.name() {
+            Some(name) => MonitorId::Named(name),
+            None => MonitorId::Position(monitor.position()),
+        }
     }
 
-    fn capture_content_id_for_monitor(_monitor: &winit::monitor::MonitorHandle) -> Option<u32> {
-        Some(0)
+    fn capture_content_id_for_monitor(monitor: &winit::monitor::MonitorHandle) -> Option<u32> {
+        let randr_monitors = match randr_monitors() {
+            Ok(randr_monitors) => randr_monitors,
+            Err(e) => {
+                log::error!("capture_content_id_for_monitor: failed to list monitors: {e}");
+                return None;
+            }
+        };
+        randr_monitors
+            .iter()
+            .find(|randr_monitor| randr_monitor.matches(monitor))
+            .map(|randr_monitor| randr_monitor.atom)
     }
 }
 
@@ -29,4 +94,39 @@ impl ScreenshareFunctions {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Resolves a `DesktopCapturer` source id to the winit monitor showing it.
+    ///
+    /// Returns `None` when the id isn't an XRandR monitor, which is the case
+    /// when capturing through the desktop portal.
+    pub(crate) fn monitor_for_content_id(
+        monitors: &[winit::monitor::MonitorHandle],
+        content_id: u32,
+    ) -> Option<winit::monitor::MonitorHandle> {
+        let randr_monitors = match randr_monitors() {
+            Ok(randr_monitors) => randr_monitors,
+            Err(e) => {
+                log::warn!("monitor_for_content_id: failed to list monitors: {e}");
+                return None;
+            }
+        };
+        let Some(randr_monitor) = randr_monitors
+            .iter()
+            .find(|randr_monitor| randr_monitor.atom == content_id)
+        else {
+            log::warn!("monitor_for_content_id: no XRandR monitor for {content_id}");
+            return None;
+        };
+
+        /* Prefer an exact name match before letting geometry decide. */
+        monitors
+            .iter()
+            .find(|monitor| monitor.name().as_deref() == Some(randr_monitor.name.as_str()))
+            .or_else(|| {
+                monitors
+                    .iter()
+                    .find(|monitor| randr_monitor.matches(monitor))
+            })
+            .cloned()
+    }
 }
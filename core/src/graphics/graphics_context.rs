@@ -157,6 +157,9 @@ pub struct GraphicsContext<'a> {
     surface_present_mode: wgpu::PresentMode,
 
     screen_selection: Option<SelectionOverlayState>,
+
+    /// Name of the controller whose control request the sharer is asked about
+    control_request: Option<String>,
 }
 
 impl<'a> GraphicsContext<'a> {
@@ -245,6 +248,7 @@ impl<'a> GraphicsContext<'a> {
             surface_alpha_mode,
             surface_present_mode,
             screen_selection: None,
+            control_request: None,
         })
     }
 
@@ -282,6 +286,28 @@ impl<'a> GraphicsContext<'a> {
         result
     }
 
+    /// Shows the control request prompt for `name`, or hides it. The overlay
+    /// takes the mouse and keyboard while the prompt is up.
+    pub(crate) fn set_control_request(&mut self, name: Option<String>) {
+        let _ = self.window.set_cursor_hittest(name.is_some());
+        if name.is_some() {
+            self.window.focus_window();
+        }
+        self.control_request = name;
+        self.window.request_redraw();
+    }
+
+    pub(crate) fn handle_control_request_event(&mut self, event: &WindowEvent) -> Option<bool> {
+        let name = self.control_request.as_deref()?;
+        let (_, answer) = self.iced_renderer.handle_control_request_event(
+            event,
+            self.window.scale_factor() as f32,
+            name,
+        );
+        self.window.request_redraw();
+        answer
+    }
+
     /// Returns a clone of the redraw thread sender for use by subsystems.
     ///
     /// This allows other components (like CursorController and CursorWrapper)
@@ -392,6 +418,7 @@ impl<'a> GraphicsContext<'a> {
             click_animation_renderer: &self.click_animation_renderer,
             position_translator,
             screen_selection: self.screen_selection,
+            control_request: self.control_request.as_deref(),
             window_focused,
         });
 
@@ -1,5 +1,5 @@
 use iced::widget::canvas::{stroke, Path, Stroke};
-use iced::widget::{button, canvas, column, container, stack, text, Space};
+use iced::widget::{button, canvas, column, container, row, stack, text, Space};
 use iced::{
     mouse, Alignment, Background, Border, Color, Length, Padding, Point, Rectangle, Shadow, Size,
     Theme,
@@ -19,6 +19,8 @@ use crate::{SelectionMode, SelectionOverlayState};
 #[derive(Debug, Clone, Copy)]
 pub enum Message {
     SetSelectionMode(SelectionMode),
+    /// The sharer allowed (true) or denied a control request
+    AnswerControlRequest(bool),
 }
 
 pub struct OverlaySurfaceCanvas<'a> {
@@ -129,21 +131,100 @@ impl OverlaySurface {
         click_animation_renderer: &'a ClickAnimationRenderer,
         position_translator: &'a dyn Fn(Position) -> Position,
         screen_selection: Option<SelectionOverlayState>,
+        control_request: Option<&'a str>,
         window_focused: bool,
     ) -> Element<'a, Message, Theme, iced::Renderer> {
         if let Some(screen_selection) = screen_selection {
-            Self::screen_selection_view(screen_selection, window_focused)
-        } else {
-            canvas(OverlaySurfaceCanvas::new(
-                &self.marker,
-                participants,
-                click_animation_renderer,
-                position_translator,
-            ))
+            return Self::screen_selection_view(screen_selection, window_focused);
+        }
+        let cursors = canvas(OverlaySurfaceCanvas::new(
+            &self.marker,
+            participants,
+            click_animation_renderer,
+            position_translator,
+        ))
+        .width(Length::Fill)
+        .height(Length::Fill);
+        match control_request {
+            Some(name) => stack![cursors, Self::control_request_view(name)].into(),
+            None => cursors.into(),
+        }
+    }
+
+    /// Asks the sharer whether `name` may control their screen.
+    pub fn control_request_view<'a>(name: &str) -> Element<'a, Message, Theme, iced::Renderer> {
+        let card_background = Color::from_rgba(0.28, 0.12, 0.58, 0.98);
+        let scrim_background = Color::from_rgba(0.08, 0.05, 0.20, 0.60);
+
+        let answer_button = |label: &'static str, allow: bool| {
+            button(
+                text(label)
+                    .size(16.0)
+                    .color(Color::WHITE)
+                    .font(GEIST_REGULAR),
+            )
+            .on_press(Message::AnswerControlRequest(allow))
+            .padding(Padding::from([8.0, 20.0]))
+            .style(move |_theme: &Theme, status| {
+                let alpha = match status {
+                    button::Status::Hovered => 0.30,
+                    button::Status::Pressed => 0.40,
+                    _ => 0.20,
+                };
+                button::Style {
+                    background: Some(Background::Color(if allow {
+                        Color::from_rgba(1.0, 1.0, 1.0, alpha + 0.15)
+                    } else {
+                        Color::from_rgba(1.0, 1.0, 1.0, alpha)
+                    })),
+                    border: Border {
+                        color: Color::from_rgba(1.0, 1.0, 1.0, 0.55),
+                        width: 1.0,
+                        radius: 8.0.into(),
+                    },
+                    text_color: Color::WHITE,
+                    shadow: Shadow::default(),
+                    snap: false,
+                }
+            })
+        };
+
+        let card = column![
+            text(format!("{name} wants to control your screen"))
+                .size(24.0)
+                .color(Color::from_rgb(0.98, 0.96, 1.0))
+                .font(GEIST_REGULAR),
+            text("Press Enter to allow or ESC to deny.")
+                .size(18.0)
+                .color(Color::from_rgb(0.89, 0.84, 0.98))
+                .font(GEIST_REGULAR),
+            row![answer_button("Deny", false), answer_button("Allow", true)].spacing(12.0),
+        ]
+        .spacing(16.0)
+        .max_width(460.0);
+
+        let card =
+            container(card)
+                .padding(Padding::from([30.0, 40.0]))
+                .style(move |_theme: &Theme| container::Style {
+                    background: Some(Background::Color(card_background)),
+                    border: Border {
+                        radius: 16.0.into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+
+        container(card)
             .width(Length::Fill)
             .height(Length::Fill)
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center)
+            .style(move |_theme: &Theme| container::Style {
+                background: Some(Background::Color(scrim_background)),
+                ..Default::default()
+            })
             .into()
-        }
     }
 
     pub fn screen_selection_view(
@@ -34,6 +34,7 @@ pub(crate) struct DrawArgs<'a> {
     pub(crate) click_animation_renderer: &'a ClickAnimationRenderer,
     pub(crate) position_translator: &'a dyn Fn(Position) -> Position,
     pub(crate) screen_selection: Option<SelectionOverlayState>,
+    pub(crate) control_request: Option<&'a str>,
     pub(crate) window_focused: bool,
 }
 
@@ -97,10 +98,50 @@ impl IcedRenderer {
         screen_selection: SelectionOverlayState,
         window_focused: bool,
     ) -> (bool, Option<SelectionMode>) {
+        let (captured, messages) = self.handle_event(
+            event,
+            scale_factor,
+            OverlaySurface::screen_selection_view(screen_selection, window_focused),
+        );
+        let selected_mode = messages.into_iter().find_map(|message| match message {
+            Message::SetSelectionMode(mode) => Some(mode),
+            _ => None,
+        });
+
+        (captured, selected_mode)
+    }
+
+    /// Routes `event` to the control request prompt, returns whether it was
+    /// captured and the sharer's answer if a button was pressed.
+    pub(crate) fn handle_control_request_event(
+        &mut self,
+        event: &WindowEvent,
+        scale_factor: f32,
+        name: &str,
+    ) -> (bool, Option<bool>) {
+        let (captured, messages) = self.handle_event(
+            event,
+            scale_factor,
+            OverlaySurface::control_request_view(name),
+        );
+        let answer = messages.into_iter().find_map(|message| match message {
+            Message::AnswerControlRequest(allow) => Some(allow),
+            _ => None,
+        });
+
+        (captured, answer)
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &WindowEvent,
+        scale_factor: f32,
+        view: iced_wgpu::core::Element<'_, Message, Theme, Renderer>,
+    ) -> (bool, Vec<Message>) {
         let Some(iced_event) =
             conversion::window_event(event.clone(), scale_factor, ModifiersState::default())
         else {
-            return (false, None);
+            return (false, Vec::new());
         };
 
         match &iced_event {
@@ -115,7 +156,7 @@ impl IcedRenderer {
 
         let mut messages = Vec::new();
         let mut interface = UserInterface::build(
-            OverlaySurface::screen_selection_view(screen_selection, window_focused),
+            view,
             self.viewport.logical_size(),
             self.cache.take().unwrap_or_default(),
             &mut self.renderer,
@@ -129,12 +170,8 @@ impl IcedRenderer {
         );
         self.cache = Some(interface.into_cache());
 
-        let selected_mode = messages.into_iter().next().map(|message| match message {
-            Message::SetSelectionMode(mode) => mode,
-        });
         let captured = statuses.contains(&iced::event::Status::Captured);
-
-        (captured, selected_mode)
+        (captured, messages)
     }
 
     pub(crate) fn draw(&mut self, args: DrawArgs) {
@@ -145,6 +182,7 @@ impl IcedRenderer {
             click_animation_renderer,
             position_translator,
             screen_selection,
+            control_request,
             window_focused,
         } = args;
 
@@ -154,6 +192,7 @@ impl IcedRenderer {
                 click_animation_renderer,
                 position_translator,
                 screen_selection,
+                control_request,
                 window_focused,
             ),
             self.viewport.logical_size(),
@@ -0,0 +1,152 @@
+//! Who may drive the sharer's mouse and keyboard.
+//!
+//! Controllers that speak `Feature::ControlHandoff` ask for control with
+//! `ClientEvent::RequestControl` and only the identity the sharer granted it
+//! to gets through. Older builds can't ask, they keep taking control with
+//! their first click while nobody holds a grant. Either way only participants
+//! with the `Full` role in the sharer's `ControlRoles` may click, scroll, type
+//! or paste.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use socket_lib::ControlRole;
+
+use crate::livekit::participant::ParticipantInfo;
+use crate::room_service::{ControlRoles, Feature};
+
+/// Shared by the `CursorController` and the `KeyboardController` of a screen
+/// share, cloning it shares the grant.
+#[derive(Debug, Clone)]
+pub struct ControlGate {
+    granted: Arc<Mutex<Option<String>>>,
+    roles: Arc<RwLock<ControlRoles>>,
+    participants: Arc<RwLock<HashMap<String, ParticipantInfo>>>,
+}
+
+impl ControlGate {
+    pub fn new(
+        participants: Arc<RwLock<HashMap<String, ParticipantInfo>>>,
+        roles: ControlRoles,
+    ) -> Self {
+        Self {
+            granted: Arc::new(Mutex::new(None)),
+            roles: Arc::new(RwLock::new(roles)),
+            participants,
+        }
+    }
+
+    pub fn set_roles(&self, roles: ControlRoles) {
+        *self.roles.write().unwrap() = roles;
+    }
+
+    /// What `identity` may do on our screen.
+    pub fn role(&self, identity: &str) -> ControlRole {
+        self.roles.read().unwrap().role(identity)
+    }
+
+    /// The identity holding control, if any.
+    pub fn granted(&self) -> Option<String> {
+        self.granted.lock().unwrap().clone()
+    }
+
+    /// Gives control to `identity`, returns who had it before.
+    pub fn grant(&self, identity: &str) -> Option<String> {
+        self.granted.lock().unwrap().replace(identity.to_string())
+    }
+
+    /// Takes control back, returns who had it.
+    pub fn revoke(&self) -> Option<String> {
+        self.granted.lock().unwrap().take()
+    }
+
+    /// Whether input from `identity` may be simulated.
+    pub fn allows(&self, identity: &str) -> bool {
+        if self.role(identity) < ControlRole::Full {
+            return false;
+        }
+        if let Some(granted) = self.granted.lock().unwrap().as_deref() {
+            return granted == identity;
+        }
+        let participants = self.participants.read().unwrap();
+        participants
+            .get(identity)
+            .is_some_and(|info| !info.supports(Feature::ControlHandoff))
+    }
+}
+
+/// The user id in a participant identity, `room:team:user_id:track`.
+pub fn user_id(identity: &str) -> Option<&str> {
+    let parts: Vec<&str> = identity.split(':').collect();
+    (parts.len() >= 4).then(|| parts[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room_service::PeerHello;
+
+    #[test]
+    fn test_only_the_granted_identity_gets_through() {
+        let participants = Arc::new(RwLock::new(HashMap::new()));
+        for identity in ["a:b:alice:audio", "a:b:bob:audio"] {
+            let mut info = ParticipantInfo::new(identity.to_string(), false, false);
+            info.set_hello(PeerHello::local());
+            participants
+                .write()
+                .unwrap()
+                .insert(identity.to_string(), info);
+        }
+        participants.write().unwrap().insert(
+            "a:b:legacy:audio".to_string(),
+            ParticipantInfo::new("old build".to_string(), false, false),
+        );
+        let gate = ControlGate::new(participants, ControlRoles::default());
+
+        assert!(!gate.allows("a:b:alice:audio"));
+        assert!(gate.allows("a:b:legacy:audio"));
+
+        assert!(!gate.allows("a:b:stranger:audio"));
+
+        assert_eq!(gate.grant("a:b:alice:audio"), None);
+        assert!(gate.allows("a:b:alice:audio"));
+        assert!(!gate.clone().allows("a:b:bob:audio"));
+        assert!(!gate.allows("a:b:legacy:audio"));
+
+        assert_eq!(
+            gate.grant("a:b:bob:audio").as_deref(),
+            Some("a:b:alice:audio")
+        );
+        assert!(!gate.allows("a:b:alice:audio"));
+        assert_eq!(gate.revoke().as_deref(), Some("a:b:bob:audio"));
+        assert!(!gate.allows("a:b:bob:audio"));
+        assert!(gate.allows("a:b:legacy:audio"));
+    }
+
+    #[test]
+    fn test_roles_below_full_never_get_through() {
+        let participants = Arc::new(RwLock::new(HashMap::new()));
+        participants.write().unwrap().insert(
+            "a:b:legacy:audio".to_string(),
+            ParticipantInfo::new("old build".to_string(), false, false),
+        );
+        let gate = ControlGate::new(participants, ControlRoles::default());
+        assert!(gate.allows("a:b:legacy:audio"));
+
+        gate.set_roles(ControlRoles {
+            default_role: ControlRole::Draw,
+            roles: HashMap::from([("legacy".to_string(), ControlRole::Pointer)]),
+        });
+        assert_eq!(gate.role("a:b:legacy:audio"), ControlRole::Pointer);
+        assert_eq!(gate.role("a:b:someone:audio"), ControlRole::Draw);
+        assert!(!gate.allows("a:b:legacy:audio"));
+
+        gate.grant("a:b:legacy:audio");
+        assert!(!gate.allows("a:b:legacy:audio"));
+    }
+
+    #[test]
+    fn test_user_id_from_identity() {
+        assert_eq!(user_id("room:team:alice:audio"), Some("alice"));
+        assert_eq!(user_id("local"), None);
+    }
+}
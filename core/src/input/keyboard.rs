@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::input::control::ControlGate;
 use crate::KeystrokeData;
 
 #[cfg(target_os = "macos")]
@@ -349,6 +350,8 @@ pub struct KeyboardController<T: KeyboardLayoutTrait> {
     /// Whether keyboard simulation is currently enabled.
     enabled: bool,
     target_process_id: Option<i32>,
+    /// Which controllers may type, `None` lets everyone through.
+    control_gate: Option<ControlGate>,
 }
 
 impl<T: KeyboardLayoutTrait> KeyboardController<T> {
@@ -369,6 +372,7 @@ impl<T: KeyboardLayoutTrait> KeyboardController<T> {
             layout,
             enabled: true,
             target_process_id,
+            control_gate: None,
         }
     }
 
@@ -386,6 +390,24 @@ impl<T: KeyboardLayoutTrait> KeyboardController<T> {
         self.enabled = enabled;
     }
 
+    /// Only lets keystrokes from controllers `control_gate` allows through
+    /// `simulate_keystrokes_from`.
+    pub fn set_control_gate(&mut self, control_gate: ControlGate) {
+        self.control_gate = Some(control_gate);
+    }
+
+    /// Simulates a keystroke sent by the controller `identity`, dropped if
+    /// they weren't granted control.
+    pub fn simulate_keystrokes_from(&mut self, keystroke_data: KeystrokeData, identity: &str) {
+        if let Some(control_gate) = &self.control_gate {
+            if !control_gate.allows(identity) {
+                log::debug!("simulate_keystrokes_from: {identity} wasn't granted control");
+                return;
+            }
+        }
+        self.simulate_keystrokes(keystroke_data);
+    }
+
     /// Simulates a keystroke from high-level keystroke data.
     ///
     /// This method translates the provided keystroke data into platform-specific
@@ -1,31 +1,250 @@
 #![allow(non_snake_case)]
 use std::collections::HashMap;
 
-use super::{KeyboardEventTrait, KeyboardLayoutTrait};
+use x11rb::protocol::xproto::{Keycode, KEY_PRESS_EVENT, KEY_RELEASE_EVENT};
+use xkbcommon::xkb;
 
-pub struct KeyboardLayout {}
+use super::{get_modifiers, KeyModifier, KeyboardEventTrait, KeyboardLayoutTrait};
+use crate::input::portal::{self, PortalError, RemoteDesktopSession};
+use crate::input::x11::{self, X11Connection, X11Error};
+
+/*
+ * Keycodes on Linux are X11 keycodes, which under the evdev rules used by every
+ * modern distribution are the kernel input codes offset by 8.
+ */
+const KEYCODE_ESCAPE: u16 = 9;
+const KEYCODE_BACKSPACE: u16 = 22;
+const KEYCODE_TAB: u16 = 23;
+const KEYCODE_ENTER: u16 = 36;
+const KEYCODE_CONTROL_L: u16 = 37;
+const KEYCODE_SHIFT_L: u16 = 50;
+const KEYCODE_ALT_L: u16 = 64;
+const KEYCODE_CAPS_LOCK: u16 = 66;
+const KEYCODE_HOME: u16 = 110;
+const KEYCODE_UP: u16 = 111;
+const KEYCODE_PAGE_UP: u16 = 112;
+const KEYCODE_LEFT: u16 = 113;
+const KEYCODE_RIGHT: u16 = 114;
+const KEYCODE_END: u16 = 115;
+const KEYCODE_DOWN: u16 = 116;
+const KEYCODE_PAGE_DOWN: u16 = 117;
+const KEYCODE_DELETE: u16 = 119;
+const KEYCODE_SUPER_L: u16 = 133;
+
+/* The portal takes the kernel input codes, X11 keycodes are offset from them by 8. */
+const EVDEV_KEYCODE_OFFSET: u16 = 8;
+
+/* Used when the server doesn't publish `_XKB_RULES_NAMES`, same defaults as setxkbmap. */
+const DEFAULT_XKB_RULES: &str = "evdev";
+const DEFAULT_XKB_MODEL: &str = "pc105";
+const DEFAULT_XKB_LAYOUT: &str = "us";
+
+/// The server keyboard configuration a keymap was compiled for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LayoutState {
+    names: Option<x11::XkbRulesNames>,
+    group: u32,
+}
+
+impl LayoutState {
+    fn current() -> Self {
+        let Some(connection) = x11::connection() else {
+            return Self::default();
+        };
+        let names = connection.xkb_rules_names().unwrap_or_else(|e| {
+            log::error!("LayoutState::current: failed to read rules names: {e}");
+            None
+        });
+        let group = connection.xkb_group().unwrap_or_else(|e| {
+            log::warn!("LayoutState::current: failed to read group: {e}");
+            0
+        });
+        Self { names, group }
+    }
+}
+
+fn layout_generation() -> u64 {
+    x11::connection().map_or(0, X11Connection::layout_generation)
+}
+
+/// Compiles an xkbcommon keymap from the RMLVO names the X server keymap was
+/// built from, so translations match what the sharer types locally.
+fn compile_keymap(
+    context: &xkb::Context,
+    names: Option<&x11::XkbRulesNames>,
+) -> Option<xkb::Keymap> {
+    let keymap = match names {
+        Some(names) => xkb::Keymap::new_from_names(
+            context,
+            &names.rules,
+            &names.model,
+            &names.layout,
+            &names.variant,
+            Some(names.options.clone()),
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        ),
+        None => xkb::Keymap::new_from_names(
+            context,
+            DEFAULT_XKB_RULES,
+            DEFAULT_XKB_MODEL,
+            DEFAULT_XKB_LAYOUT,
+            "",
+            None,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        ),
+    };
+    if keymap.is_none() {
+        log::error!("compile_keymap: failed to compile keymap for {names:?}");
+    }
+    keymap
+}
+
+pub struct KeyboardLayout {
+    context: xkb::Context,
+    keymap: Option<xkb::Keymap>,
+    state: LayoutState,
+    /* `X11Connection::layout_generation` when `state` was read. */
+    generation: u64,
+}
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl KeyboardLayout {
     pub fn new() -> Self {
-        Self {}
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let generation = layout_generation();
+        let state = LayoutState::current();
+        let keymap = compile_keymap(&context, state.names.as_ref());
+        log::info!("KeyboardLayout::new: {state:?}");
+        Self {
+            context,
+            keymap,
+            state,
+            generation,
+        }
+    }
+
+    fn modifier_mask(keymap: &xkb::Keymap, modifier: u32) -> xkb::ModMask {
+        let mut mask = 0;
+        for modifier in get_modifiers(modifier) {
+            let name = match modifier {
+                KeyModifier::Shift => xkb::MOD_NAME_SHIFT,
+                KeyModifier::Ctrl => xkb::MOD_NAME_CTRL,
+                /*
+                 * Option is injected as Alt_L (see map_modifier_to_keysym), so it is
+                 * translated as Alt rather than as the AltGr level.
+                 */
+                KeyModifier::Option => xkb::MOD_NAME_ALT,
+                KeyModifier::Cmd => xkb::MOD_NAME_LOGO,
+                _ => continue,
+            };
+            let index = keymap.mod_get_index(name);
+            if index != xkb::MOD_INVALID {
+                mask |= 1 << index;
+            }
+        }
+        mask
     }
 }
 
 impl KeyboardLayoutTrait for KeyboardLayout {
     fn key_translate(&self, keycode: u16, modifier: u32) -> Option<String> {
-        None
+        let keymap = self.keymap.as_ref()?;
+        let keycode = xkb::Keycode::new(keycode as u32);
+        let mut state = xkb::State::new(keymap);
+        state.update_mask(
+            Self::modifier_mask(keymap, modifier),
+            0,
+            0,
+            0,
+            0,
+            self.state.group,
+        );
+        let translated = state.key_get_utf8(keycode);
+        /* Control characters come from keys like Enter and Tab, those use the independent codes. */
+        if translated.is_empty() || translated.chars().any(char::is_control) {
+            return None;
+        }
+        Some(translated)
     }
 
     fn has_changed(&mut self) -> bool {
-        false
+        /* Reading the layout takes several round trips, only do it after XKB told us about a change. */
+        let generation = layout_generation();
+        if generation == self.generation {
+            return false;
+        }
+        self.generation = generation;
+
+        let state = LayoutState::current();
+        if state == self.state {
+            return false;
+        }
+
+        log::info!("has_changed: keyboard layout changed to {state:?}");
+        if state.names != self.state.names {
+            self.keymap = compile_keymap(&self.context, state.names.as_ref());
+        }
+        self.state = state;
+        true
     }
 
     fn get_independent_codes(&self) -> HashMap<&'static str, u16> {
-        HashMap::new()
+        let mut map: HashMap<&'static str, u16> = HashMap::new();
+        map.insert("Enter", KEYCODE_ENTER);
+        map.insert("Tab", KEYCODE_TAB);
+        map.insert("Backspace", KEYCODE_BACKSPACE);
+        map.insert("Escape", KEYCODE_ESCAPE);
+        map.insert("ArrowUp", KEYCODE_UP);
+        map.insert("ArrowDown", KEYCODE_DOWN);
+        map.insert("ArrowLeft", KEYCODE_LEFT);
+        map.insert("ArrowRight", KEYCODE_RIGHT);
+        map.insert("PageUp", KEYCODE_PAGE_UP);
+        map.insert("PageDown", KEYCODE_PAGE_DOWN);
+        map.insert("Home", KEYCODE_HOME);
+        map.insert("End", KEYCODE_END);
+        map.insert("Delete", KEYCODE_DELETE);
+        map.insert("Control", KEYCODE_CONTROL_L);
+        map.insert("Shift", KEYCODE_SHIFT_L);
+        map.insert("Meta", KEYCODE_SUPER_L);
+        map.insert("Alt", KEYCODE_ALT_L);
+        map.insert("CapsLock", KEYCODE_CAPS_LOCK);
+        map
+    }
+}
+
+fn map_modifier_to_keysym(modifier: &KeyModifier) -> Option<u32> {
+    match modifier {
+        KeyModifier::Cmd => Some(x11::XK_SUPER_L),
+        KeyModifier::Shift => Some(x11::XK_SHIFT_L),
+        KeyModifier::Option => Some(x11::XK_ALT_L),
+        KeyModifier::Ctrl => Some(x11::XK_CONTROL_L),
+        _ => None,
+    }
+}
+
+fn map_modifier_to_keycode(modifier: &KeyModifier) -> Option<u16> {
+    match modifier {
+        KeyModifier::Cmd => Some(KEYCODE_SUPER_L),
+        KeyModifier::Shift => Some(KEYCODE_SHIFT_L),
+        KeyModifier::Option => Some(KEYCODE_ALT_L),
+        KeyModifier::Ctrl => Some(KEYCODE_CONTROL_L),
+        _ => None,
     }
 }
 
-pub struct KeyboardEvent {}
+pub struct KeyboardEvent {
+    keycode: u16,
+    modifier: u32,
+    down: bool,
+    utf: Option<char>,
+    /// Sent through the RemoteDesktop portal instead of XTest.
+    wayland: bool,
+}
 
 impl KeyboardEvent {
     pub fn new(
@@ -34,12 +253,422 @@ impl KeyboardEvent {
         down: bool,
         _target_process_id: Option<i32>,
     ) -> Option<Self> {
-        None
+        let wayland = portal::is_wayland_session();
+        if wayland {
+            if portal::session().is_none() {
+                log::error!("KeyboardEvent::new: no remote desktop session");
+                return None;
+            }
+        } else if x11::connection().is_none() {
+            log::error!("KeyboardEvent::new: no X11 connection");
+            return None;
+        }
+        Some(Self {
+            keycode,
+            modifier,
+            down,
+            utf: None,
+            wayland,
+        })
+    }
+
+    /// Presses the modifiers of this event that aren't already held and returns
+    /// the keycodes that were pressed, so they can be released afterwards.
+    fn press_modifiers(
+        &self,
+        connection: &X11Connection,
+        mapping: &x11::KeyboardMapping,
+        include_shift: bool,
+    ) -> Result<Vec<Keycode>, X11Error> {
+        let mut modifiers = get_modifiers(self.modifier);
+        if include_shift && !modifiers.iter().any(|m| matches!(m, KeyModifier::Shift)) {
+            modifiers.push(KeyModifier::Shift);
+        }
+
+        let mut pressed = Vec::new();
+        for modifier in modifiers.iter() {
+            let Some(keycode) =
+                map_modifier_to_keysym(modifier).and_then(|ks| mapping.keycode_for_keysym(ks))
+            else {
+                continue;
+            };
+            if connection.is_key_down(keycode)? {
+                continue;
+            }
+            connection.fake_input(KEY_PRESS_EVENT, keycode, 0, 0)?;
+            pressed.push(keycode);
+        }
+        Ok(pressed)
+    }
+
+    fn release_modifiers(connection: &X11Connection, pressed: &[Keycode]) -> Result<(), X11Error> {
+        for keycode in pressed.iter().rev() {
+            connection.fake_input(KEY_RELEASE_EVENT, *keycode, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Types `c` by tapping whichever key produces it. When the layout has no
+    /// key for the character, a free keycode is temporarily bound to its keysym.
+    fn type_char(&self, connection: &X11Connection, c: char) -> Result<(), X11Error> {
+        let mapping = connection.keyboard_mapping()?;
+        let shift = self.modifier & KeyModifier::Shift as u32 != 0;
+
+        /* The keycode resolved from the layout already produces this character. */
+        if let Some(keycode) = mapping
+            .keycode_for_char(c, shift)
+            .filter(|&keycode| self.keycode != 0 && u16::from(keycode) == self.keycode)
+        {
+            return self.press_key(connection, &mapping, keycode);
+        }
+
+        for (level_shift, keycode) in [
+            (shift, mapping.keycode_for_char(c, shift)),
+            (!shift, mapping.keycode_for_char(c, !shift)),
+        ] {
+            let Some(keycode) = keycode else {
+                continue;
+            };
+            /* We can add shift for the tap but we can't drop a shift the controller holds. */
+            if shift && !level_shift {
+                continue;
+            }
+            let pressed = self.press_modifiers(connection, &mapping, level_shift)?;
+            connection.fake_input(KEY_PRESS_EVENT, keycode, 0, 0)?;
+            connection.fake_input(KEY_RELEASE_EVENT, keycode, 0, 0)?;
+            Self::release_modifiers(connection, &pressed)?;
+            return connection.flush();
+        }
+
+        let Some(scratch) = mapping.unused_keycode() else {
+            log::error!("type_char: no free keycode to bind {c:?} to");
+            return Ok(());
+        };
+        let keysym = x11::char_to_keysym(c);
+        log::debug!("type_char: binding {c:?} (keysym {keysym:#x}) to keycode {scratch}");
+        let original = mapping.keysyms(scratch).to_vec();
+        let keysyms = vec![keysym; original.len().max(2)];
+        connection.change_keyboard_mapping(scratch, &keysyms)?;
+        connection.sync()?;
+        connection.fake_input(KEY_PRESS_EVENT, scratch, 0, 0)?;
+        connection.fake_input(KEY_RELEASE_EVENT, scratch, 0, 0)?;
+        connection.sync()?;
+        /*
+         * Clients resolve the keysym when they process the event, give them a
+         * moment to pick up the new mapping before restoring the old one.
+         */
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        connection.change_keyboard_mapping(scratch, &original)?;
+        connection.flush()
+    }
+
+    fn press_key(
+        &self,
+        connection: &X11Connection,
+        mapping: &x11::KeyboardMapping,
+        keycode: Keycode,
+    ) -> Result<(), X11Error> {
+        let pressed = self.press_modifiers(connection, mapping, false)?;
+        connection.fake_input(KEY_PRESS_EVENT, keycode, 0, 0)?;
+        Self::release_modifiers(connection, &pressed)?;
+        connection.flush()
+    }
+
+    /// Same as `send_inner` through the portal. Characters are typed by keysym,
+    /// the compositor picks the key and shift level that produce them, so only
+    /// the other modifiers are pressed around them.
+    fn send_portal(&self, session: &RemoteDesktopSession) -> Result<(), PortalError> {
+        let utf = self.utf.filter(|_| self.down);
+        if utf.is_none() && self.keycode < EVDEV_KEYCODE_OFFSET {
+            log::warn!("KeyboardEvent::send: invalid keycode {}", self.keycode);
+            return Ok(());
+        }
+        let key = |keycode: u16, pressed| {
+            session.keyboard_keycode(i32::from(keycode - EVDEV_KEYCODE_OFFSET), pressed)
+        };
+        if !self.down {
+            return key(self.keycode, false);
+        }
+
+        let modifiers: Vec<u16> = get_modifiers(self.modifier)
+            .iter()
+            .filter(|modifier| utf.is_none() || !matches!(modifier, KeyModifier::Shift))
+            .filter_map(map_modifier_to_keycode)
+            .collect();
+        for modifier in &modifiers {
+            key(*modifier, true)?;
+        }
+        match utf {
+            Some(c) => {
+                let keysym = x11::char_to_keysym(c);
+                session.keyboard_keysym(keysym, true)?;
+                session.keyboard_keysym(keysym, false)?;
+            }
+            None => key(self.keycode, true)?,
+        }
+        for modifier in modifiers.iter().rev() {
+            key(*modifier, false)?;
+        }
+        Ok(())
+    }
+
+    fn send_inner(&self, connection: &X11Connection) -> Result<(), X11Error> {
+        if self.down {
+            if let Some(c) = self.utf {
+                return self.type_char(connection, c);
+            }
+        }
+
+        /* X11 keycodes start at 8, anything lower means the lookup failed. */
+        if self.keycode < 8 || self.keycode > u8::MAX as u16 {
+            log::warn!("KeyboardEvent::send: invalid keycode {}", self.keycode);
+            return Ok(());
+        }
+        let keycode = self.keycode as u8;
+
+        if self.down {
+            let mapping = connection.keyboard_mapping()?;
+            self.press_key(connection, &mapping, keycode)
+        } else {
+            connection.fake_input(KEY_RELEASE_EVENT, keycode, 0, 0)?;
+            connection.flush()
+        }
     }
 }
 
 impl KeyboardEventTrait for KeyboardEvent {
-    fn override_utf(&mut self, key: &str) {}
+    fn override_utf(&mut self, key: &str) {
+        let mut chars = key.chars();
+        self.utf = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => {
+                log::error!("override_utf: key: {key} is not a single character");
+                sentry_utils::upload_logs_event("KeyboardEvent override_utf failed".to_string());
+                None
+            }
+        };
+    }
+
+    fn send(&self) {
+        if self.wayland {
+            let Some(session) = portal::session() else {
+                log::error!("KeyboardEvent::send: no remote desktop session");
+                return;
+            };
+            if let Err(e) = self.send_portal(&session) {
+                log::error!("KeyboardEvent::send: {e}");
+            }
+            return;
+        }
+        let Some(connection) = x11::connection() else {
+            log::error!("KeyboardEvent::send: no X11 connection");
+            return;
+        };
+        if let Err(e) = self.send_inner(connection) {
+            log::error!("KeyboardEvent::send: {e}");
+        }
+    }
+}
+
+/*
+ * The injection tests need an X server with XTest and the layout test needs
+ * xkeyboard-config, they are ignored by default. Run them with
+ * `xvfb-run cargo test -- --ignored`.
+ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keyboard::KeyboardController;
+    use crate::KeystrokeData;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{
+        ConnectionExt as _, CreateWindowAux, EventMask, InputFocus, WindowClass,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    /* Keyboard focus is global, the tests must not interleave. */
+    static FOCUS: Mutex<()> = Mutex::new(());
+
+    struct FocusedWindow {
+        conn: RustConnection,
+    }
+
+    impl FocusedWindow {
+        fn new() -> Option<Self> {
+            std::env::var_os("DISPLAY")?;
+            let (conn, screen_num) = x11rb::connect(None).ok()?;
+            let screen = &conn.setup().roots[screen_num];
+            let window = conn.generate_id().ok()?;
+            conn.create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                window,
+                screen.root,
+                0,
+                0,
+                100,
+                100,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                0,
+                &CreateWindowAux::new()
+                    .event_mask(EventMask::KEY_PRESS | EventMask::KEY_RELEASE)
+                    .override_redirect(1),
+            )
+            .ok()?;
+            conn.map_window(window).ok()?;
+            conn.set_input_focus(InputFocus::PARENT, window, x11rb::CURRENT_TIME)
+                .ok()?;
+            conn.sync().ok()?;
+            Some(Self { conn })
+        }
+
+        /// Collects (is_press, keycode) pairs until nothing arrives for a while.
+        fn drain(&self) -> Vec<(bool, u8)> {
+            let mut events = Vec::new();
+            let mut deadline = Instant::now() + Duration::from_millis(500);
+            while Instant::now() < deadline {
+                match self.conn.poll_for_event().unwrap() {
+                    Some(Event::KeyPress(e)) => {
+                        events.push((true, e.detail));
+                        deadline = Instant::now() + Duration::from_millis(200);
+                    }
+                    Some(Event::KeyRelease(e)) => {
+                        events.push((false, e.detail));
+                        deadline = Instant::now() + Duration::from_millis(200);
+                    }
+                    Some(_) => {}
+                    None => std::thread::sleep(Duration::from_millis(5)),
+                }
+            }
+            events
+        }
+    }
+
+    fn keystroke(key: &str, shift: bool, down: bool) -> KeystrokeData {
+        KeystrokeData {
+            key: key.to_string(),
+            meta: false,
+            shift,
+            ctrl: false,
+            alt: false,
+            down,
+        }
+    }
 
-    fn send(&self) {}
+    #[test]
+    #[ignore = "needs an X server with XTest"]
+    fn test_type_ascii_and_release() {
+        let _guard = FOCUS.lock().unwrap();
+        let window = FocusedWindow::new().expect("no X server");
+        let mapping = x11::connection().unwrap().keyboard_mapping().unwrap();
+        let a = mapping.keycode_for_char('a', false).unwrap();
+
+        let mut controller = KeyboardController::<KeyboardLayout>::new(None);
+        controller.simulate_keystrokes(keystroke("a", false, true));
+        controller.simulate_keystrokes(keystroke("Enter", false, true));
+        controller.simulate_keystrokes(keystroke("Enter", false, false));
+
+        let events = window.drain();
+        assert!(events.contains(&(true, a)));
+        assert!(events.contains(&(false, a)));
+        assert!(events.contains(&(true, KEYCODE_ENTER as u8)));
+        assert!(events.contains(&(false, KEYCODE_ENTER as u8)));
+    }
+
+    #[test]
+    #[ignore = "needs an X server with XTest"]
+    fn test_shift_is_pressed_and_released() {
+        let _guard = FOCUS.lock().unwrap();
+        let window = FocusedWindow::new().expect("no X server");
+        let connection = x11::connection().unwrap();
+        let mapping = connection.keyboard_mapping().unwrap();
+        let shift = mapping.keycode_for_keysym(x11::XK_SHIFT_L).unwrap();
+        let upper_a = mapping.keycode_for_char('A', true).unwrap();
+
+        let mut controller = KeyboardController::<KeyboardLayout>::new(None);
+        controller.simulate_keystrokes(keystroke("A", true, true));
+
+        let events = window.drain();
+        let shift_down = events.iter().position(|e| *e == (true, shift)).unwrap();
+        let key_down = events.iter().position(|e| *e == (true, upper_a)).unwrap();
+        let shift_up = events.iter().position(|e| *e == (false, shift)).unwrap();
+        assert!(shift_down < key_down && key_down < shift_up);
+        assert!(!connection.is_key_down(shift).unwrap());
+    }
+
+    #[test]
+    #[ignore = "needs an X server with XTest"]
+    fn test_unicode_uses_scratch_keycode() {
+        let _guard = FOCUS.lock().unwrap();
+        let window = FocusedWindow::new().expect("no X server");
+        let connection = x11::connection().unwrap();
+        let before = connection.keyboard_mapping().unwrap();
+        let scratch = before.unused_keycode().unwrap();
+
+        let mut controller = KeyboardController::<KeyboardLayout>::new(None);
+        controller.simulate_keystrokes(keystroke("λ", false, true));
+
+        let events = window.drain();
+        assert!(events.contains(&(true, scratch)));
+        assert!(events.contains(&(false, scratch)));
+
+        /* The scratch keycode is unbound again once the character was typed. */
+        let after = connection.keyboard_mapping().unwrap();
+        assert_eq!(before.keysyms(scratch), after.keysyms(scratch));
+    }
+
+    /// Builds a layout for the given RMLVO layout without asking the X server,
+    /// returns `None` when xkeyboard-config isn't installed.
+    fn layout_for(layout: &str, variant: &str) -> Option<KeyboardLayout> {
+        let names = x11::XkbRulesNames {
+            rules: DEFAULT_XKB_RULES.to_string(),
+            model: DEFAULT_XKB_MODEL.to_string(),
+            layout: layout.to_string(),
+            variant: variant.to_string(),
+            options: String::new(),
+        };
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = Some(compile_keymap(&context, Some(&names))?);
+        Some(KeyboardLayout {
+            context,
+            keymap,
+            state: LayoutState {
+                names: Some(names),
+                group: 0,
+            },
+            generation: 0,
+        })
+    }
+
+    #[test]
+    #[ignore = "needs xkeyboard-config"]
+    fn test_translate_non_us_layouts() {
+        /* evdev keycodes of the physical Q, W, Y and Z keys on a US keyboard. */
+        const KEY_Q: u16 = 24;
+        const KEY_W: u16 = 25;
+        const KEY_Y: u16 = 29;
+        const KEY_Z: u16 = 52;
+        let shift = KeyModifier::Shift as u32;
+
+        let us = layout_for("us", "").expect("xkeyboard-config not available");
+        assert_eq!(us.key_translate(KEY_Y, 0).as_deref(), Some("y"));
+        assert_eq!(us.key_translate(KEY_Y, shift).as_deref(), Some("Y"));
+        assert_eq!(us.key_translate(KEYCODE_ENTER, 0), None);
+
+        let de = layout_for("de", "").unwrap();
+        assert_eq!(de.key_translate(KEY_Y, 0).as_deref(), Some("z"));
+        assert_eq!(de.key_translate(KEY_Z, 0).as_deref(), Some("y"));
+
+        let fr = layout_for("fr", "").unwrap();
+        assert_eq!(fr.key_translate(KEY_Q, 0).as_deref(), Some("a"));
+        assert_eq!(fr.key_translate(KEY_W, shift).as_deref(), Some("Z"));
+
+        let dvorak = layout_for("us", "dvorak").unwrap();
+        assert_eq!(dvorak.key_translate(KEY_Q, 0).as_deref(), Some("'"));
+        assert_eq!(dvorak.key_translate(KEY_Y, 0).as_deref(), Some("f"));
+    }
 }
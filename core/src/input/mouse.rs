@@ -5,12 +5,14 @@ use std::{
 
 use crate::{
     graphics::graphics_context::{participant::cursor::CursorMode, RedrawThreadCommands},
+    input::control::ControlGate,
     overlay_window::OverlayWindow,
     utils::{clock::Clock, geometry::Position},
     MouseClickData, ScrollDelta, UserEvent,
 };
 
 use log::{debug, error};
+use socket_lib::ControlRole;
 use winit::event_loop::EventLoopProxy;
 
 /// Platform-specific mouse input implementations and cursor control system.
@@ -74,7 +76,7 @@ pub use platform::{CursorSimulator, MouseObserver};
 ///
 /// * **macOS**: Set in `EVENT_SOURCE_USER_DATA` field of CGEvent
 /// * **Windows**: Set in `dwExtraInfo` field of MOUSEINPUT structure
-/// * **Default**: Not used (no event simulation)
+/// * **Linux**: Not used, XTest events are told apart by their source device
 ///
 /// # Purpose
 ///
@@ -112,7 +114,7 @@ pub enum CursorControllerError {
 /// ## Platform Implementation Notes:
 /// - **macOS**: Uses CGEvent API with Core Graphics event sources
 /// - **Windows**: Uses SendInput API with virtual desktop coordinates
-/// - **Default**: No-op implementation that logs errors
+/// - **Linux**: Uses XTest fake input in X root window coordinates
 ///
 /// All implementations should:
 /// 1. Mark simulated events to prevent feedback loops
@@ -129,7 +131,7 @@ pub trait CursorSimulatorFunctions {
     /// # Platform Behavior:
     /// - **macOS**: Direct CGEvent posting with pixel coordinates
     /// - **Windows**: SendInput with virtual desktop coordinate mapping
-    /// - **Default**: Logs error message only
+    /// - **Linux**: XTest motion in root window coordinates
     fn simulate_cursor_movement(&mut self, position: Position, click_down: bool);
 
     /// Simulates a mouse click event.
@@ -140,7 +142,7 @@ pub trait CursorSimulatorFunctions {
     /// # Platform Behavior:
     /// - **macOS**: Preserves modifier flags and click count natively
     /// - **Windows**: Converts to INPUT structure with virtual coordinates
-    /// - **Default**: Logs error message only
+    /// - **Linux**: XTest button events, clients derive the click count themselves
     fn simulate_click(&mut self, click_data: MouseClickData);
 
     /// Simulates mouse scroll wheel events.
@@ -151,7 +153,7 @@ pub trait CursorSimulatorFunctions {
     /// # Platform Behavior:
     /// - **macOS**: Direct pixel-based scrolling via CGEvent
     /// - **Windows**: Complex translation to WHEEL_DELTA units with rate limiting
-    /// - **Default**: Logs error message only
+    /// - **Linux**: Pixel deltas accumulated into wheel button clicks (4-7)
     fn simulate_scroll(&mut self, delta: ScrollDelta);
 }
 
@@ -345,7 +347,7 @@ pub struct SharerCursor {
     controllers_cursors: Arc<Mutex<Vec<ControllerCursor>>>,
     last_event_position: Position,
     last_event_position_time: Instant,
-    // only Windows simulates the take-back click in SharerCursor.
+    // only Windows and Linux simulate the take-back click in SharerCursor.
     // macOS rewrites the hardware event in the event tap instead.
     #[cfg(not(target_os = "macos"))]
     cursor_simulator: Arc<Mutex<CursorSimulator>>,
@@ -444,6 +446,26 @@ impl SharerCursor {
         });
     }
 
+    /// Releases the take-back click simulated by `click`. On Linux the sharer's
+    /// hardware button stays grabbed until it is released, so the release
+    /// never reaches the system on its own.
+    #[cfg(target_os = "linux")]
+    fn release_click(&mut self) {
+        let global_position = self.global_position();
+        let mut cursor_simulator = self.cursor_simulator.lock().unwrap();
+        cursor_simulator.simulate_click(MouseClickData {
+            x: global_position.x as f32,
+            y: global_position.y as f32,
+            button: 0,
+            clicks: 1.,
+            down: false,
+            shift: false,
+            alt: false,
+            ctrl: false,
+            meta: false,
+        });
+    }
+
     fn scroll(&mut self) {
         log::debug!("sharer_cursor: scroll: has_control: {}", self.has_control);
 
@@ -570,6 +592,8 @@ pub struct CursorController {
     event_loop_proxy: EventLoopProxy<UserEvent>,
     /// Clock for time tracking
     clock: Arc<dyn Clock>,
+    /// Which controllers may click and scroll, shared with the keyboard controller
+    control_gate: ControlGate,
 }
 
 impl CursorController {
@@ -589,6 +613,7 @@ impl CursorController {
     /// * `redraw_thread_sender` - Sender for triggering redraws
     /// * `event_loop_proxy` - Event loop proxy for sending cursor position updates
     /// * `clock` - Clock for time tracking
+    /// * `control_gate` - Decides which controllers may take control
     ///
     /// # Returns
     ///
@@ -599,6 +624,7 @@ impl CursorController {
         redraw_thread_sender: Sender<RedrawThreadCommands>,
         event_loop_proxy: EventLoopProxy<UserEvent>,
         clock: Arc<dyn Clock>,
+        control_gate: ControlGate,
         target_process_id: Option<i32>,
         target_window_id: Option<u32>,
     ) -> Result<Self, CursorControllerError> {
@@ -639,6 +665,7 @@ impl CursorController {
             redraw_thread_sender,
             event_loop_proxy,
             clock,
+            control_gate,
         })
     }
 
@@ -669,11 +696,13 @@ impl CursorController {
             }
         }
 
+        let enabled = self.controllers_cursors_enabled
+            && self.control_gate.role(&identity) == ControlRole::Full;
         let mut controller = ControllerCursor::new(
             CursorState::new(self.redraw_thread_sender.clone(), self.clock.clone()),
             identity,
         );
-        if !self.controllers_cursors_enabled {
+        if !enabled {
             controller.disable();
         }
         controllers_cursors.push(controller);
@@ -764,6 +793,10 @@ impl CursorController {
     /// * `click_data` - Complete mouse click information including:
     /// * `identity` - Session ID identifying which controller is clicking
     pub fn mouse_click_controller(&mut self, mut click_data: MouseClickData, identity: &str) {
+        if !self.control_gate.allows(identity) {
+            debug!("mouse_click_controller: {identity} wasn't granted control.");
+            return;
+        }
         let Some(global_position) = self.overlay_window.source_to_global(Position {
             x: click_data.x as f64,
             y: click_data.y as f64,
@@ -845,6 +878,10 @@ impl CursorController {
     /// * `identity` - Session ID identifying which controller is scrolling
     pub fn scroll_controller(&mut self, delta: ScrollDelta, identity: &str) {
         debug!("scroll_controller: {delta:?}");
+        if !self.control_gate.allows(identity) {
+            debug!("scroll_controller: {identity} wasn't granted control.");
+            return;
+        }
 
         let mut control_changed = false;
         let mut controllers_cursors = self.controllers_cursors.lock().unwrap();
@@ -924,7 +961,7 @@ impl CursorController {
 
             let mut any_had_control = false;
             for controller in controllers_cursors.iter_mut() {
-                if enabled {
+                if enabled && self.control_gate.role(&controller.identity) == ControlRole::Full {
                     controller.enable();
                 } else {
                     controller.disable();
@@ -944,6 +981,62 @@ impl CursorController {
         }
     }
 
+    /// Applies the roles in the control gate after the sharer changed them.
+    ///
+    /// Controllers without the `Full` role show in Pointer mode, and give
+    /// control back to the sharer if they had it.
+    pub fn apply_control_roles(&mut self) {
+        log::info!("apply_control_roles");
+        let any_had_control = {
+            let mut controllers_cursors = self.controllers_cursors.lock().unwrap();
+            let mut any_had_control = false;
+            for controller in controllers_cursors.iter_mut() {
+                if self.control_gate.role(&controller.identity) == ControlRole::Full {
+                    if self.controllers_cursors_enabled {
+                        controller.enable();
+                    }
+                    continue;
+                }
+
+                controller.disable();
+                if controller.has_control() {
+                    controller.show();
+                    any_had_control = true;
+                }
+            }
+            any_had_control
+        };
+
+        if any_had_control {
+            let mut sharer_cursor = self.remote_control.sharer_cursor.lock().unwrap();
+            sharer_cursor.hide(false);
+        }
+    }
+
+    /// Gives control back to the sharer if `identity` has it, used when the
+    /// sharer revokes a grant.
+    pub fn release_controller(&mut self, identity: &str) {
+        log::info!("release_controller: {identity}");
+        let had_control = {
+            let mut controllers_cursors = self.controllers_cursors.lock().unwrap();
+            match controllers_cursors
+                .iter_mut()
+                .find(|controller| controller.identity == identity)
+            {
+                Some(controller) if controller.has_control() => {
+                    controller.show();
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        if had_control {
+            let mut sharer_cursor = self.remote_control.sharer_cursor.lock().unwrap();
+            sharer_cursor.hide(false);
+        }
+    }
+
     /// Sets the cursor mode for a specific controller.
     ///
     /// If the controller currently has control, it is given back to the sharer.
@@ -1019,6 +1112,17 @@ impl CursorController {
         self.overlay_window.clone()
     }
 
+    /// Sets up input simulation when the sharer enables remote control and
+    /// tears it down when they disable it. Unlike `set_controllers_enabled`
+    /// this follows the sharer's preference only, not the drawing mode.
+    pub fn set_input_simulation_enabled(&mut self, enabled: bool) {
+        self.remote_control
+            .cursor_simulator
+            .lock()
+            .unwrap()
+            .set_enabled(enabled);
+    }
+
     /// Invalidates the cached NSEvent conversion measurement (macOS only) so
     /// it is re-probed on the next posted event. Called when the capture frame
     /// changes (window moved/resized/changed monitors).
@@ -1,28 +1,369 @@
-#![allow(non_snake_case)]
+use std::collections::HashMap;
+use std::sync::{
+    mpsc::{self, Sender},
+    Arc, Mutex,
+};
+use std::time::Duration;
 
-use super::CursorSimulatorFunctions;
-use crate::{input::mouse::SharerCursor, utils::geometry::Position, MouseClickData, ScrollDelta};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xinput::{
+    self, ConnectionExt as _, DeviceId, DeviceType, EventMask, GrabOwner, XIEventMask,
+};
+use x11rb::protocol::xproto::{
+    ConnectionExt as _, GrabMode, GrabStatus, Window, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT,
+    MOTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
 
-use crate::overlay_window::OverlayWindow;
+use super::{CursorSimulatorFunctions, SharerCursor};
+use crate::input::portal;
+use crate::input::x11::{self, X11Error};
+use crate::{utils::geometry::Position, MouseClickData, ScrollDelta};
 
-use winit::dpi::PhysicalPosition;
+/* How long the observer thread sleeps when it has no X events to process. */
+const OBSERVER_POLL_INTERVAL: Duration = Duration::from_millis(8);
 
-use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+/* Browser wheel deltas are in pixels, X11 scrolls in discrete wheel clicks. */
+const PIXELS_PER_WHEEL_CLICK: f64 = 50.0;
 
-pub struct MouseObserver {}
+const BUTTON_LEFT: u8 = 1;
+const BUTTON_MIDDLE: u8 = 2;
+const BUTTON_RIGHT: u8 = 3;
+const BUTTON_WHEEL_UP: u8 = 4;
+const BUTTON_WHEEL_DOWN: u8 = 5;
+const BUTTON_WHEEL_LEFT: u8 = 6;
+const BUTTON_WHEEL_RIGHT: u8 = 7;
+const BUTTON_BACK: u8 = 8;
+const BUTTON_FORWARD: u8 = 9;
+
+/* linux/input-event-codes.h, the portal takes evdev codes instead of X11 buttons. */
+const BTN_LEFT: i32 = 0x110;
+const BTN_RIGHT: i32 = 0x111;
+const BTN_MIDDLE: i32 = 0x112;
+const BTN_SIDE: i32 = 0x113;
+const BTN_EXTRA: i32 = 0x114;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MouseObserverError {
+    #[error("Failed to connect to the X server: {0}")]
+    Connect(#[from] x11rb::errors::ConnectError),
+    #[error("X11 connection error: {0}")]
+    Connection(#[from] x11rb::errors::ConnectionError),
+    #[error("X11 request failed: {0}")]
+    Reply(#[from] x11rb::errors::ReplyError),
+    #[error("XInput 2.2 is not available")]
+    XInputMissing,
+}
+
+fn fp1616_to_f64(value: i32) -> f64 {
+    value as f64 / 65536.0
+}
+
+/// Returns the slave pointers that belong to physical devices, XTest's
+/// virtual devices are the ones our own simulated events come from.
+fn hardware_pointers(
+    conn: &RustConnection,
+) -> Result<(Vec<DeviceId>, Vec<DeviceId>), MouseObserverError> {
+    let devices = conn
+        .xinput_xi_query_device(xinput::Device::ALL)?
+        .reply()?
+        .infos;
+    let mut hardware = Vec::new();
+    let mut xtest = Vec::new();
+    for device in devices {
+        if device.type_ != DeviceType::SLAVE_POINTER {
+            continue;
+        }
+        if device.name.windows(5).any(|w| w == b"XTEST") {
+            xtest.push(device.deviceid);
+        } else {
+            hardware.push(device.deviceid);
+        }
+    }
+    Ok((hardware, xtest))
+}
+
+/// Watches the sharer's physical pointer through XInput2.
+///
+/// While the sharer has control we only listen to raw motion and forward the
+/// pointer location to `SharerCursor`. Once a controller takes control, the
+/// physical slave pointers are grabbed. A grabbed slave is detached from the
+/// master pointer by the server, so the sharer's hand no longer fights the
+/// controller for the system cursor and its clicks don't reach the windows
+/// under the controller's cursor. The grabbed events move the sharer's virtual
+/// cursor instead, and a click or leaving the shared area hands control back
+/// like on the other platforms.
+///
+/// On Wayland the global pointer can't be observed, XWayland only reports it
+/// over X11 windows, so the observer is inactive there and the sharer can't
+/// take control back by moving the mouse.
+///
+/// LOCK ORDER: same as the other platforms, `sharer_cursor` is locked before
+/// anything `SharerCursor` locks internally.
+pub struct MouseObserver {
+    shutdown_tx: Option<Sender<()>>,
+}
 
 impl MouseObserver {
-    pub fn new(internal: Arc<Mutex<SharerCursor>>) -> Result<Self, ()> {
-        Ok(Self {})
+    pub fn new(sharer_cursor: Arc<Mutex<SharerCursor>>) -> Result<Self, MouseObserverError> {
+        if portal::is_wayland_session() {
+            log::info!("MouseObserver::new: Wayland session, not observing the pointer");
+            return Ok(Self { shutdown_tx: None });
+        }
+
+        let (conn, screen_num) = x11rb::connect(None)?;
+        if conn
+            .extension_information(xinput::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Err(MouseObserverError::XInputMissing);
+        }
+        let version = conn.xinput_xi_query_version(2, 2)?.reply()?;
+        if (version.major_version, version.minor_version) < (2, 2) {
+            return Err(MouseObserverError::XInputMissing);
+        }
+
+        let root = conn.setup().roots[screen_num].root;
+        conn.xinput_xi_select_events(
+            root,
+            &[EventMask {
+                deviceid: xinput::Device::ALL.into(),
+                mask: vec![XIEventMask::RAW_MOTION],
+            }],
+        )?;
+        conn.flush()?;
+
+        let (_, xtest_devices) = hardware_pointers(&conn)?;
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut observer = ObserverThread {
+                conn,
+                root,
+                sharer_cursor,
+                grabbed: Vec::new(),
+                xtest_devices,
+                slave_positions: HashMap::new(),
+                held_buttons: 0,
+                simulated_press: false,
+            };
+            while let Err(mpsc::TryRecvError::Empty) = shutdown_rx.try_recv() {
+                if let Err(e) = observer.step() {
+                    log::error!("MouseObserver: {e}");
+                    break;
+                }
+            }
+            observer.ungrab();
+            log::info!("terminated mouse observer thread");
+        });
+
+        Ok(Self {
+            shutdown_tx: Some(shutdown_tx),
+        })
+    }
+}
+
+impl Drop for MouseObserver {
+    fn drop(&mut self) {
+        let Some(shutdown_tx) = self.shutdown_tx.take() else {
+            return;
+        };
+        if shutdown_tx.send(()).is_err() {
+            log::error!("Failed to send stop event to mouse observer thread");
+        }
+        log::info!("terminated mouse observer");
     }
 }
 
-pub struct CursorSimulator {}
+struct ObserverThread {
+    conn: RustConnection,
+    root: Window,
+    sharer_cursor: Arc<Mutex<SharerCursor>>,
+    /// Hardware slave pointers currently grabbed by us.
+    grabbed: Vec<DeviceId>,
+    xtest_devices: Vec<DeviceId>,
+    /// Last root position of each grabbed (and therefore floating) slave.
+    slave_positions: HashMap<DeviceId, Position>,
+    /// Buttons pressed on the grabbed slaves, we keep the grab until they are released.
+    held_buttons: u32,
+    /// Whether a take-back click was simulated and still needs its release.
+    simulated_press: bool,
+}
+
+impl ObserverThread {
+    fn step(&mut self) -> Result<(), MouseObserverError> {
+        let sharer_has_control = self.sharer_cursor.lock().unwrap().has_control();
+        if !sharer_has_control && self.grabbed.is_empty() {
+            self.grab()?;
+        } else if sharer_has_control && !self.grabbed.is_empty() && self.held_buttons == 0 {
+            self.ungrab();
+        }
+
+        let mut processed = false;
+        while let Some(event) = self.conn.poll_for_event()? {
+            processed = true;
+            self.handle_event(event)?;
+        }
+        if !processed {
+            std::thread::sleep(OBSERVER_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    fn grab(&mut self) -> Result<(), MouseObserverError> {
+        let (hardware, xtest) = hardware_pointers(&self.conn)?;
+        self.xtest_devices = xtest;
+        self.slave_positions.clear();
+        self.held_buttons = 0;
+        self.simulated_press = false;
+        let mask = u32::from(
+            XIEventMask::MOTION | XIEventMask::BUTTON_PRESS | XIEventMask::BUTTON_RELEASE,
+        );
+        for device in hardware {
+            let reply = self
+                .conn
+                .xinput_xi_grab_device(
+                    self.root,
+                    x11rb::CURRENT_TIME,
+                    x11rb::NONE,
+                    device,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                    GrabOwner::NO_OWNER,
+                    &[mask],
+                )?
+                .reply()?;
+            if reply.status == GrabStatus::SUCCESS {
+                self.grabbed.push(device);
+            } else {
+                log::warn!("MouseObserver: grabbing device {device} failed {reply:?}");
+            }
+        }
+        log::debug!("MouseObserver: grabbed {:?}", self.grabbed);
+        Ok(())
+    }
+
+    fn ungrab(&mut self) {
+        for device in self.grabbed.drain(..) {
+            let _ = self
+                .conn
+                .xinput_xi_ungrab_device(x11rb::CURRENT_TIME, device);
+        }
+        let _ = self.conn.flush();
+    }
+
+    fn pointer_position(&self) -> Result<Position, MouseObserverError> {
+        let pointer = self.conn.query_pointer(self.root)?.reply()?;
+        Ok(Position {
+            x: pointer.root_x as f64,
+            y: pointer.root_y as f64,
+        })
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<(), MouseObserverError> {
+        match event {
+            Event::XinputRawMotion(event) => {
+                /* Moves of our own simulated cursor or of a grabbed slave. */
+                if self.xtest_devices.contains(&event.sourceid)
+                    || self.grabbed.contains(&event.sourceid)
+                {
+                    return Ok(());
+                }
+                let mut sharer_cursor = self.sharer_cursor.lock().unwrap();
+                if sharer_cursor.has_control() {
+                    let location = self.pointer_position()?;
+                    sharer_cursor.set_position(location);
+                }
+            }
+            Event::XinputMotion(event) if self.grabbed.contains(&event.deviceid) => {
+                let location = Position {
+                    x: fp1616_to_f64(event.root_x),
+                    y: fp1616_to_f64(event.root_y),
+                };
+                let Some(last_location) = self.slave_positions.insert(event.deviceid, location)
+                else {
+                    return Ok(());
+                };
+                let mut sharer_cursor = self.sharer_cursor.lock().unwrap();
+                if sharer_cursor.has_control() {
+                    return Ok(());
+                }
+                let global_position = sharer_cursor.global_position();
+                sharer_cursor.set_position(Position {
+                    x: global_position.x + location.x - last_location.x,
+                    y: global_position.y + location.y - last_location.y,
+                });
+            }
+            Event::XinputButtonPress(event) if self.grabbed.contains(&event.deviceid) => {
+                let mut sharer_cursor = self.sharer_cursor.lock().unwrap();
+                match event.detail as u8 {
+                    BUTTON_WHEEL_UP..=BUTTON_WHEEL_RIGHT => sharer_cursor.scroll(),
+                    _ => {
+                        self.held_buttons += 1;
+                        if !sharer_cursor.has_control() {
+                            let global_position = sharer_cursor.global_position();
+                            sharer_cursor.click(global_position);
+                            self.simulated_press = true;
+                        }
+                    }
+                }
+            }
+            Event::XinputButtonRelease(event) if self.grabbed.contains(&event.deviceid) => {
+                if (BUTTON_WHEEL_UP..=BUTTON_WHEEL_RIGHT).contains(&(event.detail as u8)) {
+                    return Ok(());
+                }
+                self.held_buttons = self.held_buttons.saturating_sub(1);
+                /*
+                 * The press was replaced by a simulated one at the sharer's
+                 * position, which needs a matching release.
+                 */
+                if self.held_buttons == 0 && self.simulated_press {
+                    self.simulated_press = false;
+                    self.sharer_cursor.lock().unwrap().release_click();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub struct CursorSimulator {
+    /// Scroll distance that didn't add up to a full wheel click yet.
+    pending_scroll: ScrollDelta,
+    /// Input goes through the RemoteDesktop portal instead of XTest.
+    wayland: bool,
+    /// Portal session kept open while remote control is enabled.
+    portal_session: Option<portal::SessionGuard>,
+}
+
+impl Default for CursorSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl CursorSimulator {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            pending_scroll: ScrollDelta { x: 0., y: 0. },
+            wayland: portal::is_wayland_session(),
+            portal_session: None,
+        }
+    }
+
+    /// Opens the portal session on Wayland when the sharer enables remote
+    /// control, and closes it when they disable it.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !self.wayland {
+            return;
+        }
+        if !enabled {
+            self.portal_session = None;
+        } else if self.portal_session.is_none() {
+            self.portal_session = Some(portal::start_session());
+        }
     }
 
     /* macOS only: no conversion measurement is cached on this platform. */
@@ -32,16 +373,187 @@ impl CursorSimulator {
     pub fn has_window_target(&self) -> bool {
         false
     }
+
+    fn move_to(connection: &x11::X11Connection, x: f32, y: f32) -> Result<(), X11Error> {
+        connection.fake_input(MOTION_NOTIFY_EVENT, 0, x as i16, y as i16)
+    }
+
+    fn wheel_clicks(pending: &mut f64, delta: f64) -> i32 {
+        /* Scrolling along the other axis only, keep what's pending on this one. */
+        if delta == 0. {
+            return 0;
+        }
+        /* Drop leftovers from the other direction. */
+        if pending.signum() != delta.signum() {
+            *pending = 0.;
+        }
+        *pending += delta;
+        let clicks = (*pending / PIXELS_PER_WHEEL_CLICK).trunc();
+        *pending -= clicks * PIXELS_PER_WHEEL_CLICK;
+        clicks as i32
+    }
+
+    fn send_wheel(
+        connection: &x11::X11Connection,
+        clicks: i32,
+        negative_button: u8,
+        positive_button: u8,
+    ) -> Result<(), X11Error> {
+        let button = if clicks < 0 {
+            negative_button
+        } else {
+            positive_button
+        };
+        for _ in 0..clicks.unsigned_abs() {
+            connection.fake_input(BUTTON_PRESS_EVENT, button, 0, 0)?;
+            connection.fake_input(BUTTON_RELEASE_EVENT, button, 0, 0)?;
+        }
+        Ok(())
+    }
+}
+
+/*
+ * Browser MouseEvent.button values to X11 buttons, see
+ * https://developer.mozilla.org/en-US/docs/Web/API/MouseEvent/button
+ */
+fn map_button(button: u32) -> u8 {
+    match button {
+        0 => BUTTON_LEFT,
+        2 => BUTTON_RIGHT,
+        3 => BUTTON_BACK,
+        4 => BUTTON_FORWARD,
+        _ => BUTTON_MIDDLE,
+    }
+}
+
+fn map_evdev_button(button: u32) -> i32 {
+    match button {
+        0 => BTN_LEFT,
+        2 => BTN_RIGHT,
+        3 => BTN_SIDE,
+        4 => BTN_EXTRA,
+        _ => BTN_MIDDLE,
+    }
+}
+
+/// Runs `f` on the portal session, logging when there is none yet or the call fails.
+fn with_portal_session(
+    context: &str,
+    f: impl FnOnce(&portal::RemoteDesktopSession) -> Result<(), portal::PortalError>,
+) {
+    let Some(session) = portal::session() else {
+        log::error!("{context}: no remote desktop session");
+        return;
+    };
+    if let Err(e) = f(&session) {
+        log::error!("{context}: {e}");
+    }
 }
 
 impl CursorSimulatorFunctions for CursorSimulator {
-    fn simulate_cursor_movement(&mut self, position: Position, click_down: bool) {
-        log::error!("default_observer.rs: simulate_cursor_movement");
+    fn simulate_cursor_movement(&mut self, position: Position, _click_down: bool) {
+        log::debug!("simulate_cursor_movement: {position:?}");
+        if self.wayland {
+            with_portal_session("simulate_cursor_movement", |session| {
+                session.pointer_motion_absolute(position.x, position.y)
+            });
+            return;
+        }
+        let Some(connection) = x11::connection() else {
+            log::error!("simulate_cursor_movement: no X11 connection");
+            return;
+        };
+        let res = Self::move_to(connection, position.x as f32, position.y as f32)
+            .and_then(|_| connection.flush());
+        if let Err(e) = res {
+            log::error!("simulate_cursor_movement: {e}");
+        }
     }
+
+    /*
+     * X11 has no click count, clients detect double clicks themselves from
+     * consecutive presses at the same location, which is what we replay.
+     */
     fn simulate_click(&mut self, click_data: MouseClickData) {
-        log::error!("default_observer.rs: simulate_click");
+        log::debug!("simulate_click: click_data: {click_data:?}");
+        if self.wayland {
+            with_portal_session("simulate_click", |session| {
+                session.pointer_motion_absolute(click_data.x as f64, click_data.y as f64)?;
+                session.pointer_button(map_evdev_button(click_data.button), click_data.down)
+            });
+            return;
+        }
+        let Some(connection) = x11::connection() else {
+            log::error!("simulate_click: no X11 connection");
+            return;
+        };
+        let event_type = if click_data.down {
+            BUTTON_PRESS_EVENT
+        } else {
+            BUTTON_RELEASE_EVENT
+        };
+        let res = Self::move_to(connection, click_data.x, click_data.y)
+            .and_then(|_| connection.fake_input(event_type, map_button(click_data.button), 0, 0))
+            .and_then(|_| connection.flush());
+        if let Err(e) = res {
+            log::error!("simulate_click: {e}");
+        }
     }
+
     fn simulate_scroll(&mut self, delta: ScrollDelta) {
-        log::error!("default_observer.rs: simulate_scroll");
+        log::debug!("simulate_scroll: delta: {delta:?}");
+        if self.wayland {
+            /* The portal scrolls in pixels like the browser, no wheel clicks needed. */
+            with_portal_session("simulate_scroll", |session| {
+                session.pointer_axis(delta.x, delta.y)
+            });
+            return;
+        }
+        let Some(connection) = x11::connection() else {
+            log::error!("simulate_scroll: no X11 connection");
+            return;
+        };
+        let clicks_x = Self::wheel_clicks(&mut self.pending_scroll.x, delta.x);
+        let clicks_y = Self::wheel_clicks(&mut self.pending_scroll.y, delta.y);
+        let res = Self::send_wheel(connection, clicks_y, BUTTON_WHEEL_UP, BUTTON_WHEEL_DOWN)
+            .and_then(|_| {
+                Self::send_wheel(connection, clicks_x, BUTTON_WHEEL_LEFT, BUTTON_WHEEL_RIGHT)
+            })
+            .and_then(|_| connection.flush());
+        if let Err(e) = res {
+            log::error!("simulate_scroll: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_clicks_accumulate_small_deltas() {
+        let mut pending = 0.;
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 20.), 0);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 20.), 0);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 20.), 1);
+        assert_eq!(pending, 10.);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 120.), 2);
+    }
+
+    #[test]
+    fn test_wheel_clicks_reset_on_direction_change() {
+        let mut pending = 0.;
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 40.), 0);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, -40.), 0);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, -20.), -1);
+    }
+
+    #[test]
+    fn test_wheel_clicks_keep_pending_on_zero_delta() {
+        let mut pending = 0.;
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, -40.), 0);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, 0.), 0);
+        assert_eq!(pending, -40.);
+        assert_eq!(CursorSimulator::wheel_clicks(&mut pending, -20.), -1);
     }
 }
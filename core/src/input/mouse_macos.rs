@@ -308,6 +308,9 @@ impl CursorSimulator {
         self.sender_flip_height = None;
     }
 
+    /* Linux only: there is no input session to open or close on this platform. */
+    pub fn set_enabled(&mut self, _enabled: bool) {}
+
     /// Whether events are being delivered directly to a shared window's
     /// process (window sharing with pinned delivery) rather than system-wide.
     pub fn has_window_target(&self) -> bool {
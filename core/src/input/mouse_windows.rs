@@ -316,6 +316,9 @@ impl CursorSimulator {
         }
     }
 
+    /* Linux only: there is no input session to open or close on this platform. */
+    pub fn set_enabled(&mut self, _enabled: bool) {}
+
     /* macOS only: no conversion measurement is cached on this platform. */
     pub fn invalidate_sender_flip_height(&mut self) {}
 
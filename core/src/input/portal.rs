@@ -0,0 +1,790 @@
+//! Remote control on Wayland through the `org.freedesktop.portal.RemoteDesktop` portal.
+//!
+//! Wayland compositors don't accept XTest, instead the compositor injects the
+//! input for us inside a portal session the sharer approved. The session also
+//! selects the monitors through the ScreenCast interface, because absolute
+//! pointer motion is expressed relative to one of the session's streams.
+//!
+//! The portal hands out a restore token when a session starts. It is persisted
+//! so the next session is restored without asking the sharer again.
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use zbus::blocking::{proxy, Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const REMOTE_DESKTOP_INTERFACE: &str = "org.freedesktop.portal.RemoteDesktop";
+const SCREENCAST_INTERFACE: &str = "org.freedesktop.portal.ScreenCast";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+
+const DEVICE_KEYBOARD: u32 = 1;
+const DEVICE_POINTER: u32 = 2;
+const SOURCE_MONITOR: u32 = 1;
+/* Keep the permission until the sharer revokes it, not only for this app run. */
+const PERSIST_UNTIL_REVOKED: u32 = 2;
+
+const RESPONSE_SUCCESS: u32 = 0;
+const RESPONSE_CANCELLED: u32 = 1;
+
+const RESTORE_TOKEN_FILE: &str = "com.hopp.app/remote_desktop_restore_token";
+
+type Results = HashMap<String, OwnedValue>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PortalError {
+    #[error("D-Bus error: {0}")]
+    DBus(#[from] zbus::Error),
+    #[error("Invalid portal reply: {0}")]
+    Variant(#[from] zbus::zvariant::Error),
+    #[error("The sharer cancelled the remote desktop request")]
+    Cancelled,
+    #[error("The portal request failed with response {0}")]
+    Failed(u32),
+    #[error("The portal response has no {0}")]
+    MissingResult(&'static str),
+}
+
+/// Returns whether input has to go through the portal instead of XTest.
+pub fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+}
+
+/// Where the restore token of the last session is kept between runs.
+pub struct RestoreTokenStore {
+    path: Option<PathBuf>,
+}
+
+impl Default for RestoreTokenStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RestoreTokenStore {
+    pub fn new() -> Self {
+        Self {
+            path: dirs::data_local_dir().map(|dir| dir.join(RESTORE_TOKEN_FILE)),
+        }
+    }
+
+    fn load(&self) -> Option<String> {
+        let token = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        let token = token.trim();
+        (!token.is_empty()).then(|| token.to_string())
+    }
+
+    /// Tokens are single use, every started session replaces the stored one.
+    fn save(&self, token: Option<&str>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let res = match token {
+            Some(token) => path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(path, token)),
+            None => match std::fs::remove_file(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            },
+        };
+        if let Err(e) = res {
+            log::warn!("RestoreTokenStore::save: {}: {e}", path.display());
+        }
+    }
+}
+
+/// A monitor shared with the session, in compositor coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stream {
+    node_id: u32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Stream {
+    fn parse(node_id: u32, properties: &HashMap<String, OwnedValue>) -> Self {
+        let pair = |key: &str| {
+            properties
+                .get(key)
+                .and_then(|value| value.downcast_ref::<(i32, i32)>().ok())
+                .unwrap_or_default()
+        };
+        let (x, y) = pair("position");
+        let (width, height) = pair("size");
+        Self {
+            node_id,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && y >= self.y as f64
+            && x < (self.x + self.width) as f64
+            && y < (self.y + self.height) as f64
+    }
+}
+
+/// Finds the stream showing the global position `(x, y)` and returns its node
+/// id with the position relative to the stream.
+fn stream_position(streams: &[Stream], x: f64, y: f64) -> Option<(u32, f64, f64)> {
+    /* With a single stream the portal may not report its position, it covers everything. */
+    if let [stream] = streams {
+        return Some((stream.node_id, x - stream.x as f64, y - stream.y as f64));
+    }
+    streams
+        .iter()
+        .find(|stream| stream.contains(x, y))
+        .map(|stream| (stream.node_id, x - stream.x as f64, y - stream.y as f64))
+}
+
+fn handle_token() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    format!("hopp{}", COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+fn no_options() -> HashMap<&'static str, Value<'static>> {
+    HashMap::new()
+}
+
+/// An approved RemoteDesktop session, closed when dropped.
+pub struct RemoteDesktopSession {
+    connection: Connection,
+    remote_desktop: Proxy<'static>,
+    handle: OwnedObjectPath,
+    streams: Vec<Stream>,
+}
+
+impl RemoteDesktopSession {
+    /// Creates and starts a session with keyboard and pointer access to every
+    /// monitor. Blocks until the sharer answers the portal dialog, or returns
+    /// right away when the stored restore token is still valid.
+    pub fn start(connection: Connection, tokens: &RestoreTokenStore) -> Result<Self, PortalError> {
+        let remote_desktop = Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            REMOTE_DESKTOP_INTERFACE,
+        )?;
+        let screencast = Proxy::new(
+            &connection,
+            PORTAL_DESTINATION,
+            PORTAL_PATH,
+            SCREENCAST_INTERFACE,
+        )?;
+
+        let token = handle_token();
+        let results = Self::request(&connection, &token, || {
+            let options = HashMap::from([
+                ("handle_token", Value::from(token.as_str())),
+                ("session_handle_token", Value::from(token.as_str())),
+            ]);
+            remote_desktop.call_method("CreateSession", &(options,))
+        })?;
+        let handle = Self::session_handle(&results)?;
+        let mut session = Self {
+            connection,
+            remote_desktop,
+            handle,
+            streams: Vec::new(),
+        };
+
+        let restore_token = tokens.load();
+        let token = handle_token();
+        Self::request(&session.connection, &token, || {
+            let mut options = HashMap::from([
+                ("handle_token", Value::from(token.as_str())),
+                ("types", Value::from(DEVICE_KEYBOARD | DEVICE_POINTER)),
+                ("persist_mode", Value::from(PERSIST_UNTIL_REVOKED)),
+            ]);
+            if let Some(restore_token) = &restore_token {
+                options.insert("restore_token", Value::from(restore_token.as_str()));
+            }
+            session
+                .remote_desktop
+                .call_method("SelectDevices", &(&session.handle, options))
+        })?;
+
+        /* Persistence is set on SelectDevices, the sources of a remote desktop session follow it. */
+        let token = handle_token();
+        Self::request(&session.connection, &token, || {
+            let options = HashMap::from([
+                ("handle_token", Value::from(token.as_str())),
+                ("types", Value::from(SOURCE_MONITOR)),
+                ("multiple", Value::from(true)),
+            ]);
+            screencast.call_method("SelectSources", &(&session.handle, options))
+        })?;
+
+        let token = handle_token();
+        let results = Self::request(&session.connection, &token, || {
+            let options = HashMap::from([("handle_token", Value::from(token.as_str()))]);
+            session
+                .remote_desktop
+                .call_method("Start", &(&session.handle, "", options))
+        })?;
+        tokens.save(
+            results
+                .get("restore_token")
+                .and_then(|value| value.downcast_ref::<&str>().ok()),
+        );
+        if let Some(streams) = results.get("streams") {
+            let streams: Vec<(u32, HashMap<String, OwnedValue>)> =
+                streams.try_clone()?.try_into()?;
+            session.streams = streams
+                .iter()
+                .map(|(node_id, properties)| Stream::parse(*node_id, properties))
+                .collect();
+        }
+        log::info!(
+            "RemoteDesktopSession::start: restored: {}, streams: {:?}",
+            restore_token.is_some(),
+            session.streams
+        );
+        Ok(session)
+    }
+
+    /// Runs a portal method that answers through a `Request` object and waits
+    /// for its `Response`.
+    ///
+    /// The request path is derived from our unique name and the handle token,
+    /// so the signal is subscribed before the call and can't be missed.
+    fn request<F>(connection: &Connection, token: &str, call: F) -> Result<Results, PortalError>
+    where
+        F: FnOnce() -> zbus::Result<zbus::Message>,
+    {
+        let sender = connection
+            .unique_name()
+            .ok_or(PortalError::MissingResult("unique name"))?
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let path = format!("{PORTAL_PATH}/request/{sender}/{token}");
+        let request: Proxy = proxy::Builder::new(connection)
+            .destination(PORTAL_DESTINATION)?
+            .path(path)?
+            .interface(REQUEST_INTERFACE)?
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()?;
+        let mut responses = request.receive_signal("Response")?;
+
+        call()?;
+        let response = responses
+            .next()
+            .ok_or(PortalError::MissingResult("response"))?;
+        let (code, results): (u32, Results) = response.body().deserialize()?;
+        match code {
+            RESPONSE_SUCCESS => Ok(results),
+            RESPONSE_CANCELLED => Err(PortalError::Cancelled),
+            code => Err(PortalError::Failed(code)),
+        }
+    }
+
+    /* Recent portals return the handle as a string, older ones as an object path. */
+    fn session_handle(results: &Results) -> Result<OwnedObjectPath, PortalError> {
+        let value = results
+            .get("session_handle")
+            .ok_or(PortalError::MissingResult("session_handle"))?;
+        let path = match value.downcast_ref::<&str>() {
+            Ok(handle) => ObjectPath::try_from(handle)?.into(),
+            Err(_) => value.downcast_ref::<ObjectPath>()?.into(),
+        };
+        Ok(path)
+    }
+
+    /*
+     * The notify calls don't wait for a reply. Input is sent at a high rate and
+     * D-Bus keeps the messages ordered, errors only show up in the portal logs.
+     */
+    fn notify<B>(&self, method: &str, body: &B) -> Result<(), PortalError>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.remote_desktop.call_noreply(method, body)?;
+        Ok(())
+    }
+
+    /// Moves the pointer to a global position. Positions outside every shared
+    /// monitor are dropped.
+    pub fn pointer_motion_absolute(&self, x: f64, y: f64) -> Result<(), PortalError> {
+        let Some((stream, x, y)) = stream_position(&self.streams, x, y) else {
+            log::warn!("pointer_motion_absolute: ({x}, {y}) is outside the shared monitors");
+            return Ok(());
+        };
+        self.notify(
+            "NotifyPointerMotionAbsolute",
+            &(&self.handle, no_options(), stream, x, y),
+        )
+    }
+
+    /// `button` is a linux evdev button code (`BTN_LEFT`, ...).
+    pub fn pointer_button(&self, button: i32, pressed: bool) -> Result<(), PortalError> {
+        self.notify(
+            "NotifyPointerButton",
+            &(&self.handle, no_options(), button, u32::from(pressed)),
+        )
+    }
+
+    /// Smooth scroll in pixels, each call is a complete scroll gesture.
+    pub fn pointer_axis(&self, dx: f64, dy: f64) -> Result<(), PortalError> {
+        let options = HashMap::from([("finish", Value::from(true))]);
+        self.notify("NotifyPointerAxis", &(&self.handle, options, dx, dy))
+    }
+
+    /// `keycode` is a linux evdev key code, X11 keycodes minus 8.
+    pub fn keyboard_keycode(&self, keycode: i32, pressed: bool) -> Result<(), PortalError> {
+        self.notify(
+            "NotifyKeyboardKeycode",
+            &(&self.handle, no_options(), keycode, u32::from(pressed)),
+        )
+    }
+
+    /// Types `keysym`, the compositor picks the key and level producing it.
+    pub fn keyboard_keysym(&self, keysym: u32, pressed: bool) -> Result<(), PortalError> {
+        self.notify(
+            "NotifyKeyboardKeysym",
+            &(
+                &self.handle,
+                no_options(),
+                keysym as i32,
+                u32::from(pressed),
+            ),
+        )
+    }
+}
+
+impl Drop for RemoteDesktopSession {
+    fn drop(&mut self) {
+        let res = Proxy::new(
+            &self.connection,
+            PORTAL_DESTINATION,
+            self.handle.as_ref(),
+            SESSION_INTERFACE,
+        )
+        .and_then(|session| session.call_noreply("Close", &()));
+        if let Err(e) = res {
+            log::warn!("RemoteDesktopSession: failed to close session: {e}");
+        }
+    }
+}
+
+/// The running session, tagged with the `start_session` call it belongs to.
+struct SessionSlot {
+    generation: u64,
+    session: Option<Arc<RemoteDesktopSession>>,
+}
+
+static SESSION: Mutex<SessionSlot> = Mutex::new(SessionSlot {
+    generation: 0,
+    session: None,
+});
+
+/// Keeps the session started by `start_session` open, dropping it closes the session.
+pub struct SessionGuard {
+    generation: u64,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let session = {
+            let mut slot = SESSION.lock().unwrap();
+            if slot.generation != self.generation {
+                return;
+            }
+            /* A session that is still waiting for the sharer is closed once it starts. */
+            slot.generation += 1;
+            slot.session.take()
+        };
+        drop(session);
+    }
+}
+
+/// Starts a new portal session in the background, replacing the previous one.
+///
+/// Called when remote control gets enabled for a share. The portal may show
+/// a dialog, input arriving before the sharer answered it is dropped.
+#[must_use = "the session is closed when the guard is dropped"]
+pub fn start_session() -> SessionGuard {
+    let (generation, previous) = {
+        let mut slot = SESSION.lock().unwrap();
+        slot.generation += 1;
+        (slot.generation, slot.session.take())
+    };
+    drop(previous);
+    let res = std::thread::Builder::new()
+        .name("remote-desktop-portal".to_string())
+        .spawn(move || {
+            let session = Connection::session()
+                .map_err(PortalError::from)
+                .and_then(|connection| {
+                    RemoteDesktopSession::start(connection, &RestoreTokenStore::new())
+                });
+            let session = match session {
+                Ok(session) => session,
+                Err(e) => {
+                    log::error!("start_session: {e}");
+                    return;
+                }
+            };
+            let mut slot = SESSION.lock().unwrap();
+            if slot.generation == generation {
+                slot.session = Some(Arc::new(session));
+            } else {
+                drop(slot);
+                log::info!("start_session: remote control ended before the session started");
+            }
+        });
+    if let Err(e) = res {
+        log::error!("start_session: failed to spawn portal thread: {e}");
+    }
+    SessionGuard { generation }
+}
+
+/// Returns the current portal session, `None` until the sharer approved it.
+pub fn session() -> Option<Arc<RemoteDesktopSession>> {
+    SESSION.lock().unwrap().session.clone()
+}
+
+/*
+ * The mock portal runs on a private bus, these tests need `dbus-daemon` and
+ * return early without it.
+ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+    use zbus::message::Header;
+    use zbus::object_server::SignalEmitter;
+
+    const NODE_ID: u32 = 42;
+
+    #[derive(Default)]
+    struct PortalState {
+        restore_tokens: Vec<Option<String>>,
+        issued_tokens: u32,
+        notifications: Vec<String>,
+        closed: u32,
+    }
+
+    type SharedState = Arc<Mutex<PortalState>>;
+
+    struct MockRemoteDesktop {
+        state: SharedState,
+    }
+
+    struct MockScreenCast;
+
+    struct MockSession {
+        state: SharedState,
+    }
+
+    /// Answers a portal request the way xdg-desktop-portal does, with a
+    /// `Response` signal on the request object of the caller.
+    async fn respond(
+        emitter: &SignalEmitter<'_>,
+        header: &Header<'_>,
+        options: &HashMap<String, OwnedValue>,
+        results: HashMap<&str, Value<'_>>,
+    ) -> OwnedObjectPath {
+        let sender = header
+            .sender()
+            .unwrap()
+            .trim_start_matches(':')
+            .replace('.', "_");
+        let token = options["handle_token"].downcast_ref::<&str>().unwrap();
+        let path = format!("{PORTAL_PATH}/request/{sender}/{token}");
+        emitter
+            .connection()
+            .emit_signal(
+                header.sender().map(|sender| sender.as_str()),
+                path.as_str(),
+                REQUEST_INTERFACE,
+                "Response",
+                &(RESPONSE_SUCCESS, results),
+            )
+            .await
+            .unwrap();
+        ObjectPath::try_from(path).unwrap().into()
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.RemoteDesktop")]
+    impl MockRemoteDesktop {
+        async fn create_session(
+            &self,
+            options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> OwnedObjectPath {
+            let token = options["session_handle_token"]
+                .downcast_ref::<&str>()
+                .unwrap();
+            let handle = format!("{PORTAL_PATH}/session/mock/{token}");
+            let session = MockSession {
+                state: self.state.clone(),
+            };
+            emitter
+                .connection()
+                .object_server()
+                .at(handle.as_str(), session)
+                .await
+                .unwrap();
+            let results = HashMap::from([("session_handle", Value::from(handle.as_str()))]);
+            respond(&emitter, &header, &options, results).await
+        }
+
+        async fn select_devices(
+            &self,
+            _session: OwnedObjectPath,
+            options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> OwnedObjectPath {
+            let restore_token = options
+                .get("restore_token")
+                .map(|token| token.downcast_ref::<&str>().unwrap().to_string());
+            self.state
+                .lock()
+                .unwrap()
+                .restore_tokens
+                .push(restore_token);
+            respond(&emitter, &header, &options, HashMap::new()).await
+        }
+
+        async fn start(
+            &self,
+            _session: OwnedObjectPath,
+            _parent_window: &str,
+            options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> OwnedObjectPath {
+            let restore_token = {
+                let mut state = self.state.lock().unwrap();
+                state.issued_tokens += 1;
+                format!("token-{}", state.issued_tokens)
+            };
+            let properties = HashMap::from([
+                ("position", Value::from((1920i32, 0i32))),
+                ("size", Value::from((1280i32, 720i32))),
+            ]);
+            let results = HashMap::from([
+                ("devices", Value::from(DEVICE_KEYBOARD | DEVICE_POINTER)),
+                ("restore_token", Value::from(restore_token)),
+                ("streams", Value::from(vec![(NODE_ID, properties)])),
+            ]);
+            respond(&emitter, &header, &options, results).await
+        }
+
+        fn notify_pointer_motion_absolute(
+            &self,
+            _session: OwnedObjectPath,
+            _options: HashMap<String, OwnedValue>,
+            stream: u32,
+            x: f64,
+            y: f64,
+        ) {
+            self.state
+                .lock()
+                .unwrap()
+                .notifications
+                .push(format!("motion {stream} {x} {y}"));
+        }
+
+        fn notify_keyboard_keycode(
+            &self,
+            _session: OwnedObjectPath,
+            _options: HashMap<String, OwnedValue>,
+            keycode: i32,
+            state: u32,
+        ) {
+            self.state
+                .lock()
+                .unwrap()
+                .notifications
+                .push(format!("key {keycode} {state}"));
+        }
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.ScreenCast")]
+    impl MockScreenCast {
+        async fn select_sources(
+            &self,
+            _session: OwnedObjectPath,
+            options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> OwnedObjectPath {
+            respond(&emitter, &header, &options, HashMap::new()).await
+        }
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.Session")]
+    impl MockSession {
+        fn close(&self) {
+            self.state.lock().unwrap().closed += 1;
+        }
+    }
+
+    /// A `dbus-daemon` running a private session bus, killed when dropped.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn new() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> Connection {
+            zbus::blocking::connection::Builder::address(self.address.as_str())
+                .unwrap()
+                .build()
+                .unwrap()
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn mock_portal(bus: &PrivateBus, state: &SharedState) -> Connection {
+        zbus::blocking::connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name(PORTAL_DESTINATION)
+            .unwrap()
+            .serve_at(
+                PORTAL_PATH,
+                MockRemoteDesktop {
+                    state: state.clone(),
+                },
+            )
+            .unwrap()
+            .serve_at(PORTAL_PATH, MockScreenCast)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    /// Waits for the mock to have received `count` notifications, the notify
+    /// calls don't wait for a reply.
+    fn wait_for_notifications(state: &SharedState, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while state.lock().unwrap().notifications.len() < count && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        state.lock().unwrap().notifications.clone()
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon"]
+    fn test_session_persists_restore_token() {
+        let bus = PrivateBus::new().expect("dbus-daemon");
+        let state = SharedState::default();
+        let _portal = mock_portal(&bus, &state);
+
+        let token_path =
+            std::env::temp_dir().join(format!("hopp-restore-token-{}", std::process::id()));
+        let _ = std::fs::remove_file(&token_path);
+        let tokens = RestoreTokenStore {
+            path: Some(token_path.clone()),
+        };
+
+        let session = RemoteDesktopSession::start(bus.connect(), &tokens).unwrap();
+        assert_eq!(tokens.load().as_deref(), Some("token-1"));
+        drop(session);
+
+        /* The second session hands the stored token back and stores the new one. */
+        let _session = RemoteDesktopSession::start(bus.connect(), &tokens).unwrap();
+        assert_eq!(tokens.load().as_deref(), Some("token-2"));
+        let state = state.lock().unwrap();
+        assert_eq!(
+            state.restore_tokens,
+            vec![None, Some("token-1".to_string())]
+        );
+
+        std::fs::remove_file(&token_path).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon"]
+    fn test_input_is_sent_relative_to_stream() {
+        let bus = PrivateBus::new().expect("dbus-daemon");
+        let state = SharedState::default();
+        let _portal = mock_portal(&bus, &state);
+
+        let session =
+            RemoteDesktopSession::start(bus.connect(), &RestoreTokenStore { path: None }).unwrap();
+        session.pointer_motion_absolute(2000., 100.).unwrap();
+        session.keyboard_keycode(30, true).unwrap();
+        session.keyboard_keycode(30, false).unwrap();
+
+        let notifications = wait_for_notifications(&state, 3);
+        assert_eq!(
+            notifications,
+            vec![
+                format!("motion {NODE_ID} 80 100"),
+                "key 30 1".to_string(),
+                "key 30 0".to_string(),
+            ]
+        );
+
+        drop(session);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while state.lock().unwrap().closed == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(state.lock().unwrap().closed, 1);
+    }
+
+    #[test]
+    fn test_stream_position() {
+        let left = Stream {
+            node_id: 1,
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let right = Stream {
+            node_id: 2,
+            x: 1920,
+            y: 0,
+            width: 1280,
+            height: 720,
+        };
+        let streams = [left, right];
+        assert_eq!(stream_position(&streams, 10., 20.), Some((1, 10., 20.)));
+        assert_eq!(stream_position(&streams, 1930., 20.), Some((2, 10., 20.)));
+        assert_eq!(stream_position(&streams, 1930., 900.), None);
+        assert_eq!(stream_position(&[right], 10., 20.), Some((2, -1910., 20.)));
+    }
+}
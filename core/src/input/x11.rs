@@ -0,0 +1,376 @@
+//! Shared X11 plumbing for the Linux input backends.
+//!
+//! Keyboard injection and cursor simulation both go through XTest on a single
+//! process-wide connection. Keeping one connection avoids paying the X11 setup
+//! cost for every synthesized event and keeps the requests from the keyboard
+//! and mouse paths ordered relative to each other.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::randr::{self, ConnectionExt as _};
+use x11rb::protocol::xkb::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, Keycode, Keysym, Window};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+pub const NO_SYMBOL: Keysym = 0;
+
+pub const XK_SHIFT_L: Keysym = 0xffe1;
+pub const XK_CONTROL_L: Keysym = 0xffe3;
+pub const XK_ALT_L: Keysym = 0xffe9;
+pub const XK_SUPER_L: Keysym = 0xffeb;
+
+/* Root window property where the server publishes the RMLVO names its keymap was built from. */
+const XKB_RULES_NAMES_PROPERTY: &[u8] = b"_XKB_RULES_NAMES";
+
+/* Keysyms in this range map 1:1 to the unicode code point, see X11/keysymdef.h. */
+const UNICODE_KEYSYM_OFFSET: Keysym = 0x0100_0000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum X11Error {
+    #[error("Failed to connect to the X server: {0}")]
+    Connect(#[from] x11rb::errors::ConnectError),
+    #[error("X11 connection error: {0}")]
+    Connection(#[from] x11rb::errors::ConnectionError),
+    #[error("X11 request failed: {0}")]
+    Reply(#[from] x11rb::errors::ReplyError),
+    #[error("XTest extension is not available")]
+    XTestMissing,
+    #[error("XKB extension is not available")]
+    XkbMissing,
+    #[error("No X11 connection")]
+    NotConnected,
+}
+
+pub struct X11Connection {
+    conn: RustConnection,
+    root: Window,
+    xkb: bool,
+    rules_names_atom: Atom,
+    /* Bumped for every XKB event saying the layout may have changed. */
+    layout_generation: AtomicU64,
+}
+
+impl X11Connection {
+    pub fn new() -> Result<Self, X11Error> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        if conn
+            .extension_information(x11rb::protocol::xtest::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Err(X11Error::XTestMissing);
+        }
+        let root = conn.setup().roots[screen_num].root;
+        /* XKB has to be enabled once per connection before any of its requests are used. */
+        let xkb = conn
+            .xkb_use_extension(1, 0)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|reply| reply.supported);
+        if !xkb {
+            log::warn!(
+                "X11Connection::new: XKB is not available, layout changes won't be detected"
+            );
+        } else if let Err(e) = select_layout_events(&conn) {
+            log::warn!("X11Connection::new: layout changes won't be detected: {e}");
+        }
+        let rules_names_atom = conn
+            .intern_atom(false, XKB_RULES_NAMES_PROPERTY)?
+            .reply()?
+            .atom;
+        Ok(Self {
+            conn,
+            root,
+            xkb,
+            rules_names_atom,
+            layout_generation: AtomicU64::new(0),
+        })
+    }
+
+    /// Sends a single XTest event. `event_type` is one of the core protocol
+    /// event codes (`KEY_PRESS_EVENT`, `BUTTON_PRESS_EVENT`, ...).
+    pub fn fake_input(&self, event_type: u8, detail: u8, x: i16, y: i16) -> Result<(), X11Error> {
+        self.conn
+            .xtest_fake_input(event_type, detail, x11rb::CURRENT_TIME, self.root, x, y, 0)?;
+        Ok(())
+    }
+
+    pub fn flush(&self) -> Result<(), X11Error> {
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Round trips to the server, guaranteeing every request sent so far was processed.
+    pub fn sync(&self) -> Result<(), X11Error> {
+        self.conn.sync()?;
+        Ok(())
+    }
+
+    pub fn keyboard_mapping(&self) -> Result<KeyboardMapping, X11Error> {
+        let setup = self.conn.setup();
+        let min_keycode = setup.min_keycode;
+        let max_keycode = setup.max_keycode;
+        let reply = self
+            .conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?
+            .reply()?;
+        Ok(KeyboardMapping {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    pub fn change_keyboard_mapping(
+        &self,
+        keycode: Keycode,
+        keysyms: &[Keysym],
+    ) -> Result<(), X11Error> {
+        self.conn
+            .change_keyboard_mapping(1, keycode, keysyms.len() as u8, keysyms)?;
+        Ok(())
+    }
+
+    /// Returns whether the server currently considers `keycode` held down.
+    pub fn is_key_down(&self, keycode: Keycode) -> Result<bool, X11Error> {
+        let keys = self.conn.query_keymap()?.reply()?.keys;
+        Ok(keys[keycode as usize / 8] & (1 << (keycode % 8)) != 0)
+    }
+
+    /// Reads the rules, model, layout, variant and options the server keymap was
+    /// compiled from. Returns `None` when the property isn't set.
+    pub fn xkb_rules_names(&self) -> Result<Option<XkbRulesNames>, X11Error> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.rules_names_atom,
+                AtomEnum::STRING,
+                0,
+                1024,
+            )?
+            .reply()?;
+        Ok(XkbRulesNames::parse(&reply.value))
+    }
+
+    /// Returns the index of the active layout group of the core keyboard.
+    pub fn xkb_group(&self) -> Result<u32, X11Error> {
+        if !self.xkb {
+            return Err(X11Error::XkbMissing);
+        }
+        let state = self
+            .conn
+            .xkb_get_state(xkb::ID::USE_CORE_KBD.into())?
+            .reply()?;
+        Ok(u8::from(state.group).into())
+    }
+
+    /// The XRandR monitors of the screen with the names of their atoms.
+    pub fn randr_monitors(&self) -> Result<Vec<(randr::MonitorInfo, String)>, X11Error> {
+        let reply = self.conn.randr_get_monitors(self.root, true)?.reply()?;
+        /* Send every name request before waiting on the first answer. */
+        let cookies = reply
+            .monitors
+            .iter()
+            .map(|info| self.conn.get_atom_name(info.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut monitors = Vec::with_capacity(reply.monitors.len());
+        for (info, cookie) in reply.monitors.into_iter().zip(cookies) {
+            let name = String::from_utf8_lossy(&cookie.reply()?.name).into_owned();
+            monitors.push((info, name));
+        }
+        Ok(monitors)
+    }
+
+    /// Goes up whenever the server reports a new keymap or a group switch, only
+    /// reads the events already queued so it never waits on the server.
+    pub fn layout_generation(&self) -> u64 {
+        loop {
+            match self.conn.poll_for_event() {
+                Ok(Some(Event::XkbNewKeyboardNotify(_) | Event::XkbStateNotify(_))) => {
+                    self.layout_generation.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) => {
+                    log::error!("layout_generation: failed to read events: {e}");
+                    break;
+                }
+            }
+        }
+        self.layout_generation.load(Ordering::Relaxed)
+    }
+}
+
+/// Asks for the XKB events sent when the keymap is replaced or the active
+/// group changes, see `X11Connection::layout_generation`.
+fn select_layout_events(conn: &RustConnection) -> Result<(), X11Error> {
+    let all_details =
+        xkb::NKNDetail::KEYCODES | xkb::NKNDetail::GEOMETRY | xkb::NKNDetail::DEVICE_ID;
+    let details = xkb::SelectEventsAux::new()
+        .new_keyboard_notify(xkb::SelectEventsAuxNewKeyboardNotify {
+            affect_new_keyboard: all_details,
+            new_keyboard_details: all_details,
+        })
+        .state_notify(xkb::SelectEventsAuxStateNotify {
+            affect_state: xkb::StatePart::GROUP_STATE,
+            state_details: xkb::StatePart::GROUP_STATE,
+        });
+    let none = xkb::EventType::from(0u16);
+    let no_map = xkb::MapPart::from(0u16);
+    conn.xkb_select_events(
+        xkb::ID::USE_CORE_KBD.into(),
+        none,
+        none,
+        no_map,
+        no_map,
+        &details,
+    )?
+    .check()?;
+    Ok(())
+}
+
+/// The RMLVO names published in `_XKB_RULES_NAMES`, which are enough to
+/// compile the same keymap the server is using with xkbcommon.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XkbRulesNames {
+    pub rules: String,
+    pub model: String,
+    pub layout: String,
+    pub variant: String,
+    pub options: String,
+}
+
+impl XkbRulesNames {
+    /// Parses the property value, five NUL separated strings in RMLVO order.
+    fn parse(value: &[u8]) -> Option<Self> {
+        if value.is_empty() {
+            return None;
+        }
+        let mut fields = value
+            .split(|b| *b == 0)
+            .map(|field| String::from_utf8_lossy(field).into_owned());
+        let mut next = || fields.next().unwrap_or_default();
+        Some(Self {
+            rules: next(),
+            model: next(),
+            layout: next(),
+            variant: next(),
+            options: next(),
+        })
+    }
+}
+
+static CONNECTION: OnceLock<Option<X11Connection>> = OnceLock::new();
+
+/// Returns the process-wide X11 connection used for input injection.
+///
+/// The connection is opened on first use. If it can't be established (no
+/// `DISPLAY`, missing XTest) the failure is logged once and every later call
+/// returns `None`.
+pub fn connection() -> Option<&'static X11Connection> {
+    CONNECTION
+        .get_or_init(|| match X11Connection::new() {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                log::error!("x11 connection: {e}");
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// A snapshot of the server's core keycode → keysym table.
+pub struct KeyboardMapping {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+    pub fn keysyms(&self, keycode: Keycode) -> &[Keysym] {
+        if keycode < self.min_keycode {
+            return &[];
+        }
+        let per = self.keysyms_per_keycode as usize;
+        let start = (keycode - self.min_keycode) as usize * per;
+        self.keysyms.get(start..start + per).unwrap_or(&[])
+    }
+
+    fn keycodes(&self) -> impl DoubleEndedIterator<Item = Keycode> + '_ {
+        let count = self.keysyms.len() / (self.keysyms_per_keycode.max(1) as usize);
+        (0..count).map(move |i| self.min_keycode + i as Keycode)
+    }
+
+    /// Finds the first keycode that produces `keysym` on its unshifted level.
+    pub fn keycode_for_keysym(&self, keysym: Keysym) -> Option<Keycode> {
+        self.keycodes()
+            .find(|&keycode| self.keysyms(keycode).first() == Some(&keysym))
+    }
+
+    /// Finds a keycode that produces `c` on the group 1 level selected by `shift`.
+    pub fn keycode_for_char(&self, c: char, shift: bool) -> Option<Keycode> {
+        let level = usize::from(shift);
+        self.keycodes().find(|&keycode| {
+            self.keysyms(keycode)
+                .get(level)
+                .and_then(|keysym| keysym_to_char(*keysym))
+                == Some(c)
+        })
+    }
+
+    /// Returns a keycode without any keysym bound to it. These are used as a
+    /// scratch slot for characters that the current layout can't produce.
+    pub fn unused_keycode(&self) -> Option<Keycode> {
+        self.keycodes().rev().find(|&keycode| {
+            self.keysyms(keycode)
+                .iter()
+                .all(|keysym| *keysym == NO_SYMBOL)
+        })
+    }
+}
+
+pub fn char_to_keysym(c: char) -> Keysym {
+    match c as u32 {
+        code @ (0x20..=0x7e | 0xa0..=0xff) => code,
+        code => UNICODE_KEYSYM_OFFSET | code,
+    }
+}
+
+pub fn keysym_to_char(keysym: Keysym) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        0x0100_0100..=0x0110_ffff => char::from_u32(keysym - UNICODE_KEYSYM_OFFSET),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xkb_rules_names() {
+        let names =
+            XkbRulesNames::parse(b"evdev\0pc105\0us,de\0,nodeadkeys\0grp:alt_shift_toggle\0")
+                .unwrap();
+        assert_eq!(names.rules, "evdev");
+        assert_eq!(names.model, "pc105");
+        assert_eq!(names.layout, "us,de");
+        assert_eq!(names.variant, ",nodeadkeys");
+        assert_eq!(names.options, "grp:alt_shift_toggle");
+    }
+
+    #[test]
+    fn test_parse_xkb_rules_names_missing_fields() {
+        let names = XkbRulesNames::parse(b"evdev\0pc105\0fr").unwrap();
+        assert_eq!(names.layout, "fr");
+        assert_eq!(names.variant, "");
+        assert_eq!(names.options, "");
+        assert!(XkbRulesNames::parse(b"").is_none());
+    }
+}
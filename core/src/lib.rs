@@ -10,16 +10,24 @@ pub mod livekit {
     pub mod audio;
     pub mod participant;
     pub mod stats;
+    pub mod transport;
     pub mod video;
+    pub mod wire;
 }
 
 pub mod room_service;
 mod snapshot_sender;
+pub mod timeline;
 
 pub mod input {
     pub mod clipboard;
+    pub mod control;
     pub mod keyboard;
     pub mod mouse;
+    #[cfg(target_os = "linux")]
+    pub(crate) mod portal;
+    #[cfg(target_os = "linux")]
+    pub(crate) mod x11;
 }
 
 pub mod camera {
@@ -63,23 +71,30 @@ use graphics::graphics_context::participant::CursorMode;
 use graphics::graphics_context::GraphicsContext;
 use graphics::graphics_window_context::ContextManager;
 use input::clipboard::ClipboardController;
+use input::control::{self, ControlGate};
 use input::keyboard::{KeyboardController, KeyboardLayout};
 use input::mouse::CursorController;
 use log::{debug, error};
 use overlay_window::OverlayWindow;
-use room_service::RoomService;
+use room_service::{ControlRoles, Feature, RoomService};
+use socket_lib::server::{ClientMessage, ReplyTo, ServerSender};
 use socket_lib::{
-    CallStartMessage, CameraStartMessage, Content, ContentType, Message, ScreenShareMessage,
-    ScreenSharePickerMode, ScreenShareResolution, SentryMetadata, SocketSender,
+    CallStartMessage, CameraStartMessage, Capability, Content, ContentType, ControlRole,
+    ControlRoleAssignment, Message, ScreenShareMessage, ScreenSharePickerMode,
+    ScreenShareResolution, SentryMetadata,
 };
+use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
+use timeline::{Timeline, TimelineOptions};
 use utils::geometry::{Extent, Frame};
 use window::camera_window::CameraWindow;
 use window::drawing_window::DrawingWindow;
-use window::screensharing_window::{ScreensharingWindow, ScreensharingWindowConfig};
+use window::screensharing_window::{
+    ControlHandoff, ScreensharingWindow, ScreensharingWindowConfig,
+};
 use window::stats_window::StatsWindow;
 use winit::application::ApplicationHandler;
 use winit::error::EventLoopError;
@@ -306,6 +321,8 @@ pub enum ServerError {
 struct RemoteControl {
     cursor_controller: CursorController,
     keyboard_controller: KeyboardController<KeyboardLayout>,
+    /// Who the sharer granted control to, shared with both controllers.
+    control_gate: ControlGate,
 }
 
 impl RemoteControl {
@@ -330,6 +347,37 @@ impl RemoteControl {
     }
 }
 
+/// A controller waiting for the sharer to answer their control request.
+#[derive(Debug)]
+struct ControlRequest {
+    identity: String,
+    name: String,
+}
+
+/// Whether a controller's click, scroll or keystroke is sent to the sharer.
+/// Sharers that hand out control only take input after a grant, a press in
+/// the meantime asks for one.
+fn controller_input_allowed(
+    room_service: &RoomService,
+    screensharing_window: &mut ScreensharingWindow,
+    press: bool,
+) -> bool {
+    if !room_service.sharer_supports(Feature::ControlHandoff) {
+        return true;
+    }
+    match screensharing_window.control_handoff() {
+        ControlHandoff::Granted => true,
+        ControlHandoff::Requested => false,
+        ControlHandoff::NotRequested => {
+            if press {
+                room_service.request_control();
+                screensharing_window.set_control_handoff(ControlHandoff::Requested);
+            }
+            false
+        }
+    }
+}
+
 /// The main application struct that manages the entire remote desktop control session.
 ///
 /// This struct coordinates all aspects of the remote desktop system, including screen capture,
@@ -378,8 +426,7 @@ pub struct Application<'a> {
     // thread that checks if the stream has failed.
     //screen_capturer: Arc<Mutex<ScreenCapturer>>,
     screen_capturer: Arc<Mutex<Capturer>>,
-    socket: SocketSender,
-    socket_responses: std::sync::mpsc::Receiver<socket_lib::Message>,
+    socket: ServerSender,
     room_service: Option<RoomService>,
     event_loop_proxy: EventLoopProxy<UserEvent>,
     controller_draw_persist: bool,
@@ -403,6 +450,18 @@ pub struct Application<'a> {
     clipboard_controller: Option<ClipboardController>,
     screen_selection: Option<ScreenSelectionState>,
     pending_overlay_repair: Option<MonitorId>,
+    /// Control requests waiting for the sharer, the first one is on the overlay.
+    control_requests: VecDeque<ControlRequest>,
+    /// User ids whose control requests are granted without asking.
+    trusted_controllers: Vec<String>,
+    /// What everyone may do on our screen when we share.
+    control_roles: ControlRoles,
+    /// The role the sharer announced for us, with the sharer's identity.
+    local_control_role: Option<(String, ControlRole)>,
+    /// Records the room's events while a screen share is active.
+    timeline: Arc<Timeline>,
+    /// Set with `--timeline-dir`, timelines are only recorded then.
+    timeline_options: Option<TimelineOptions>,
 }
 
 #[derive(Error, Debug)]
@@ -424,6 +483,7 @@ impl<'a> Application<'a> {
     /// * `input` - Configuration including texture paths and LiveKit server URL
     /// * `socket` - Established socket connection for client communication
     /// * `event_loop_proxy` - Proxy for sending events to the main event loop
+    /// * `timeline_options` - Where to record screen share timelines, if anywhere
     ///
     /// # Returns
     ///
@@ -436,10 +496,10 @@ impl<'a> Application<'a> {
     /// - Screen capturer initialization fails
     /// - Event loop proxy is invalid
     pub fn new(
-        socket: SocketSender,
-        socket_responses: std::sync::mpsc::Receiver<socket_lib::Message>,
+        socket: ServerSender,
         event_loop_proxy: EventLoopProxy<UserEvent>,
         hang_protection_counter: Arc<AtomicU64>,
+        timeline_options: Option<TimelineOptions>,
     ) -> Result<Self, ApplicationError> {
         let screencapturer = Arc::new(Mutex::new(Capturer::new(event_loop_proxy.clone())));
 
@@ -467,7 +527,6 @@ impl<'a> Application<'a> {
             remote_control: None,
             screen_capturer: screencapturer,
             socket,
-            socket_responses,
             room_service: None,
             event_loop_proxy,
             controller_draw_persist: false,
@@ -491,6 +550,12 @@ impl<'a> Application<'a> {
             clipboard_controller,
             screen_selection: None,
             pending_overlay_repair: None,
+            control_requests: VecDeque::new(),
+            trusted_controllers: Vec::new(),
+            control_roles: ControlRoles::default(),
+            local_control_role: None,
+            timeline: Arc::new(Timeline::default()),
+            timeline_options,
         })
     }
 
@@ -908,6 +973,7 @@ impl<'a> Application<'a> {
         if let Some(room_service) = self.room_service.as_ref() {
             room_service.mute_screen_share_track();
         }
+        self.control_requests.clear();
         self.destroy_overlay_window();
         self.set_screensharing_active(false);
         self.close_drawing_window();
@@ -920,6 +986,15 @@ impl<'a> Application<'a> {
         if let Some(cam) = &mut self.camera_window {
             cam.set_screensharing_active(active);
         }
+        if !active {
+            self.timeline.stop();
+        } else if let Some(options) = &self.timeline_options {
+            if !self.timeline.is_recording() {
+                if let Err(e) = self.timeline.start(options) {
+                    log::error!("set_screensharing_active: Failed to start timeline: {e}");
+                }
+            }
+        }
         log::info!("set_screensharing_active: {active}");
     }
 
@@ -1022,11 +1097,19 @@ impl<'a> Application<'a> {
         let redraw_sender = gfx.redraw_sender();
         let clock = gfx.clock();
 
+        let control_gate = ControlGate::new(
+            self.room_service
+                .as_ref()
+                .map(|room_service| room_service.participants())
+                .unwrap_or_default(),
+            self.control_roles.clone(),
+        );
         let cursor_controller = CursorController::new(
             overlay_window,
             redraw_sender,
             self.event_loop_proxy.clone(),
             clock,
+            control_gate.clone(),
             target_process_id,
             target_window_id,
         )
@@ -1034,12 +1117,19 @@ impl<'a> Application<'a> {
             log::error!("create_overlay_window: Error creating cursor controller {error:?}");
             ServerError::CursorControllerCreationError
         })?;
+        let mut keyboard_controller = KeyboardController::<KeyboardLayout>::new(target_process_id);
+        keyboard_controller.set_control_gate(control_gate.clone());
         let mut remote_control = RemoteControl {
             cursor_controller,
-            keyboard_controller: KeyboardController::<KeyboardLayout>::new(target_process_id),
+            keyboard_controller,
+            control_gate,
         };
         remote_control.set_enabled(remote_control_enabled);
+        remote_control
+            .cursor_controller
+            .set_input_simulation_enabled(remote_control_enabled);
         self.remote_control = Some(remote_control);
+        self.announce_control_roles();
 
         let existing_participants = self
             .room_service
@@ -1098,6 +1188,176 @@ impl<'a> Application<'a> {
         }
     }
 
+    /// Whether `identity` has at least `role` on our screen, always true when
+    /// we aren't sharing. `Full` also needs remote control to be enabled.
+    fn role_allows(&self, identity: &str, role: ControlRole) -> bool {
+        self.remote_control.as_ref().is_none_or(|remote_control| {
+            remote_control.control_gate.role(identity) >= role
+                && (role < ControlRole::Full || self.remote_control_enabled)
+        })
+    }
+
+    /// Tells everyone their role while we share.
+    fn announce_control_roles(&self) {
+        if let (Some(_), Some(room_service)) = (&self.remote_control, &self.room_service) {
+            room_service.publish_control_roles(self.control_roles.clone());
+        }
+    }
+
+    /// Applies changed roles to the controllers and announces them. Whoever
+    /// was granted control loses it without the `Full` role.
+    fn update_control_roles(&mut self) {
+        if let Some(remote_control) = self.remote_control.as_mut() {
+            remote_control
+                .control_gate
+                .set_roles(self.control_roles.clone());
+            remote_control.cursor_controller.apply_control_roles();
+            let granted = remote_control.control_gate.granted();
+            if granted
+                .is_some_and(|identity| self.control_roles.role(&identity) < ControlRole::Full)
+            {
+                self.revoke_control();
+            }
+        }
+        self.announce_control_roles();
+    }
+
+    /// Limits the screensharing window to what the current sharer lets us
+    /// do. Sharers that didn't announce roles let us do everything.
+    fn apply_local_control_role(&mut self) {
+        let sharer = self
+            .room_service
+            .as_ref()
+            .and_then(|room_service| room_service.sharer_identity());
+        let role = match (&self.local_control_role, sharer) {
+            (Some((announced_by, role)), Some(sharer)) if *announced_by == sharer => *role,
+            _ => ControlRole::Full,
+        };
+        let Some(screensharing_window) = &mut self.screensharing_window else {
+            return;
+        };
+        let Some(mode) = screensharing_window.set_control_role(role) else {
+            return;
+        };
+        if let Some(room_service) = self.room_service.as_ref() {
+            if !matches!(mode, DrawingMode::Draw(_)) {
+                room_service.publish_draw_clear_all_paths();
+            }
+            room_service.publish_drawing_mode(mode);
+        }
+    }
+
+    /// Queues a control request from `identity`, trusted teammates are
+    /// granted control without asking.
+    fn request_control(&mut self, identity: String) {
+        if self.remote_control.is_none() {
+            log::debug!("request_control: not sharing, ignoring request from {identity}");
+            return;
+        }
+        if !self.role_allows(&identity, ControlRole::Full) {
+            log::info!("request_control: {identity} may not control, denying");
+            if let Some(room_service) = self.room_service.as_ref() {
+                room_service.deny_control(identity);
+            }
+            return;
+        }
+        let trusted = control::user_id(&identity).is_some_and(|user_id| {
+            self.trusted_controllers
+                .iter()
+                .any(|trusted| trusted == user_id)
+        });
+        if trusted {
+            log::info!("request_control: {identity} is trusted, granting control");
+            self.grant_control(identity);
+            return;
+        }
+        if self
+            .control_requests
+            .iter()
+            .any(|request| request.identity == identity)
+        {
+            return;
+        }
+
+        let name = self
+            .room_service
+            .as_ref()
+            .and_then(|room_service| {
+                room_service
+                    .get_participants()
+                    .into_iter()
+                    .find(|participant| participant.identity == identity)
+            })
+            .map_or_else(|| identity.clone(), |participant| participant.name);
+        self.control_requests
+            .push_back(ControlRequest { identity, name });
+        self.show_control_request();
+    }
+
+    /// Shows the oldest pending control request on the overlay, or hides the
+    /// prompt when there is none.
+    fn show_control_request(&mut self) {
+        let name = self
+            .control_requests
+            .front()
+            .map(|request| request.name.clone());
+        if let Some(gfx) = self
+            .window_manager
+            .as_mut()
+            .and_then(|window_manager| window_manager.active_gfx_mut())
+        {
+            gfx.set_control_request(name);
+        }
+    }
+
+    fn answer_control_request(&mut self, allow: bool) {
+        let Some(request) = self.control_requests.pop_front() else {
+            return;
+        };
+        log::info!("answer_control_request: {} {allow}", request.identity);
+        if allow {
+            self.grant_control(request.identity);
+        } else if let Some(room_service) = self.room_service.as_ref() {
+            room_service.deny_control(request.identity);
+        }
+        self.show_control_request();
+    }
+
+    /// Gives control to `identity`, taking it back from the previous holder.
+    fn grant_control(&mut self, identity: String) {
+        let Some(remote_control) = self.remote_control.as_mut() else {
+            return;
+        };
+        let previous = remote_control.control_gate.grant(&identity);
+        if let Some(previous) = previous.filter(|previous| *previous != identity) {
+            remote_control
+                .cursor_controller
+                .release_controller(&previous);
+            if let Some(room_service) = self.room_service.as_ref() {
+                room_service.revoke_control(previous);
+            }
+        }
+        if let Some(room_service) = self.room_service.as_ref() {
+            room_service.grant_control(identity);
+        }
+    }
+
+    /// Takes control back from whoever was granted it.
+    fn revoke_control(&mut self) {
+        let Some(remote_control) = self.remote_control.as_mut() else {
+            return;
+        };
+        let Some(identity) = remote_control.control_gate.revoke() else {
+            return;
+        };
+        remote_control
+            .cursor_controller
+            .release_controller(&identity);
+        if let Some(room_service) = self.room_service.as_ref() {
+            room_service.revoke_control(identity);
+        }
+    }
+
     fn repair_overlay_monitor(
         &mut self,
         event_loop: &ActiveEventLoop,
@@ -1126,6 +1386,10 @@ impl<'a> Application<'a> {
                 .drawing_window
                 .as_ref()
                 .is_none_or(|drawing_window| !drawing_window.is_visible());
+        let granted = self
+            .remote_control
+            .as_ref()
+            .and_then(|remote_control| remote_control.control_gate.granted());
 
         self.destroy_overlay_window();
 
@@ -1171,6 +1435,10 @@ impl<'a> Application<'a> {
         for (identity, drawing_mode) in participant_drawing_modes {
             self.apply_overlay_participant_drawing_mode(&identity, drawing_mode);
         }
+        if let (Some(remote_control), Some(identity)) = (self.remote_control.as_ref(), granted) {
+            remote_control.control_gate.grant(&identity);
+        }
+        self.show_control_request();
 
         if let Some(drawing_window) = self
             .drawing_window
@@ -1249,6 +1517,9 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
         match event {
             UserEvent::CursorPosition(x, y, sid) => {
                 log::debug!("user_event: cursor position: {x} {y} {sid}");
+                if !self.role_allows(&sid, ControlRole::Pointer) {
+                    return;
+                }
                 if let Some(remote_control) = &mut self.remote_control {
                     remote_control.cursor_controller.cursor_move_controller(
                         x as f64,
@@ -1283,7 +1554,13 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 log::debug!("user_event: cursor enabled: {enabled:?}");
                 self.remote_control_enabled = enabled;
 
+                if !enabled {
+                    self.revoke_control();
+                }
                 if let Some(remote_control) = self.remote_control.as_mut() {
+                    remote_control
+                        .cursor_controller
+                        .set_input_simulation_enabled(enabled);
                     if self.drawing_window.as_ref().is_none_or(|w| !w.is_visible()) {
                         remote_control.set_enabled(enabled);
                     }
@@ -1297,15 +1574,15 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     room_service.publish_controller_cursor_enabled(enabled);
                 }
             }
-            UserEvent::Keystroke(keystroke_data) => {
-                log::debug!("user_event: keystroke: {keystroke_data:?}");
+            UserEvent::Keystroke(keystroke_data, sid) => {
+                log::debug!("user_event: keystroke: {keystroke_data:?} {sid}");
                 if self.remote_control.is_none() {
                     log::warn!("user_event: remote control is none keystroke");
                     return;
                 }
                 let remote_control = &mut self.remote_control.as_mut().unwrap();
                 let keyboard_controller = &mut remote_control.keyboard_controller;
-                keyboard_controller.simulate_keystrokes(keystroke_data);
+                keyboard_controller.simulate_keystrokes_from(keystroke_data, sid.as_str());
             }
             UserEvent::Scroll(delta, sid) => {
                 log::debug!("user_event: scroll: {delta:?} {sid}");
@@ -1328,7 +1605,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 log::debug!("user_event: GetAvailableContent -> screen selection");
                 self.start_screen_selection(event_loop);
             }
-            UserEvent::CallStart(call_start) => {
+            UserEvent::CallStart(call_start, reply_to) => {
                 log::info!("user_event: CallStart");
                 let start_camera_on_call = call_start.start_camera_on_call.unwrap_or(false);
                 if let Err(e) = self.audio_player.start() {
@@ -1336,7 +1613,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     sentry_utils::upload_logs_event(format!("Failed to start audio player: {e}"));
                     if let Err(e) = self
                         .socket
-                        .send(Message::CallStartResult(Err(e.to_string())))
+                        .respond(reply_to, Message::CallStartResult(Err(e.to_string())))
                     {
                         error!("user_event: Error sending CallStartResult: {e:?}");
                     }
@@ -1359,7 +1636,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     Err(e) => {
                         log::error!("user_event: CallStart audio capture failed: {e}");
                         self.audio_player.stop();
-                        if let Err(send_err) = self.socket.send(Message::CallStartResult(Err(e))) {
+                        if let Err(send_err) = self
+                            .socket
+                            .respond(reply_to, Message::CallStartResult(Err(e)))
+                        {
                             error!("user_event: Error sending CallStartResult: {send_err:?}");
                         }
                         return;
@@ -1380,7 +1660,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                         start_camera_on_call,
                     }) {
                         Ok(_) => {
-                            if let Err(e) = self.socket.send(Message::CallStartResult(Ok(()))) {
+                            if let Err(e) = self
+                                .socket
+                                .respond(reply_to, Message::CallStartResult(Ok(())))
+                            {
                                 error!("user_event: Error sending CallStartResult ack: {e:?}");
                             }
                             self.start_camera_on_call = start_camera_on_call;
@@ -1397,7 +1680,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                             self.audio_player.stop();
                             if let Err(e) = self
                                 .socket
-                                .send(Message::CallStartResult(Err(e.to_string())))
+                                .respond(reply_to, Message::CallStartResult(Err(e.to_string())))
                             {
                                 error!("user_event: Error sending CallStartResult: {e:?}");
                             }
@@ -1407,9 +1690,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     log::error!("user_event: Room service not found for CallStart");
                     self.stop_mic();
                     self.audio_player.stop();
-                    if let Err(e) = self.socket.send(Message::CallStartResult(Err(
-                        ServerError::RoomServiceNotFound.to_string(),
-                    ))) {
+                    if let Err(e) = self.socket.respond(
+                        reply_to,
+                        Message::CallStartResult(Err(ServerError::RoomServiceNotFound.to_string())),
+                    ) {
                         error!("user_event: Error sending CallStartResult: {e:?}");
                     }
                 }
@@ -1438,7 +1722,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                             self.start_camera_on_call = false;
                             let res = self.event_loop_proxy.send_event(UserEvent::StartCamera {
                                 msg: CameraStartMessage { device_name: None },
-                                from_socket: false,
+                                reply_to: None,
                             });
                             if res.is_err() {
                                 log::error!("user_event: CreateRoomResult failed to queue StartCamera: {res:?}");
@@ -1674,10 +1958,24 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                         }
                     }
                 }
+                self.announce_control_roles();
             }
             UserEvent::ParticipantDisconnected(participant) => {
                 log::debug!("user_event: Participant disconnected: {participant:?}");
 
+                if let Some(remote_control) = self.remote_control.as_ref() {
+                    if remote_control.control_gate.granted().as_ref() == Some(&participant.identity)
+                    {
+                        remote_control.control_gate.revoke();
+                    }
+                }
+                let pending = self.control_requests.len();
+                self.control_requests
+                    .retain(|request| request.identity != participant.identity);
+                if self.control_requests.len() != pending {
+                    self.show_control_request();
+                }
+
                 if let (Some(window_manager), Some(remote_control)) =
                     (self.window_manager.as_mut(), self.remote_control.as_mut())
                 {
@@ -1698,7 +1996,8 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
             UserEvent::LivekitServerUrl(url) => {
                 log::debug!("user_event: Livekit server url: {url}");
 
-                let room_service = RoomService::new(url, self.socket.clone());
+                let room_service =
+                    RoomService::new(url, self.socket.clone(), self.timeline.clone());
                 if room_service.is_err() {
                     log::error!(
                         "user_event: Error creating room service: {:?}",
@@ -1731,6 +2030,25 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     .unwrap()
                     .publish_participant_in_control(participant);
             }
+            UserEvent::ControlRequested(identity) => {
+                log::info!("user_event: control requested by {identity}");
+                self.request_control(identity);
+            }
+            UserEvent::LocalControlGranted(granted) => {
+                log::info!("user_event: local control granted: {granted}");
+                if let Some(screensharing_window) = &mut self.screensharing_window {
+                    screensharing_window.set_control_handoff(if granted {
+                        ControlHandoff::Granted
+                    } else {
+                        ControlHandoff::NotRequested
+                    });
+                }
+            }
+            UserEvent::LocalControlRole(role, sharer) => {
+                log::info!("user_event: {sharer} gave us the {role:?} role");
+                self.local_control_role = Some((sharer, role));
+                self.apply_local_control_role();
+            }
             UserEvent::LocalParticipantInControl(in_control) => {
                 log::debug!("user_event: local participant in control: {in_control}");
                 if let Some(screensharing_window) = &mut self.screensharing_window {
@@ -1754,6 +2072,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     return;
                 }
                 let remote_control = self.remote_control.as_mut().unwrap();
+                if !remote_control.control_gate.allows(&requester_identity) {
+                    log::debug!("user_event: {requester_identity} wasn't granted control");
+                    return;
+                }
                 let clipboard_text = self
                     .clipboard_controller
                     .as_mut()
@@ -1763,7 +2085,11 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                         &mut remote_control.keyboard_controller,
                     );
                 if let Some(text) = clipboard_text {
-                    if let Some(room_service) = &self.room_service {
+                    if let Some(room_service) = self
+                        .room_service
+                        .as_ref()
+                        .filter(|rs| rs.peer_supports(&requester_identity, Feature::ClipboardSync))
+                    {
                         let bytes = text.as_bytes();
                         const MAX_PACKET: usize = 15 * 1024;
                         let total_packets = bytes.len().div_ceil(MAX_PACKET) as u64;
@@ -1784,8 +2110,8 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     }
                 }
             }
-            UserEvent::PasteFromClipboard(paste_from_clipboard_data) => {
-                log::info!("user_event: Paste from clipboard");
+            UserEvent::PasteFromClipboard(paste_from_clipboard_data, sid) => {
+                log::info!("user_event: Paste from clipboard {sid}");
                 if self.remote_control.is_none() {
                     log::warn!("user_event: remote control is none paste from clipboard");
                     return;
@@ -1795,6 +2121,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     return;
                 }
                 let remote_control = self.remote_control.as_mut().unwrap();
+                if !remote_control.control_gate.allows(&sid) {
+                    log::debug!("user_event: {sid} wasn't granted control");
+                    return;
+                }
                 self.clipboard_controller
                     .as_mut()
                     .unwrap()
@@ -1820,6 +2150,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
             }
             UserEvent::DrawStart(point, path_id, sid) => {
                 log::debug!("user_event: DrawStart: {:?} {} {}", point, path_id, sid);
+                if !self.role_allows(&sid, ControlRole::Draw) {
+                    log::debug!("user_event: DrawStart: {sid} may not draw");
+                    return;
+                }
                 let pos = Position {
                     x: point.x,
                     y: point.y,
@@ -1845,6 +2179,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
             }
             UserEvent::DrawAddPoint(point, sid) => {
                 log::debug!("user_event: DrawAddPoint: {:?} {}", point, sid);
+                if !self.role_allows(&sid, ControlRole::Draw) {
+                    log::debug!("user_event: DrawAddPoint: {sid} may not draw");
+                    return;
+                }
                 let pos = Position {
                     x: point.x,
                     y: point.y,
@@ -1870,6 +2208,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
             }
             UserEvent::DrawEnd(point, sid) => {
                 log::debug!("user_event: DrawEnd: {:?} {}", point, sid);
+                if !self.role_allows(&sid, ControlRole::Draw) {
+                    log::debug!("user_event: DrawEnd: {sid} may not draw");
+                    return;
+                }
                 let pos = Position {
                     x: point.x,
                     y: point.y,
@@ -1931,6 +2273,9 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     point,
                     sid
                 );
+                if !self.role_allows(&sid, ControlRole::Pointer) {
+                    return;
+                }
                 if let Some(gfx) = self
                     .window_manager
                     .as_mut()
@@ -1950,14 +2295,17 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     }
                 }
             }
-            UserEvent::ListAudioDevices => {
+            UserEvent::ListAudioDevices(reply_to) => {
                 log::debug!("user_event: ListAudioDevices");
                 let devices = self.audio_capturer.list_sources();
-                if let Err(e) = self.socket.send(Message::AudioDeviceList(devices)) {
+                if let Err(e) = self
+                    .socket
+                    .respond(reply_to, Message::AudioDeviceList(devices))
+                {
                     error!("user_event: Error sending audio device list: {e:?}");
                 }
             }
-            UserEvent::StartAudioCapture { msg, from_socket } => {
+            UserEvent::StartAudioCapture { msg, reply_to } => {
                 log::info!(
                     "user_event: StartAudioCapture device_name={}",
                     msg.device_name
@@ -1979,7 +2327,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                                 .map(|s| s.to_string()),
                         );
                     }
-                    if !from_socket {
+                    if reply_to.is_none() {
                         if let Some(device_name) = self.audio_capturer.active_device_name() {
                             if let Err(e) = self
                                 .socket
@@ -1991,8 +2339,11 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     }
                 }
 
-                if from_socket {
-                    if let Err(e) = self.socket.send(Message::StartAudioCaptureResult(result)) {
+                if let Some(reply_to) = reply_to {
+                    if let Err(e) = self
+                        .socket
+                        .respond(reply_to, Message::StartAudioCaptureResult(result))
+                    {
                         error!("user_event: Error sending StartAudioCaptureResult: {e:?}");
                     }
                 }
@@ -2032,6 +2383,22 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 log::info!("user_event: SetTelemetryEnabled({enabled})");
                 sentry_utils::set_telemetry_enabled(enabled);
             }
+            UserEvent::SetTrustedControllers(user_ids) => {
+                log::info!("user_event: SetTrustedControllers({})", user_ids.len());
+                self.trusted_controllers = user_ids;
+            }
+            UserEvent::SetControlRole(assignment) => {
+                log::info!("user_event: SetControlRole({assignment:?})");
+                match assignment.role {
+                    Some(role) => {
+                        self.control_roles.roles.insert(assignment.user_id, role);
+                    }
+                    None => {
+                        self.control_roles.roles.remove(&assignment.user_id);
+                    }
+                }
+                self.update_control_roles();
+            }
             UserEvent::ToggleMic => {
                 log::info!("user_event: ToggleMic");
                 if let Some(room_service) = self.room_service.as_ref() {
@@ -2042,31 +2409,30 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     }
                 }
             }
-            UserEvent::ListCameras => {
+            UserEvent::ListCameras(reply_to) => {
                 log::debug!("user_event: ListCameras");
                 let devices = CameraCapturer::list_devices();
-                if let Err(e) = self.socket.send(Message::CameraList(devices)) {
+                if let Err(e) = self.socket.respond(reply_to, Message::CameraList(devices)) {
                     error!("user_event: Error sending camera list: {e:?}");
                 }
             }
-            UserEvent::StartCamera { msg, from_socket } => {
+            UserEvent::StartCamera { msg, reply_to } => {
                 let device_name = if msg.device_name.is_some() {
                     msg.device_name
-                } else if let Err(e) = self.socket.send(Message::QueryPreferredCamera) {
-                    log::error!("user_event: StartCamera: failed to query preferred camera: {e:?}");
-                    None
                 } else {
-                    match self
-                        .socket_responses
-                        .recv_timeout(std::time::Duration::from_millis(500))
-                    {
+                    match self.socket.call(
+                        Message::QueryPreferredCamera,
+                        std::time::Duration::from_millis(500),
+                    ) {
                         Ok(Message::PreferredCamera(name)) => name,
                         Ok(other) => {
                             log::warn!("user_event: StartCamera: unexpected response to QueryPreferredCamera: {other:?}");
                             None
                         }
-                        Err(_) => {
-                            log::warn!("user_event: StartCamera: timeout waiting for preferred camera, using default");
+                        Err(e) => {
+                            log::warn!(
+                                "user_event: StartCamera: no preferred camera ({e}), using default"
+                            );
                             None
                         }
                     }
@@ -2077,10 +2443,13 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 let room_service = match self.room_service.as_ref() {
                     Some(rs) => rs,
                     None => {
-                        if from_socket {
-                            let _ = self.socket.send(Message::StartCameraResult(Err(
-                                "Room service not found".to_string(),
-                            )));
+                        if let Some(reply_to) = reply_to {
+                            let _ = self.socket.respond(
+                                reply_to,
+                                Message::StartCameraResult(Err(
+                                    "Room service not found".to_string()
+                                )),
+                            );
                         }
                         return;
                     }
@@ -2090,10 +2459,11 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     Some(s) => s,
                     None => {
                         log::error!("user_event: StartCamera: no camera buffer source available");
-                        if from_socket {
-                            let _ = self.socket.send(Message::StartCameraResult(Err(
-                                "Camera not ready".to_string(),
-                            )));
+                        if let Some(reply_to) = reply_to {
+                            let _ = self.socket.respond(
+                                reply_to,
+                                Message::StartCameraResult(Err("Camera not ready".to_string())),
+                            );
                         }
                         return;
                     }
@@ -2123,8 +2493,10 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     if let Some(cam) = &mut self.camera_window {
                         cam.show_error_toast("Failed to start camera");
                     }
-                    if from_socket {
-                        if let Err(e) = self.socket.send(Message::StartCameraResult(Err(e.clone())))
+                    if let Some(reply_to) = reply_to {
+                        if let Err(e) = self
+                            .socket
+                            .respond(reply_to, Message::StartCameraResult(Err(e.clone())))
                         {
                             error!("user_event: Error sending StartCameraResult: {e:?}");
                         }
@@ -2134,8 +2506,11 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 }
 
                 // Send success result via socket
-                if from_socket {
-                    if let Err(e) = self.socket.send(Message::StartCameraResult(Ok(()))) {
+                if let Some(reply_to) = reply_to {
+                    if let Err(e) = self
+                        .socket
+                        .respond(reply_to, Message::StartCameraResult(Ok(())))
+                    {
                         error!("user_event: Error sending StartCameraResult: {e:?}");
                     }
                 }
@@ -2152,7 +2527,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     cam.set_camera_active(true, actual_name.clone());
                 }
 
-                if !from_socket {
+                if reply_to.is_none() {
                     if let Some(name) = &actual_name {
                         if let Err(e) = self.socket.send(Message::ActiveCameraChanged(name.clone()))
                         {
@@ -2240,6 +2615,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 } else {
                     log::warn!("user_event: Room service not available");
                 }
+                self.apply_local_control_role();
                 if let Some(screensharing_window) = &self.screensharing_window {
                     if let Some(room_service) = self.room_service.as_ref() {
                         room_service.publish_drawing_mode(screensharing_window.drawing_mode());
@@ -2265,7 +2641,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 log::info!("user_event: CloseCameraWindow");
                 self.close_camera_window();
             }
-            UserEvent::BringWindowsToFront => {
+            UserEvent::BringWindowsToFront(reply_to) => {
                 log::info!("user_event: BringWindowsToFront");
                 let mut focused = false;
                 if let Some(screen_sharing_window) = &mut self.screensharing_window {
@@ -2282,7 +2658,7 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                 }
                 if let Err(e) = self
                     .socket
-                    .send(Message::BringWindowsToFrontResult(focused))
+                    .respond(reply_to, Message::BringWindowsToFrontResult(focused))
                 {
                     log::error!("user_event: Error sending BringWindowsToFrontResult: {e:?}");
                 }
@@ -2586,13 +2962,19 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                                 rs.publish_cursor_position(x, y, true);
                             }
                             ScreenShareInputEvent::MouseClick(data) => {
-                                rs.publish_mouse_click(data);
+                                if controller_input_allowed(rs, screen_sharing_window, data.down) {
+                                    rs.publish_mouse_click(data);
+                                }
                             }
                             ScreenShareInputEvent::Scroll(data) => {
-                                rs.publish_wheel_event(data);
+                                if controller_input_allowed(rs, screen_sharing_window, false) {
+                                    rs.publish_wheel_event(data);
+                                }
                             }
                             ScreenShareInputEvent::KeyInput(data) => {
-                                rs.publish_keystroke(data);
+                                if controller_input_allowed(rs, screen_sharing_window, false) {
+                                    rs.publish_keystroke(data);
+                                }
                             }
                             ScreenShareInputEvent::DrawStart { x, y, path_id } => {
                                 rs.publish_draw_start(crate::room_service::DrawPathPoint {
@@ -2616,17 +2998,28 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                                 rs.publish_draw_clear_paths(ids);
                             }
                             ScreenShareInputEvent::ClickAnimation { x, y } => {
+                                if !rs.peers_support(Feature::ClickAnimation) {
+                                    log::debug!("ClickAnimation: not supported by every peer");
+                                    continue;
+                                }
                                 rs.publish_click_animation(crate::room_service::ClientPoint {
                                     x,
                                     y,
                                 });
                             }
                             ScreenShareInputEvent::AddToClipboard { is_copy } => {
+                                if !rs.peers_support(Feature::ClipboardSync) {
+                                    log::debug!("AddToClipboard: not supported by every peer");
+                                    continue;
+                                }
                                 rs.publish_add_to_clipboard(
                                     crate::room_service::AddToClipboardData { is_copy },
                                 );
                             }
-                            ScreenShareInputEvent::PasteFromClipboard(text) => match text {
+                            /* Without clipboard sync the sharer pastes its own clipboard. */
+                            ScreenShareInputEvent::PasteFromClipboard(text) => match text
+                                .filter(|_| rs.peers_support(Feature::ClipboardSync))
+                            {
                                 Some(clipboard_text) => {
                                     log::debug!("PasteFromClipboard: {:?}", clipboard_text);
                                     let bytes = clipboard_text.as_bytes();
@@ -2735,6 +3128,19 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
             }
         }
 
+        /* While a control request is up the overlay takes the mouse. */
+        if screen_selection_mouse_event && !self.control_requests.is_empty() {
+            let answer = self
+                .window_manager
+                .as_mut()
+                .filter(|window_manager| window_manager.is_active_window(window_id))
+                .and_then(|window_manager| window_manager.active_gfx_mut())
+                .and_then(|gfx| gfx.handle_control_request_event(&event));
+            if let Some(allow) = answer {
+                self.answer_control_request(allow);
+            }
+        }
+
         if matches!(
             &event,
             WindowEvent::Moved(_)
@@ -2898,6 +3304,15 @@ impl<'a> ApplicationHandler<UserEvent> for Application<'a> {
                     _ => {}
                 }
             }
+            WindowEvent::KeyboardInput { event, .. }
+                if !self.control_requests.is_empty() && event.state.is_pressed() =>
+            {
+                match event.logical_key {
+                    Key::Named(NamedKey::Enter) => self.answer_control_request(true),
+                    Key::Named(NamedKey::Escape) => self.answer_control_request(false),
+                    _ => {}
+                }
+            }
             WindowEvent::Resized(new_size) => {
                 if let Some(wm) = self.window_manager.as_mut() {
                     if self.screen_selection.is_some() {
@@ -3000,7 +3415,7 @@ pub enum UserEvent {
     CursorPosition(f32, f32, String),
     MouseClick(MouseClickData, String),
     ControllerCursorEnabled(bool),
-    Keystroke(KeystrokeData),
+    Keystroke(KeystrokeData, String),
     Scroll(ScrollDelta, String),
     GetAvailableContent,
     Terminate,
@@ -3019,9 +3434,15 @@ pub enum UserEvent {
     ControllerTakesScreenShare,
     ParticipantInControl(String),
     LocalParticipantInControl(bool),
+    /// A controller asked us, the sharer, for control
+    ControlRequested(String),
+    /// The sharer granted (true), denied or revoked our control request
+    LocalControlGranted(bool),
+    /// Our role on the screen of the sharer with the given identity
+    LocalControlRole(ControlRole, String),
     SentryMetadata(SentryMetadata),
     AddToClipboard(room_service::AddToClipboardData, String),
-    PasteFromClipboard(room_service::PasteFromClipboardData),
+    PasteFromClipboard(room_service::PasteFromClipboardData, String),
     SetClipboard(room_service::ClipboardDataPayload),
     DrawingMode(room_service::DrawingMode, String),
     DrawStart(room_service::ClientPoint, u64, String),
@@ -3034,19 +3455,20 @@ pub enum UserEvent {
     SharerDrawPersistChanged(bool),
     ControllerDrawPersistChanged(bool),
     LastModeChanged(socket_lib::StoredMode),
-    ListAudioDevices,
+    ListAudioDevices(ReplyTo),
+    /// `reply_to` is set when a socket client asked and waits for the result
     StartAudioCapture {
         msg: socket_lib::AudioCaptureMessage,
-        from_socket: bool,
+        reply_to: Option<ReplyTo>,
     },
     StopAudioCapture,
     MuteAudio,
     UnmuteAudio,
     ToggleMic,
-    ListCameras,
+    ListCameras(ReplyTo),
     StartCamera {
         msg: CameraStartMessage,
-        from_socket: bool,
+        reply_to: Option<ReplyTo>,
     },
     StopCamera,
     OpenCamera,
@@ -3065,7 +3487,7 @@ pub enum UserEvent {
     CloseScreenShareWindow,
     CloseCameraWindow,
     OpenStatsWindow,
-    BringWindowsToFront,
+    BringWindowsToFront(ReplyTo),
     SharerControlEnabled(bool),
     DefaultOutputDeviceChanged,
     DefaultInputDeviceChanged,
@@ -3074,6 +3496,8 @@ pub enum UserEvent {
     SetScreenShareResolution(ScreenShareResolution),
     SetScreenSharePickerMode(ScreenSharePickerMode),
     SetTelemetryEnabled(bool),
+    SetTrustedControllers(Vec<String>),
+    SetControlRole(ControlRoleAssignment),
     CreateRoomResult(Result<Vec<socket_lib::CoreParticipantState>, String>),
     ExitRequested,
 }
@@ -3100,6 +3524,20 @@ impl Default for RenderEventLoop {
     }
 }
 
+/// Features of this core build, advertised to the shell in the socket handshake.
+fn core_capabilities() -> Vec<Capability> {
+    let mut capabilities = vec![
+        Capability::NoiseCancellation,
+        Capability::Camera,
+        Capability::RemoteControl,
+    ];
+    /* The window picker is only implemented on macOS, see start_screen_selection. */
+    if cfg!(target_os = "macos") {
+        capabilities.push(Capability::WindowPicking);
+    }
+    capabilities
+}
+
 impl RenderEventLoop {
     pub fn new() -> Self {
         let mut event_loop = EventLoop::<UserEvent>::with_user_event();
@@ -3116,15 +3554,27 @@ impl RenderEventLoop {
         Self { event_loop }
     }
 
-    pub fn run(self, socket_path: String) -> Result<(), RenderLoopError> {
+    pub fn run(
+        self,
+        socket_path: String,
+        auth_token: Option<String>,
+        timeline_options: Option<TimelineOptions>,
+    ) -> Result<(), RenderLoopError> {
         log::info!("Starting RenderEventLoop");
 
         log::info!("Creating socket at path: {socket_path}");
-        let (sender, mut event_socket) = socket_lib::listen(&socket_path).map_err(|e| {
-            log::error!("Error creating socket: {e:?}");
-            RenderLoopError::SocketError(e)
-        })?;
-        let socket_responses = event_socket.take_responses();
+        let options = socket_lib::SocketOptions {
+            auth_token,
+            ..Default::default()
+        };
+        /* The shell owns the socket, clients like hoppctl may join it. */
+        let (sender, socket_events) =
+            socket_lib::server::serve(&socket_path, &core_capabilities(), options).map_err(
+                |e| {
+                    log::error!("Error creating socket: {e:?}");
+                    RenderLoopError::SocketError(e)
+                },
+            )?;
 
         let event_loop_proxy = self.event_loop.create_proxy();
         /*
@@ -3132,46 +3582,41 @@ impl RenderEventLoop {
          */
         std::thread::spawn(move || {
             loop {
-                let message =
-                    match event_socket
-                        .events
-                        .recv_timeout(std::time::Duration::from_secs(
-                            SOCKET_MESSAGE_TIMEOUT_SECONDS,
-                        )) {
-                        Ok(msg) => msg,
-                        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                let ClientMessage { reply_to, message } = match socket_events.recv_timeout(
+                    std::time::Duration::from_secs(SOCKET_MESSAGE_TIMEOUT_SECONDS),
+                ) {
+                    Ok(msg) => msg,
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                        log::error!("RenderEventLoop::run Socket message timeout, terminating.");
+                        let res = event_loop_proxy.send_event(UserEvent::Terminate);
+                        if res.is_err() {
                             log::error!(
-                                "RenderEventLoop::run Socket message timeout, terminating."
+                                "RenderEventLoop::run Error sending terminate event: {:?}",
+                                res.err()
                             );
-                            let res = event_loop_proxy.send_event(UserEvent::Terminate);
-                            if res.is_err() {
-                                log::error!(
-                                    "RenderEventLoop::run Error sending terminate event: {:?}",
-                                    res.err()
-                                );
-                            }
-                            std::thread::sleep(std::time::Duration::from_secs(1));
-                            std::process::exit(PROCESS_EXIT_CODE_ERROR);
                         }
-                        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                        std::process::exit(PROCESS_EXIT_CODE_ERROR);
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                        log::error!(
+                            "RenderEventLoop::run Socket event channel closed, terminating."
+                        );
+                        let res = event_loop_proxy.send_event(UserEvent::Terminate);
+                        if res.is_err() {
                             log::error!(
-                                "RenderEventLoop::run Socket event channel closed, terminating."
+                                "RenderEventLoop::run Error sending terminate event: {:?}",
+                                res.err()
                             );
-                            let res = event_loop_proxy.send_event(UserEvent::Terminate);
-                            if res.is_err() {
-                                log::error!(
-                                    "RenderEventLoop::run Error sending terminate event: {:?}",
-                                    res.err()
-                                );
-                            }
-                            std::thread::sleep(std::time::Duration::from_secs(1));
-                            std::process::exit(PROCESS_EXIT_CODE_ERROR);
                         }
-                    };
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                        std::process::exit(PROCESS_EXIT_CODE_ERROR);
+                    }
+                };
                 let user_event = match message {
                     Message::GetAvailableContent => UserEvent::GetAvailableContent,
                     Message::CallStart(call_start_message) => {
-                        UserEvent::CallStart(call_start_message)
+                        UserEvent::CallStart(call_start_message, reply_to)
                     }
                     Message::CallEnd => UserEvent::CallEnd,
                     Message::StartScreenShare(screen_share_message) => {
@@ -3189,19 +3634,19 @@ impl RenderEventLoop {
                         UserEvent::ControllerDrawPersistChanged(persist)
                     }
                     Message::LastModeChanged(mode) => UserEvent::LastModeChanged(mode),
-                    Message::ListAudioDevices => UserEvent::ListAudioDevices,
+                    Message::ListAudioDevices => UserEvent::ListAudioDevices(reply_to),
                     Message::StartAudioCapture(msg) => UserEvent::StartAudioCapture {
                         msg,
-                        from_socket: true,
+                        reply_to: Some(reply_to),
                     },
                     Message::StopAudioCapture => UserEvent::StopAudioCapture,
                     Message::MuteAudio => UserEvent::MuteAudio,
                     Message::UnmuteAudio => UserEvent::UnmuteAudio,
                     Message::ToggleMic => UserEvent::ToggleMic,
-                    Message::ListCameras => UserEvent::ListCameras,
+                    Message::ListCameras => UserEvent::ListCameras(reply_to),
                     Message::StartCamera(msg) => UserEvent::StartCamera {
                         msg,
-                        from_socket: true,
+                        reply_to: Some(reply_to),
                     },
                     Message::StopCamera => UserEvent::StopCamera,
                     Message::OpenCamera => UserEvent::OpenCamera,
@@ -3213,7 +3658,7 @@ impl RenderEventLoop {
                     },
                     Message::CloseScreenShareWindow => UserEvent::CloseScreenShareWindow,
                     Message::OpenStatsWindow => UserEvent::OpenStatsWindow,
-                    Message::BringWindowsToFront => UserEvent::BringWindowsToFront,
+                    Message::BringWindowsToFront => UserEvent::BringWindowsToFront(reply_to),
                     Message::SetNoiseCancellation(enabled) => {
                         UserEvent::SetNoiseCancellation(enabled)
                     }
@@ -3226,6 +3671,10 @@ impl RenderEventLoop {
                     Message::SetTelemetryEnabled(enabled) => {
                         UserEvent::SetTelemetryEnabled(enabled)
                     }
+                    Message::SetTrustedControllers(user_ids) => {
+                        UserEvent::SetTrustedControllers(user_ids)
+                    }
+                    Message::SetControlRole(assignment) => UserEvent::SetControlRole(assignment),
                     // Ping is on purpose empty. We use it only for keeping the connection alive.
                     Message::Ping => {
                         continue;
@@ -3278,7 +3727,7 @@ impl RenderEventLoop {
 
         let proxy = self.event_loop.create_proxy();
         let mut application =
-            Application::new(sender, socket_responses, proxy, hang_protection_counter)?;
+            Application::new(sender, proxy, hang_protection_counter, timeline_options)?;
         self.event_loop.run_app(&mut application).map_err(|e| {
             log::error!("Error running application: {e:?}");
             RenderLoopError::EventLoopError(e)
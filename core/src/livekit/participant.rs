@@ -1,8 +1,11 @@
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc;
 
 use crate::livekit::audio::AudioTrackHandle;
+use crate::livekit::transport::PeerInfo;
 use crate::livekit::video::VideoBufferManager;
+use crate::room_service::{Feature, PeerHello, HELLO_TIMEOUT};
 
 pub struct ParticipantInfo {
     name: String,
@@ -12,6 +15,9 @@ pub struct ParticipantInfo {
     camera_buffers: Arc<VideoBufferManager>,
     audio_handle: Option<AudioTrackHandle>,
     camera_stop_tx: Option<mpsc::UnboundedSender<()>>,
+    /// `None` until the participant says hello, or forever for legacy builds.
+    hello: Option<PeerHello>,
+    joined_at: Instant,
 }
 
 impl std::fmt::Debug for ParticipantInfo {
@@ -34,24 +40,14 @@ impl ParticipantInfo {
             camera_buffers: Arc::new(VideoBufferManager::default()),
             audio_handle: None,
             camera_stop_tx: None,
+            hello: None,
+            joined_at: Instant::now(),
         }
     }
 
-    /// Creates a ParticipantInfo from a LiveKit remote participant.
-    /// Extracts name, muted state (from audio tracks), and speaking state.
-    pub fn from_remote_participant(participant: &livekit::participant::RemoteParticipant) -> Self {
-        let name = participant.name();
-        let is_speaking = participant.is_speaking();
-
-        let mut muted = false;
-        for (_, publication) in participant.track_publications() {
-            if publication.kind() == livekit::track::TrackKind::Audio {
-                muted = publication.is_muted();
-                break;
-            }
-        }
-
-        Self::new(name.clone(), muted, is_speaking)
+    /// Creates a ParticipantInfo from a participant reported by the transport.
+    pub fn from_peer(peer: &PeerInfo) -> Self {
+        Self::new(peer.name.clone(), peer.muted, peer.is_speaking)
     }
 
     pub fn name(&self) -> &str {
@@ -98,6 +94,27 @@ impl ParticipantInfo {
         self.camera_stop_tx = Some(tx);
     }
 
+    pub fn set_hello(&mut self, hello: PeerHello) {
+        self.hello = Some(hello);
+    }
+
+    /// The participant's protocol version, 0 for builds that don't say hello.
+    /// `None` while its hello may still be on the way.
+    pub fn protocol_version(&self) -> Option<u32> {
+        match &self.hello {
+            Some(hello) => Some(hello.version),
+            None if self.joined_at.elapsed() >= HELLO_TIMEOUT => Some(PeerHello::legacy().version),
+            None => None,
+        }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        match &self.hello {
+            Some(hello) => hello.supports(feature),
+            None => PeerHello::legacy().supports(feature),
+        }
+    }
+
     pub fn stop_audio_stream(&mut self) {
         // Dropping the handle removes source from mixer and aborts task
         self.audio_handle.take();
@@ -0,0 +1,388 @@
+//! The part of a room `RoomService` talks to for data and participant events.
+//!
+//! `LiveKitTransport` wraps a connected `livekit::Room`, `LoopbackRoom` hands
+//! out in-process transports that deliver to each other, so the remote
+//! control, drawing and clipboard flows run without a LiveKit server.
+//!
+//! Tracks are not abstracted: publishing still goes to the `livekit::Room`
+//! directly, and track and connection events are passed through unchanged as
+//! `TransportEvent::Media`, which the loopback never emits.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use livekit::participant::{LocalParticipant, RemoteParticipant};
+use livekit::track::TrackKind;
+use livekit::{DataPacket, Room, RoomEvent};
+use tokio::sync::mpsc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransportError {
+    #[error("Failed to publish data: {0}")]
+    Publish(String),
+}
+
+pub type PublishFuture<'a> = Pin<Box<dyn Future<Output = Result<(), TransportError>> + Send + 'a>>;
+
+/// A remote participant as seen by the transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub identity: String,
+    pub name: String,
+    pub muted: bool,
+    pub is_speaking: bool,
+}
+
+impl PeerInfo {
+    /// Muted if the first audio publication is.
+    pub fn from_remote(participant: &RemoteParticipant) -> Self {
+        let muted = participant
+            .track_publications()
+            .values()
+            .find(|publication| publication.kind() == TrackKind::Audio)
+            .is_some_and(|publication| publication.is_muted());
+        Self {
+            identity: participant.identity().as_str().to_string(),
+            name: participant.name(),
+            muted,
+            is_speaking: participant.is_speaking(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// A data packet, `sender` is `None` when the server sent it
+    DataReceived {
+        payload: Arc<Vec<u8>>,
+        topic: Option<String>,
+        sender: Option<String>,
+    },
+    ParticipantConnected(PeerInfo),
+    ParticipantDisconnected(PeerInfo),
+    /// A participant muted or unmuted their microphone
+    AudioMuted {
+        identity: String,
+        muted: bool,
+    },
+    /// Identities of the participants speaking now
+    ActiveSpeakersChanged(Vec<String>),
+    /// LiveKit events carrying tracks or connection state, passed through as is
+    Media(RoomEvent),
+}
+
+/// Data publishing and the participant list of a room. Tracks are not part of
+/// it, see the module docs.
+pub trait RoomTransport: std::fmt::Debug + Send + Sync {
+    fn local_identity(&self) -> String;
+
+    fn local_name(&self) -> String;
+
+    /// Participants that were in the room when the transport was created.
+    fn remote_participants(&self) -> Vec<PeerInfo>;
+
+    /// Sends `packet` to its `destination_identities`, or everyone else if
+    /// empty. We never receive our own packets.
+    fn publish_data(&self, packet: DataPacket) -> PublishFuture<'_>;
+}
+
+#[derive(Debug)]
+pub struct LiveKitTransport {
+    local_participant: LocalParticipant,
+    initial_participants: Vec<PeerInfo>,
+}
+
+impl LiveKitTransport {
+    /// Wraps `room` and translates its `events`, must be called from the
+    /// async runtime.
+    pub fn new(
+        room: &Room,
+        mut events: mpsc::UnboundedReceiver<RoomEvent>,
+    ) -> (Self, mpsc::UnboundedReceiver<TransportEvent>) {
+        let initial_participants = room
+            .remote_participants()
+            .values()
+            .map(PeerInfo::from_remote)
+            .collect();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if tx.send(translate(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let transport = Self {
+            local_participant: room.local_participant(),
+            initial_participants,
+        };
+        (transport, rx)
+    }
+}
+
+fn translate(event: RoomEvent) -> TransportEvent {
+    match event {
+        RoomEvent::DataReceived {
+            payload,
+            topic,
+            kind: _,
+            participant,
+        } => TransportEvent::DataReceived {
+            payload,
+            topic,
+            sender: participant.map(|p| p.identity().as_str().to_string()),
+        },
+        RoomEvent::ParticipantConnected(participant) => {
+            TransportEvent::ParticipantConnected(PeerInfo::from_remote(&participant))
+        }
+        RoomEvent::ParticipantDisconnected(participant) => {
+            TransportEvent::ParticipantDisconnected(PeerInfo::from_remote(&participant))
+        }
+        RoomEvent::TrackMuted {
+            participant,
+            publication,
+        } if publication.kind() == TrackKind::Audio => TransportEvent::AudioMuted {
+            identity: participant.identity().as_str().to_string(),
+            muted: true,
+        },
+        RoomEvent::TrackUnmuted {
+            participant,
+            publication,
+        } if publication.kind() == TrackKind::Audio => TransportEvent::AudioMuted {
+            identity: participant.identity().as_str().to_string(),
+            muted: false,
+        },
+        RoomEvent::ActiveSpeakersChanged { speakers } => TransportEvent::ActiveSpeakersChanged(
+            speakers
+                .iter()
+                .map(|speaker| speaker.identity().as_str().to_string())
+                .collect(),
+        ),
+        event => TransportEvent::Media(event),
+    }
+}
+
+impl RoomTransport for LiveKitTransport {
+    fn local_identity(&self) -> String {
+        self.local_participant.identity().as_str().to_string()
+    }
+
+    fn local_name(&self) -> String {
+        self.local_participant.name()
+    }
+
+    fn remote_participants(&self) -> Vec<PeerInfo> {
+        self.initial_participants.clone()
+    }
+
+    fn publish_data(&self, packet: DataPacket) -> PublishFuture<'_> {
+        Box::pin(async move {
+            self.local_participant
+                .publish_data(packet)
+                .await
+                .map_err(|e| TransportError::Publish(format!("{e:?}")))
+        })
+    }
+}
+
+#[derive(Debug)]
+struct LoopbackPeer {
+    info: PeerInfo,
+    events: mpsc::UnboundedSender<TransportEvent>,
+}
+
+/// An in-process room, every transport joined to it receives what the
+/// others publish.
+#[derive(Debug, Default)]
+pub struct LoopbackRoom {
+    peers: Mutex<Vec<LoopbackPeer>>,
+}
+
+impl LoopbackRoom {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Joins as `identity`, the participants already here get
+    /// `ParticipantConnected`. Dropping the transport leaves the room.
+    pub fn join(
+        self: &Arc<Self>,
+        identity: &str,
+        name: &str,
+    ) -> (LoopbackTransport, mpsc::UnboundedReceiver<TransportEvent>) {
+        let info = PeerInfo {
+            identity: identity.to_string(),
+            name: name.to_string(),
+            muted: false,
+            is_speaking: false,
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut peers = self.peers.lock().unwrap();
+        for peer in peers.iter() {
+            let _ = peer
+                .events
+                .send(TransportEvent::ParticipantConnected(info.clone()));
+        }
+        peers.push(LoopbackPeer {
+            info: info.clone(),
+            events: tx,
+        });
+        let transport = LoopbackTransport {
+            room: self.clone(),
+            info,
+        };
+        (transport, rx)
+    }
+
+    fn send_to_others(&self, from: &str, event: impl Fn(&PeerInfo) -> Option<TransportEvent>) {
+        let peers = self.peers.lock().unwrap();
+        for peer in peers.iter().filter(|peer| peer.info.identity != from) {
+            if let Some(event) = event(&peer.info) {
+                let _ = peer.events.send(event);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoopbackTransport {
+    room: Arc<LoopbackRoom>,
+    info: PeerInfo,
+}
+
+impl LoopbackTransport {
+    /// Tells the others our microphone was muted or unmuted.
+    pub fn set_muted(&self, muted: bool) {
+        let identity = self.info.identity.clone();
+        self.room.send_to_others(&self.info.identity, |_| {
+            Some(TransportEvent::AudioMuted {
+                identity: identity.clone(),
+                muted,
+            })
+        });
+    }
+}
+
+impl RoomTransport for LoopbackTransport {
+    fn local_identity(&self) -> String {
+        self.info.identity.clone()
+    }
+
+    fn local_name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    fn remote_participants(&self) -> Vec<PeerInfo> {
+        let peers = self.room.peers.lock().unwrap();
+        peers
+            .iter()
+            .filter(|peer| peer.info.identity != self.info.identity)
+            .map(|peer| peer.info.clone())
+            .collect()
+    }
+
+    fn publish_data(&self, packet: DataPacket) -> PublishFuture<'_> {
+        let payload = Arc::new(packet.payload);
+        let destinations = packet.destination_identities;
+        self.room.send_to_others(&self.info.identity, |peer| {
+            let addressed = destinations.is_empty()
+                || destinations
+                    .iter()
+                    .any(|identity| identity.as_str() == peer.identity);
+            addressed.then(|| TransportEvent::DataReceived {
+                payload: payload.clone(),
+                topic: packet.topic.clone(),
+                sender: Some(self.info.identity.clone()),
+            })
+        });
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl Drop for LoopbackTransport {
+    fn drop(&mut self) {
+        let mut peers = self.room.peers.lock().unwrap();
+        peers.retain(|peer| peer.info.identity != self.info.identity);
+        for peer in peers.iter() {
+            let _ = peer
+                .events
+                .send(TransportEvent::ParticipantDisconnected(self.info.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(payload: &[u8], topic: &str) -> DataPacket {
+        DataPacket {
+            payload: payload.to_vec(),
+            topic: Some(topic.to_string()),
+            reliable: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loopback_delivers_to_everyone_else() {
+        let room = LoopbackRoom::new();
+        let (sharer, mut sharer_events) = room.join("a:b:sharer:audio", "Sharer");
+        let (controller, mut controller_events) = room.join("a:b:controller:audio", "Controller");
+
+        match sharer_events.recv().await.unwrap() {
+            TransportEvent::ParticipantConnected(peer) => assert_eq!(peer.name, "Controller"),
+            other => panic!("expected ParticipantConnected, got {other:?}"),
+        }
+        assert_eq!(controller.remote_participants().len(), 1);
+
+        controller
+            .publish_data(packet(b"hi", "draw"))
+            .await
+            .unwrap();
+        match sharer_events.recv().await.unwrap() {
+            TransportEvent::DataReceived {
+                payload,
+                topic,
+                sender,
+            } => {
+                assert_eq!(payload.as_slice(), b"hi");
+                assert_eq!(topic.as_deref(), Some("draw"));
+                assert_eq!(sender.as_deref(), Some("a:b:controller:audio"));
+            }
+            other => panic!("expected DataReceived, got {other:?}"),
+        }
+        assert!(controller_events.try_recv().is_err());
+
+        drop(sharer);
+        assert!(matches!(
+            controller_events.recv().await.unwrap(),
+            TransportEvent::ParticipantDisconnected(peer) if peer.name == "Sharer"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_loopback_respects_destinations() {
+        let room = LoopbackRoom::new();
+        let (sender, _sender_events) = room.join("sender", "Sender");
+        let (_target, mut target_events) = room.join("target", "Target");
+        let (_other, mut other_events) = room.join("other", "Other");
+
+        let mut addressed = packet(b"for you", "clipboard");
+        addressed.destination_identities = vec!["target".to_string().into()];
+        sender.publish_data(addressed).await.unwrap();
+
+        assert!(matches!(
+            target_events.recv().await.unwrap(),
+            TransportEvent::ParticipantConnected(_)
+        ));
+        assert!(matches!(
+            target_events.recv().await.unwrap(),
+            TransportEvent::DataReceived { .. }
+        ));
+        assert!(matches!(
+            other_events.try_recv(),
+            Err(mpsc::error::TryRecvError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+//! Compact binary form of the `ClientEvent`s sent several times per frame.
+//!
+//! Cursor moves and drawing points are fixed 5 byte packets instead of
+//! tagged JSON: a kind byte followed by x and y, each a normalized coordinate
+//! quantized to a little-endian u16. JSON events always start with `{`, so
+//! the first byte tells the two forms apart. Only sent once every peer
+//! advertises `Feature::BinaryEvents`, older peers keep getting JSON.
+use crate::room_service::{ClientEvent, ClientPoint};
+
+const KIND_MOUSE_MOVE: u8 = 0x01;
+const KIND_DRAW_ADD_POINT: u8 = 0x02;
+
+/// Kind byte plus two u16 coordinates.
+const EVENT_LEN: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WireError {
+    #[error("Unknown binary event kind {0:#04x}")]
+    UnknownKind(u8),
+    #[error("Binary event of {0} bytes, expected {EVENT_LEN}")]
+    Length(usize),
+}
+
+/// Whether `payload` is a binary event rather than JSON.
+pub fn is_binary(payload: &[u8]) -> bool {
+    payload.first().is_some_and(|byte| *byte != b'{')
+}
+
+/// Encodes `event` in binary, `None` for events that only exist as JSON.
+pub fn encode(event: &ClientEvent) -> Option<[u8; EVENT_LEN]> {
+    let (kind, point) = match event {
+        ClientEvent::MouseMove(point) => (KIND_MOUSE_MOVE, point),
+        ClientEvent::DrawAddPoint(point) => (KIND_DRAW_ADD_POINT, point),
+        _ => return None,
+    };
+    let mut packet = [0; EVENT_LEN];
+    packet[0] = kind;
+    packet[1..3].copy_from_slice(&quantize(point.x).to_le_bytes());
+    packet[3..5].copy_from_slice(&quantize(point.y).to_le_bytes());
+    Some(packet)
+}
+
+pub fn decode(payload: &[u8]) -> Result<ClientEvent, WireError> {
+    if payload.len() != EVENT_LEN {
+        return Err(WireError::Length(payload.len()));
+    }
+    let point = ClientPoint {
+        x: dequantize(u16::from_le_bytes([payload[1], payload[2]])),
+        y: dequantize(u16::from_le_bytes([payload[3], payload[4]])),
+    };
+    match payload[0] {
+        KIND_MOUSE_MOVE => Ok(ClientEvent::MouseMove(point)),
+        KIND_DRAW_ADD_POINT => Ok(ClientEvent::DrawAddPoint(point)),
+        kind => Err(WireError::UnknownKind(kind)),
+    }
+}
+
+/// Maps a coordinate in [0, 1] to the full u16 range, a step is well under
+/// a pixel even on 8K displays.
+fn quantize(value: f64) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+}
+
+fn dequantize(value: u16) -> f64 {
+    value as f64 / u16::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_is_within_a_quantization_step() {
+        let event = ClientEvent::DrawAddPoint(ClientPoint {
+            x: 0.123456,
+            y: 0.987654,
+        });
+        let packet = encode(&event).unwrap();
+        assert!(is_binary(&packet));
+        match decode(&packet).unwrap() {
+            ClientEvent::DrawAddPoint(point) => {
+                assert!((point.x - 0.123456).abs() <= 1.0 / u16::MAX as f64);
+                assert!((point.y - 0.987654).abs() <= 1.0 / u16::MAX as f64);
+            }
+            other => panic!("expected DrawAddPoint, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_json_events_are_not_binary() {
+        let event = ClientEvent::MouseMove(ClientPoint { x: 0.5, y: 0.5 });
+        assert!(!is_binary(&serde_json::to_vec(&event).unwrap()));
+        assert!(encode(&ClientEvent::DrawClearAllPaths).is_none());
+    }
+
+    #[test]
+    fn test_malformed_packets_are_rejected() {
+        assert!(matches!(
+            decode(&[KIND_MOUSE_MOVE, 0, 0]),
+            Err(WireError::Length(3))
+        ));
+        assert!(matches!(
+            decode(&[0x7f, 0, 0, 0, 0]),
+            Err(WireError::UnknownKind(0x7f))
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use clap::Parser;
+use hopp_core::timeline::TimelineOptions;
 use hopp_core::RenderEventLoop;
 use sentry_utils::init_sentry;
+use std::path::PathBuf;
 
 /// Hopp Core - Remote Desktop Control System
 #[derive(Parser, Debug)]
@@ -13,6 +15,18 @@ struct Args {
     /// Socket name
     #[arg(long)]
     socket_path: Option<String>,
+
+    /// Secret the client has to present when connecting to the socket
+    #[arg(long)]
+    auth_token: Option<String>,
+
+    /// Directory to record a timeline of every screen share's events to
+    #[arg(long)]
+    timeline_dir: Option<PathBuf>,
+
+    /// Leave keystroke keys and clipboard contents out of timelines
+    #[arg(long, requires = "timeline_dir")]
+    timeline_redact: bool,
 }
 
 fn main() -> Result<(), impl std::error::Error> {
@@ -40,6 +54,11 @@ fn main() -> Result<(), impl std::error::Error> {
             .to_string(),
     };
 
+    let timeline_options = args.timeline_dir.map(|dir| TimelineOptions {
+        dir,
+        redact: args.timeline_redact,
+    });
+
     let render_event_loop = RenderEventLoop::new();
-    render_event_loop.run(socket_path)
+    render_event_loop.run(socket_path, args.auth_token, timeline_options)
 }
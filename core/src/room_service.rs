@@ -15,13 +15,18 @@ use tokio::runtime::Handle as TokioHandle;
 use crate::audio::mixer::SharedProcessor;
 use crate::livekit::audio::AudioPublisher;
 use crate::livekit::participant::ParticipantInfo;
+use crate::livekit::transport::{LiveKitTransport, PeerInfo, RoomTransport, TransportEvent};
 use crate::livekit::video::{process_video_stream, VideoBufferManager};
+use crate::livekit::wire;
 
 use serde::{Deserialize, Serialize};
+use socket_lib::ControlRole;
 use tokio::sync::{mpsc, oneshot, Mutex};
-use winit::event_loop::EventLoopProxy;
+use winit::event_loop::{EventLoopClosed, EventLoopProxy};
 
+use crate::input::control;
 use crate::snapshot_sender::SnapshotSender;
+use crate::timeline::{self, RecordingTransport, Timeline};
 use crate::{audio, ParticipantData, UserEvent};
 
 // Constants for magic values
@@ -31,6 +36,8 @@ const TOPIC_PARTICIPANT_IN_CONTROL: &str = "participant_in_control";
 const TOPIC_TICK_RESPONSE: &str = "tick_response";
 const VIDEO_TRACK_NAME: &str = "screen_share";
 const TOPIC_DRAW: &str = "draw";
+const TOPIC_HELLO: &str = "hello";
+const TOPIC_CONTROL: &str = "control";
 const MAX_FRAMERATE: f64 = 40.0;
 const CAMERA_TRACK_NAME: &str = "camera";
 const CAMERA_MAX_BITRATE: u64 = 3_500_000; // ~3.5Mbps for 1080p
@@ -53,8 +60,39 @@ pub struct CreateRoomParams {
     pub start_camera_on_call: bool,
 }
 
+/// A room that is already connected, e.g. one joined on a `LoopbackRoom`.
+pub struct JoinTransportParams {
+    pub transport: Arc<dyn RoomTransport>,
+    pub events: mpsc::UnboundedReceiver<TransportEvent>,
+    pub event_sink: EventSink,
+}
+
+/// Where the room's events go, the winit event loop or a channel when no
+/// windows are needed.
+#[derive(Clone)]
+pub enum EventSink {
+    EventLoop(EventLoopProxy<UserEvent>),
+    Channel(std::sync::mpsc::Sender<UserEvent>),
+}
+
+impl EventSink {
+    pub fn send_event(&self, event: UserEvent) -> Result<(), EventLoopClosed<UserEvent>> {
+        match self {
+            Self::EventLoop(proxy) => proxy.send_event(event),
+            Self::Channel(tx) => tx.send(event).map_err(|e| EventLoopClosed(e.0)),
+        }
+    }
+}
+
+impl From<EventLoopProxy<UserEvent>> for EventSink {
+    fn from(proxy: EventLoopProxy<UserEvent>) -> Self {
+        Self::EventLoop(proxy)
+    }
+}
+
 enum RoomServiceCommand {
     CreateRoom(CreateRoomParams),
+    JoinTransport(JoinTransportParams),
     PublishCursorPosition(f64, f64, bool),
     PublishControllerCursorEnabled(bool),
     DestroyRoom,
@@ -80,12 +118,14 @@ enum RoomServiceCommand {
     PublishPasteFromClipboard(PasteFromClipboardData),
     PublishClipboardData(ClipboardDataPayload),
     PublishClickAnimation(ClientPoint),
+    PublishControl(ClientEvent),
 }
 
 impl std::fmt::Debug for RoomServiceCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::CreateRoom { .. } => write!(f, "CreateRoom"),
+            Self::JoinTransport(..) => write!(f, "JoinTransport"),
             Self::PublishCursorPosition(..) => write!(f, "PublishCursorPosition"),
             Self::PublishControllerCursorEnabled(v) => {
                 write!(f, "PublishControllerCursorEnabled({v})")
@@ -115,6 +155,7 @@ impl std::fmt::Debug for RoomServiceCommand {
             Self::PublishPasteFromClipboard(..) => write!(f, "PublishPasteFromClipboard"),
             Self::PublishClipboardData(..) => write!(f, "PublishClipboardData"),
             Self::PublishClickAnimation(..) => write!(f, "PublishClickAnimation"),
+            Self::PublishControl(v) => write!(f, "PublishControl({v:?})"),
         }
     }
 }
@@ -141,6 +182,8 @@ pub(crate) struct RoomServiceInner {
     // TODO: See if we can use a sync::Mutex instead of tokio::sync::Mutex
     pub(crate) room: Mutex<Option<Room>>,
     pub(crate) video_room: Mutex<Option<Room>>,
+    /// Carries data and participant events, set once the room connects.
+    transport: std::sync::Mutex<Option<Arc<dyn RoomTransport>>>,
     buffer_source: std::sync::Mutex<Option<NativeVideoSource>>,
     camera_buffer_source: std::sync::Mutex<Option<NativeVideoSource>>,
     camera_track: std::sync::Mutex<Option<LocalVideoTrack>>,
@@ -151,10 +194,18 @@ pub(crate) struct RoomServiceInner {
     connection_quality: Arc<std::sync::Mutex<Option<ConnectionQuality>>>,
     cancel_connect: std::sync::Mutex<Vec<oneshot::Sender<()>>>,
     snapshot_sender: SnapshotSender,
+    timeline: Arc<Timeline>,
 }
 
 impl RoomServiceInner {
+    fn transport(&self) -> Option<Arc<dyn RoomTransport>> {
+        self.transport.lock().unwrap().clone()
+    }
+
     async fn clear(&self) {
+        {
+            self.transport.lock().unwrap().take();
+        }
         {
             let mut inner_room = self.room.lock().await;
             if let Some(room) = inner_room.take() {
@@ -207,35 +258,30 @@ impl RoomServiceInner {
 /// Returns `true` if the participant was newly inserted.
 fn insert_participant_if_absent(
     participants: &std::sync::RwLock<HashMap<String, ParticipantInfo>>,
-    identity: &str,
-    remote_participant: &livekit::participant::RemoteParticipant,
+    peer: &PeerInfo,
 ) -> bool {
-    if remote_participant.identity().as_str().contains("video") {
+    if peer.identity.contains("video") {
         return false;
     }
     let mut guard = participants.write().unwrap();
-    if guard.contains_key(identity) {
+    if guard.contains_key(&peer.identity) {
         return false;
     }
-    guard.insert(
-        identity.to_string(),
-        ParticipantInfo::from_remote_participant(remote_participant),
-    );
+    guard.insert(peer.identity.clone(), ParticipantInfo::from_peer(peer));
     true
 }
 
 /// Iterates over the remote participants in the room and inserts them into the
 /// participants hashmap if not already present.
-fn populate_participants_from_room(
-    room: &Room,
+fn populate_participants(
+    transport: &dyn RoomTransport,
     participants: &std::sync::RwLock<HashMap<String, ParticipantInfo>>,
 ) {
-    for (_, remote_participant) in room.remote_participants() {
-        let identity = remote_participant.identity().as_str().to_string();
-        if insert_participant_if_absent(participants, &identity, &remote_participant) {
+    for peer in transport.remote_participants() {
+        if insert_participant_if_absent(participants, &peer) {
             log::info!(
-                "populate_participants_from_room: participant added: {}",
-                identity
+                "populate_participants: participant added: {}",
+                peer.identity
             );
         }
     }
@@ -270,6 +316,7 @@ impl RoomService {
     /// # Arguments
     ///
     /// * `livekit_server_url` - The URL of the LiveKit server to connect to
+    /// * `timeline` - Records the events sent and received in the room
     ///
     /// # Returns
     ///
@@ -277,7 +324,8 @@ impl RoomService {
     /// * `Err(std::io::Error)` - If the async runtime could not be created
     pub fn new(
         livekit_server_url: String,
-        socket: socket_lib::SocketSender,
+        socket: socket_lib::server::ServerSender,
+        timeline: Arc<Timeline>,
     ) -> Result<Self, std::io::Error> {
         livekit::webrtc::enable_zero_playout_delay().map_err(std::io::Error::other)?;
 
@@ -291,6 +339,7 @@ impl RoomService {
         let inner = Arc::new(RoomServiceInner {
             room: Mutex::new(None),
             video_room: Mutex::new(None),
+            transport: std::sync::Mutex::new(None),
             buffer_source: std::sync::Mutex::new(None),
             camera_buffer_source: std::sync::Mutex::new(None),
             camera_track: std::sync::Mutex::new(None),
@@ -305,6 +354,7 @@ impl RoomService {
             connection_quality: Arc::new(std::sync::Mutex::new(None)),
             cancel_connect: std::sync::Mutex::new(Vec::new()),
             snapshot_sender,
+            timeline,
         });
         let audio_runtime = tokio::runtime::Builder::new_multi_thread()
             .worker_threads(2)
@@ -363,6 +413,16 @@ impl RoomService {
         Ok(())
     }
 
+    /// Joins the room behind `params.transport` instead of connecting to
+    /// LiveKit. Data and participant events work as in a call, media tracks
+    /// are never published.
+    pub fn join_transport(&self, params: JoinTransportParams) -> Result<(), RoomServiceError> {
+        log::info!("join_transport");
+        self.service_command_tx
+            .send(RoomServiceCommand::JoinTransport(params))
+            .map_err(|e| RoomServiceError::CreateRoom(format!("Failed to send command: {e:?}")))
+    }
+
     /// Destroys the current room connection.
     pub fn destroy_room(&self) {
         log::info!("destroy_room");
@@ -686,6 +746,41 @@ impl RoomService {
         }
     }
 
+    /// Asks the sharer for control of their mouse and keyboard.
+    pub fn request_control(&self) {
+        self.publish_control(ClientEvent::RequestControl);
+    }
+
+    /// Hands control to `identity`, only their input is simulated until revoked.
+    pub fn grant_control(&self, identity: String) {
+        self.publish_control(ClientEvent::GrantControl(ControlTarget { identity }));
+    }
+
+    /// Turns down a control request from `identity`.
+    pub fn deny_control(&self, identity: String) {
+        self.publish_control(ClientEvent::DenyControl(ControlTarget { identity }));
+    }
+
+    /// Takes control back from `identity`.
+    pub fn revoke_control(&self, identity: String) {
+        self.publish_control(ClientEvent::RevokeControl(ControlTarget { identity }));
+    }
+
+    /// Announces what everyone may do on our screen.
+    pub fn publish_control_roles(&self, roles: ControlRoles) {
+        self.publish_control(ClientEvent::ControlRoles(roles));
+    }
+
+    fn publish_control(&self, event: ClientEvent) {
+        log::info!("publish_control: {event:?}");
+        let res = self
+            .service_command_tx
+            .send(RoomServiceCommand::PublishControl(event));
+        if let Err(e) = res {
+            log::error!("publish_control: Failed to send command: {e:?}");
+        }
+    }
+
     /// Retrieves the camera video source buffer.
     pub fn get_camera_buffer_source(&self) -> Option<NativeVideoSource> {
         log::info!("get_camera_buffer_source");
@@ -723,6 +818,37 @@ impl RoomService {
         true
     }
 
+    /// Whether every remote participant supports `feature`.
+    pub fn peers_support(&self, feature: Feature) -> bool {
+        let participants = self.inner.participants.read().unwrap();
+        all_peers_support(&participants, feature)
+    }
+
+    /// Whether the remote participant `identity` supports `feature`.
+    pub fn peer_supports(&self, identity: &str, feature: Feature) -> bool {
+        let participants = self.inner.participants.read().unwrap();
+        participants
+            .get(identity)
+            .is_some_and(|info| info.supports(feature))
+    }
+
+    /// Whether the participant sharing their screen supports `feature`, false
+    /// when nobody is sharing.
+    pub fn sharer_supports(&self, feature: Feature) -> bool {
+        self.sharer_identity()
+            .is_some_and(|identity| self.peer_supports(&identity, feature))
+    }
+
+    /// The remote participant sharing their screen, if any.
+    pub fn sharer_identity(&self) -> Option<String> {
+        let participants = self.inner.participants.read().unwrap();
+        participants
+            .iter()
+            .filter(|(key, _)| *key != "local")
+            .find(|(_, info)| info.is_screensharing())
+            .map(|(key, _)| key.clone())
+    }
+
     /// Builds a participants snapshot and sends it directly over the socket.
     pub fn send_participants_snapshot(&self) {
         self.inner.snapshot_sender.send_participants_snapshot();
@@ -740,6 +866,27 @@ impl RoomService {
     }
 }
 
+fn all_peers_support(participants: &HashMap<String, ParticipantInfo>, feature: Feature) -> bool {
+    participants
+        .iter()
+        .filter(|(key, _)| *key != "local")
+        .all(|(_, info)| info.supports(feature))
+}
+
+/// Serializes an event sent several times per frame, in the binary form once
+/// every peer reads it.
+fn hot_event_payload(
+    event: &ClientEvent,
+    participants: &std::sync::RwLock<HashMap<String, ParticipantInfo>>,
+) -> Vec<u8> {
+    if all_peers_support(&participants.read().unwrap(), Feature::BinaryEvents) {
+        if let Some(packet) = wire::encode(event) {
+            return packet.to_vec();
+        }
+    }
+    serde_json::to_vec(event).unwrap()
+}
+
 fn collect_remote_participants(
     participants: &Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
     sharer_identity: &str,
@@ -1080,9 +1227,13 @@ async fn room_service_commands(
                     inner.clone(),
                 )));
                 log::info!("room_service_commands: Spawned stats task");
-                let user_identity = room.local_participant().identity().as_str().to_string();
-                let user_name = room.local_participant().name();
-                log::info!("room_service_commands: Got user identity and name");
+                let (transport, events) = LiveKitTransport::new(&room, rx);
+                let transport: Arc<dyn RoomTransport> = Arc::new(RecordingTransport::new(
+                    Arc::new(transport),
+                    inner.timeline.clone(),
+                ));
+                let user_name = transport.local_name();
+                log::info!("room_service_commands: Got user name");
                 {
                     let mut participants = inner.participants.write().unwrap();
                     participants.insert(
@@ -1097,7 +1248,7 @@ async fn room_service_commands(
                     "room_service_commands: Inserted local participant into participants map"
                 );
 
-                populate_participants_from_room(&room, &inner.participants);
+                populate_participants(transport.as_ref(), &inner.participants);
 
                 // Handle video room result — optional, failure is non-fatal.
                 let mut video_rx_opt: Option<mpsc::UnboundedReceiver<RoomEvent>> = None;
@@ -1141,23 +1292,15 @@ async fn room_service_commands(
                 );
                 let snapshot = inner.snapshot_sender.build_snapshot();
                 let _ = event_loop_proxy.send_event(UserEvent::CreateRoomResult(Ok(snapshot)));
-                tokio::spawn(handle_room_events(RoomEventContext {
-                    receiver: rx,
-                    event_loop_proxy,
-                    user_identity,
+                spawn_room_events(
+                    &inner,
+                    transport,
+                    events,
+                    event_loop_proxy.into(),
                     video_participant_identity,
-                    participants: inner.participants.clone(),
-                    snapshot_sender: inner.snapshot_sender.clone(),
-                    mixer,
-                    remote_screen_share: RemoteScreenShare {
-                        buffer: inner.remote_screen_share.buffer.clone(),
-                        stop_tx: inner.remote_screen_share.stop_tx.clone(),
-                        publisher_identity: inner.remote_screen_share.publisher_identity.clone(),
-                    },
-                    connection_quality: inner.connection_quality.clone(),
-                    audio_handle: audio_handle.clone(),
-                }));
-                log::info!("room_service_commands: Spawned handle_room_events");
+                    Some(mixer),
+                    audio_handle.clone(),
+                );
                 if let Some(video_rx) = video_rx_opt {
                     tokio::spawn(drain_video_room_events(video_rx));
                     log::info!("room_service_commands: Spawned video_room event drainer");
@@ -1165,6 +1308,30 @@ async fn room_service_commands(
                 let mut inner_room = inner.room.lock().await;
                 *inner_room = Some(room);
             }
+            RoomServiceCommand::JoinTransport(JoinTransportParams {
+                transport,
+                events,
+                event_sink,
+            }) => {
+                log::info!("room_service_commands: JoinTransport");
+                inner.clear().await;
+                let transport: Arc<dyn RoomTransport> =
+                    Arc::new(RecordingTransport::new(transport, inner.timeline.clone()));
+                inner.participants.write().unwrap().insert(
+                    "local".to_string(),
+                    ParticipantInfo::new(transport.local_name(), false, false),
+                );
+                populate_participants(transport.as_ref(), &inner.participants);
+                spawn_room_events(
+                    &inner,
+                    transport,
+                    events,
+                    event_sink,
+                    String::new(),
+                    None,
+                    audio_handle.clone(),
+                );
+            }
             RoomServiceCommand::DestroyRoom => {
                 if let Some(task) = stats_task.take() {
                     task.abort();
@@ -1202,17 +1369,16 @@ async fn room_service_commands(
                     continue;
                 }
                 last_cursor_publish = now;
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
-                let res = local_participant
+                };
+                let res = transport
                     .publish_data(DataPacket {
-                        payload: serde_json::to_vec(&ClientEvent::MouseMove(ClientPoint { x, y }))
-                            .unwrap(),
+                        payload: hot_event_payload(
+                            &ClientEvent::MouseMove(ClientPoint { x, y }),
+                            &inner.participants,
+                        ),
                         reliable: true,
                         topic: Some(TOPIC_SHARER_LOCATION.to_string()),
                         ..Default::default()
@@ -1226,14 +1392,11 @@ async fn room_service_commands(
                 );
             }
             RoomServiceCommand::PublishControllerCursorEnabled(enabled) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
-                let res = local_participant
+                };
+                let res = transport
                     .publish_data(DataPacket {
                         payload: serde_json::to_vec(&ClientEvent::RemoteControlEnabled(
                             RemoteControlEnabled { enabled },
@@ -1251,14 +1414,11 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::TickResponse(time) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
-                let res = local_participant
+                };
+                let res = transport
                     .publish_data(DataPacket {
                         payload: serde_json::to_vec(&ClientEvent::TickResponse(TickData { time }))
                             .unwrap(),
@@ -1272,14 +1432,11 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishParticipantInControl(participant) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
-                let res = local_participant
+                };
+                let res = transport
                     .publish_data(DataPacket {
                         payload: participant.to_string().as_bytes().to_vec(),
                         reliable: true,
@@ -1294,16 +1451,13 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawStart(point) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
                 let event = ClientEvent::DrawStart(point);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1317,16 +1471,13 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawAddPoint(point) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
                 let event = ClientEvent::DrawAddPoint(point);
-                let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let payload = hot_event_payload(&event, &inner.participants);
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: false,
@@ -1340,16 +1491,13 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawEnd(point) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
                 let event = ClientEvent::DrawEnd(point);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1363,19 +1511,16 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawClearPaths(path_ids) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 // Send individual DrawClearPath events for each path ID
                 for path_id in path_ids {
                     let event = ClientEvent::DrawClearPath { path_id };
                     let payload = serde_json::to_vec(&event).unwrap();
-                    let res = local_participant
+                    let res = transport
                         .publish_data(DataPacket {
                             payload,
                             reliable: true,
@@ -1393,16 +1538,13 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawClearAllPaths => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
                 let event = ClientEvent::DrawClearAllPaths;
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1418,16 +1560,13 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishDrawingMode(mode) => {
-                let room = inner.room.lock().await;
-                if room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist");
                     continue;
-                }
-                let room = room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
                 let event = ClientEvent::DrawingMode(mode);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1440,6 +1579,35 @@ async fn room_service_commands(
                     log::error!("room_service_commands: Failed to publish drawing mode: {e:?}");
                 }
             }
+            RoomServiceCommand::PublishControl(event) => {
+                let Some(transport) = inner.transport() else {
+                    log::warn!("room_service_commands: Room doesn't exist");
+                    continue;
+                };
+                /* Answers only concern the controller they name. */
+                let destination_identities = match &event {
+                    ClientEvent::GrantControl(target)
+                    | ClientEvent::DenyControl(target)
+                    | ClientEvent::RevokeControl(target) => {
+                        vec![target.identity.clone().into()]
+                    }
+                    _ => Vec::new(),
+                };
+                let payload = serde_json::to_vec(&event).unwrap();
+                let res = transport
+                    .publish_data(DataPacket {
+                        payload,
+                        reliable: true,
+                        topic: Some(TOPIC_CONTROL.to_string()),
+                        destination_identities,
+                        ..Default::default()
+                    })
+                    .await;
+
+                if let Err(e) = res {
+                    log::error!("room_service_commands: Failed to publish control event: {e:?}");
+                }
+            }
             RoomServiceCommand::UnpublishAudioTrack => {
                 if let Some(publisher) = audio_publisher.take() {
                     let inner_room = inner.room.lock().await;
@@ -1506,17 +1674,14 @@ async fn room_service_commands(
                 log::info!("room_service_commands: Screen share track unmuted");
             }
             RoomServiceCommand::PublishMouseClick(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist for PublishMouseClick");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::MouseClick(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1530,17 +1695,14 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishKeystroke(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist for PublishKeystroke");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::Keystroke(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1554,17 +1716,14 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishWheelEvent(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!("room_service_commands: Room doesn't exist for PublishWheelEvent");
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::WheelEvent(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1578,19 +1737,16 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishAddToClipboard(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!(
                         "room_service_commands: Room doesn't exist for PublishAddToClipboard"
                     );
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::AddToClipboard(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1604,19 +1760,16 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishPasteFromClipboard(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!(
                         "room_service_commands: Room doesn't exist for PublishPasteFromClipboard"
                     );
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::PasteFromClipboard(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1632,19 +1785,16 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishClipboardData(data) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!(
                         "room_service_commands: Room doesn't exist for PublishClipboardData"
                     );
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::ClipboardData(data);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1658,19 +1808,16 @@ async fn room_service_commands(
                 }
             }
             RoomServiceCommand::PublishClickAnimation(point) => {
-                let inner_room = inner.room.lock().await;
-                if inner_room.is_none() {
+                let Some(transport) = inner.transport() else {
                     log::warn!(
                         "room_service_commands: Room doesn't exist for PublishClickAnimation"
                     );
                     continue;
-                }
-                let room = inner_room.as_ref().unwrap();
-                let local_participant = room.local_participant();
+                };
 
                 let event = ClientEvent::ClickAnimation(point);
                 let payload = serde_json::to_vec(&event).unwrap();
-                let res = local_participant
+                let res = transport
                     .publish_data(DataPacket {
                         payload,
                         reliable: true,
@@ -1820,6 +1967,41 @@ pub struct ClipboardDataPayload {
     pub data: Option<ClipboardPayload>,
 }
 
+/// The controller a control handoff answer is meant for.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ControlTarget {
+    /// Identity of the controller
+    pub identity: String,
+}
+
+/// What everyone may do on the sharer's screen.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ControlRoles {
+    /// Role of everyone the sharer didn't assign one to
+    pub default_role: ControlRole,
+    /// Assigned roles by user id
+    pub roles: HashMap<String, ControlRole>,
+}
+
+impl Default for ControlRoles {
+    fn default() -> Self {
+        Self {
+            default_role: ControlRole::Full,
+            roles: HashMap::new(),
+        }
+    }
+}
+
+impl ControlRoles {
+    /// The role of the participant `identity`.
+    pub fn role(&self, identity: &str) -> ControlRole {
+        control::user_id(identity)
+            .and_then(|user_id| self.roles.get(user_id))
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+}
+
 /// Settings specific to the Draw mode.
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub struct DrawSettings {
@@ -1884,13 +2066,123 @@ pub enum ClientEvent {
     DrawClearAllPaths,
     /// Click animation at a point
     ClickAnimation(ClientPoint),
+    /// Protocol version and features of the sender
+    Hello(PeerHello),
+    /// A controller asks the sharer for control
+    RequestControl,
+    /// The sharer gives control to a controller
+    GrantControl(ControlTarget),
+    /// The sharer turns down a controller's request
+    DenyControl(ControlTarget),
+    /// The sharer takes control back from a controller
+    RevokeControl(ControlTarget),
+    /// The sharer tells everyone what they may do on the shared screen
+    ControlRoles(ControlRoles),
+}
+
+/// Version of the `ClientEvent` protocol this build speaks. Bump it when an
+/// event changes in a way older builds can't read.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long a participant has to say hello before it's taken for a build
+/// from before versioning.
+pub const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Optional in-room features, only used when every participant supports them.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Copy and paste between the controller's and the sharer's clipboards
+    ClipboardSync,
+    /// Click animations sent by controllers
+    ClickAnimation,
+    /// Cursor moves and drawing points in the binary form of `livekit::wire`
+    BinaryEvents,
+    /// Controllers ask for control instead of taking it with a click
+    ControlHandoff,
+    /// The sharer announces per participant roles with `ClientEvent::ControlRoles`
+    ControlRoles,
+    /// A feature added by a newer build
+    #[serde(other)]
+    Unknown,
+}
+
+/// What a participant speaks, sent as `ClientEvent::Hello` when joining and
+/// again whenever someone else joins.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PeerHello {
+    /// The sender's `PROTOCOL_VERSION`
+    pub version: u32,
+    /// Features the sender supports
+    pub features: Vec<Feature>,
+}
+
+impl PeerHello {
+    pub fn local() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            features: vec![
+                Feature::ClipboardSync,
+                Feature::ClickAnimation,
+                Feature::BinaryEvents,
+                Feature::ControlHandoff,
+                Feature::ControlRoles,
+            ],
+        }
+    }
+
+    /// Builds from before versioning never say hello. They speak version 0,
+    /// which already had clipboard sync and click animations.
+    pub fn legacy() -> Self {
+        Self {
+            version: 0,
+            features: vec![Feature::ClipboardSync, Feature::ClickAnimation],
+        }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Just the tag of a `ClientEvent`, for telling events of a newer protocol
+/// apart from garbage.
+#[derive(Deserialize)]
+struct ClientEventTag {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Sends the snapshot again once the participants that joined until now had
+/// time to say hello, so the ones that never do show up as mismatched.
+fn resend_snapshot_after_hello_timeout(snapshot_sender: &SnapshotSender) {
+    let snapshot_sender = snapshot_sender.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(HELLO_TIMEOUT).await;
+        snapshot_sender.send_participants_snapshot();
+    });
+}
+
+/// Tells the other participants which protocol version and features we speak.
+async fn publish_hello(transport: &dyn RoomTransport) {
+    let payload = serde_json::to_vec(&ClientEvent::Hello(PeerHello::local())).unwrap();
+    let res = transport
+        .publish_data(DataPacket {
+            payload,
+            reliable: true,
+            topic: Some(TOPIC_HELLO.to_string()),
+            ..Default::default()
+        })
+        .await;
+    if let Err(e) = res {
+        log::error!("publish_hello: Failed to publish hello: {e:?}");
+    }
 }
 
 fn start_remote_camera_stream(
     video_track: livekit::track::RemoteVideoTrack,
     participants: &Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
     identity: &str,
-    event_loop_proxy: &EventLoopProxy<UserEvent>,
+    event_loop_proxy: &EventSink,
 ) {
     if let Err(e) = event_loop_proxy.send_event(UserEvent::OpenCamera) {
         log::error!("handle_room_events: Failed to send OpenCamera event: {e:?}");
@@ -1917,7 +2209,7 @@ fn start_remote_screen_share_stream(
     remote_screen_share: &RemoteScreenShare,
     participants: &Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
     participant_identity: &str,
-    event_loop_proxy: &EventLoopProxy<UserEvent>,
+    event_loop_proxy: &EventSink,
     snapshot_sender: &SnapshotSender,
 ) {
     {
@@ -1994,16 +2286,51 @@ fn start_remote_screen_share_stream(
 }
 
 struct RoomEventContext {
-    receiver: mpsc::UnboundedReceiver<RoomEvent>,
-    event_loop_proxy: EventLoopProxy<UserEvent>,
+    receiver: mpsc::UnboundedReceiver<TransportEvent>,
+    event_loop_proxy: EventSink,
     user_identity: String,
     video_participant_identity: String,
     participants: Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
     snapshot_sender: SnapshotSender,
-    mixer: audio::mixer::MixerHandle,
+    /// Plays remote audio, `None` for transports without media
+    mixer: Option<audio::mixer::MixerHandle>,
     remote_screen_share: RemoteScreenShare,
     connection_quality: Arc<std::sync::Mutex<Option<ConnectionQuality>>>,
     audio_handle: TokioHandle,
+    transport: Arc<dyn RoomTransport>,
+    timeline: Arc<Timeline>,
+}
+
+/// Makes `transport` the room's and handles its `events` from now on.
+fn spawn_room_events(
+    inner: &RoomServiceInner,
+    transport: Arc<dyn RoomTransport>,
+    events: mpsc::UnboundedReceiver<TransportEvent>,
+    event_sink: EventSink,
+    video_participant_identity: String,
+    mixer: Option<audio::mixer::MixerHandle>,
+    audio_handle: TokioHandle,
+) {
+    *inner.transport.lock().unwrap() = Some(transport.clone());
+    tokio::spawn(handle_room_events(RoomEventContext {
+        receiver: events,
+        event_loop_proxy: event_sink,
+        user_identity: transport.local_identity(),
+        video_participant_identity,
+        participants: inner.participants.clone(),
+        snapshot_sender: inner.snapshot_sender.clone(),
+        mixer,
+        remote_screen_share: RemoteScreenShare {
+            buffer: inner.remote_screen_share.buffer.clone(),
+            stop_tx: inner.remote_screen_share.stop_tx.clone(),
+            publisher_identity: inner.remote_screen_share.publisher_identity.clone(),
+        },
+        connection_quality: inner.connection_quality.clone(),
+        audio_handle,
+        transport,
+        timeline: inner.timeline.clone(),
+    }));
+    log::info!("room_service_commands: Spawned handle_room_events");
 }
 
 async fn drain_video_room_events(mut receiver: mpsc::UnboundedReceiver<RoomEvent>) {
@@ -2023,14 +2350,17 @@ async fn handle_room_events(ctx: RoomEventContext) {
         remote_screen_share,
         connection_quality,
         audio_handle,
+        transport,
+        timeline,
     } = ctx;
+    publish_hello(transport.as_ref()).await;
+    resend_snapshot_after_hello_timeout(&snapshot_sender);
     while let Some(msg) = receiver.recv().await {
         match msg {
-            RoomEvent::DataReceived {
+            TransportEvent::DataReceived {
                 payload,
                 topic,
-                kind: _,
-                participant,
+                sender,
             } => {
                 // participant_in_control uses raw UTF-8 identity, not JSON. Handle before deserialize.
                 // TODO(@konsalex): Maybe follow a JSON  type
@@ -2052,16 +2382,30 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     continue;
                 }
 
-                let client_event: ClientEvent = match serde_json::from_slice(&payload) {
+                let decoded = if wire::is_binary(&payload) {
+                    wire::decode(&payload).map_err(|e| e.to_string())
+                } else {
+                    serde_json::from_slice(&payload).map_err(|e| e.to_string())
+                };
+                let client_event: ClientEvent = match decoded {
                     Ok(event) => event,
                     Err(e) => {
-                        log::error!("handle_room_events: Failed to deserialize event: {e:?}");
+                        /* Events of a newer protocol are expected, its hello tells us about them. */
+                        match serde_json::from_slice::<ClientEventTag>(&payload) {
+                            Ok(tag) => log::warn!(
+                                "handle_room_events: Ignoring unsupported event {}: {e}",
+                                tag.kind
+                            ),
+                            Err(_) => {
+                                log::error!("handle_room_events: Failed to deserialize event: {e}")
+                            }
+                        }
                         continue;
                     }
                 };
                 log::debug!("handle_room_events: Data received: {client_event:?}");
-                let identity = if let Some(participant) = participant {
-                    participant.identity().as_str().to_string()
+                let identity = if let Some(sender) = sender {
+                    sender
                 } else {
                     log::warn!("handle_room_events: Participant is none");
                     "".to_string()
@@ -2072,6 +2416,25 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     log::debug!("handle_room_events: Skipping own event");
                     continue;
                 }
+                timeline.record(timeline::Direction::Received, &identity, &client_event);
+
+                if let ClientEvent::Hello(hello) = client_event {
+                    if hello.version != PROTOCOL_VERSION {
+                        log::warn!(
+                            "handle_room_events: {identity} speaks protocol version {}, we speak {PROTOCOL_VERSION}",
+                            hello.version
+                        );
+                    }
+                    {
+                        let mut participants_guard = participants.write().unwrap();
+                        match participants_guard.get_mut(&identity) {
+                            Some(info) => info.set_hello(hello),
+                            None => log::warn!("handle_room_events: Hello from unknown {identity}"),
+                        }
+                    }
+                    snapshot_sender.send_participants_snapshot();
+                    continue;
+                }
 
                 let res = match client_event {
                     ClientEvent::MouseMove(point) => {
@@ -2099,14 +2462,17 @@ async fn handle_room_events(ctx: RoomEventContext) {
                         ))
                     }
                     ClientEvent::Keystroke(key) => {
-                        event_loop_proxy.send_event(UserEvent::Keystroke(crate::KeystrokeData {
-                            key: key.key[0].clone(),
-                            meta: key.meta,
-                            ctrl: key.ctrl,
-                            shift: key.shift,
-                            alt: key.alt,
-                            down: key.down,
-                        }))
+                        event_loop_proxy.send_event(UserEvent::Keystroke(
+                            crate::KeystrokeData {
+                                key: key.key[0].clone(),
+                                meta: key.meta,
+                                ctrl: key.ctrl,
+                                shift: key.shift,
+                                alt: key.alt,
+                                down: key.down,
+                            },
+                            identity,
+                        ))
                     }
                     ClientEvent::WheelEvent(wheel_data) => {
                         event_loop_proxy.send_event(UserEvent::Scroll(
@@ -2127,7 +2493,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     ClientEvent::AddToClipboard(add_to_clipboard_data) => event_loop_proxy
                         .send_event(UserEvent::AddToClipboard(add_to_clipboard_data, identity)),
                     ClientEvent::PasteFromClipboard(paste_from_clipboard_data) => event_loop_proxy
-                        .send_event(UserEvent::PasteFromClipboard(paste_from_clipboard_data)),
+                        .send_event(UserEvent::PasteFromClipboard(
+                            paste_from_clipboard_data,
+                            identity,
+                        )),
                     ClientEvent::DrawingMode(drawing_mode) => {
                         event_loop_proxy.send_event(UserEvent::DrawingMode(drawing_mode, identity))
                     }
@@ -2162,22 +2531,39 @@ async fn handle_room_events(ctx: RoomEventContext) {
                             Ok(())
                         }
                     }
+                    ClientEvent::RequestControl => {
+                        event_loop_proxy.send_event(UserEvent::ControlRequested(identity))
+                    }
+                    ClientEvent::GrantControl(target) if target.identity == user_identity => {
+                        event_loop_proxy.send_event(UserEvent::LocalControlGranted(true))
+                    }
+                    ClientEvent::DenyControl(target) | ClientEvent::RevokeControl(target)
+                        if target.identity == user_identity =>
+                    {
+                        event_loop_proxy.send_event(UserEvent::LocalControlGranted(false))
+                    }
+                    ClientEvent::ControlRoles(roles) => event_loop_proxy.send_event(
+                        UserEvent::LocalControlRole(roles.role(&user_identity), identity),
+                    ),
                     _ => Ok(()),
                 };
                 if let Err(e) = res {
                     log::error!("handle_room_events: Failed to send message: {e:?}");
                 }
             }
-            RoomEvent::ParticipantConnected(participant) => {
-                let identity = participant.identity().as_str().to_string();
-                let name = participant.name();
+            TransportEvent::ParticipantConnected(peer) => {
+                let identity = peer.identity.clone();
+                let name = peer.name.clone();
 
                 log::info!("handle_room_events: Participant connected: {}", identity);
 
-                if !insert_participant_if_absent(&participants, &identity, &participant) {
+                if !insert_participant_if_absent(&participants, &peer) {
                     continue;
                 }
 
+                /* The newcomer hasn't heard our hello yet. */
+                publish_hello(transport.as_ref()).await;
+
                 if let Err(e) =
                     event_loop_proxy.send_event(UserEvent::ParticipantConnected(ParticipantData {
                         name,
@@ -2190,11 +2576,9 @@ async fn handle_room_events(ctx: RoomEventContext) {
                 }
 
                 snapshot_sender.send_participants_snapshot();
+                resend_snapshot_after_hello_timeout(&snapshot_sender);
             }
-            RoomEvent::ParticipantDisconnected(participant) => {
-                let identity = participant.identity().as_str().to_string();
-                let name = participant.name();
-
+            TransportEvent::ParticipantDisconnected(PeerInfo { identity, name, .. }) => {
                 log::info!("handle_room_events: Participant disconnected: {}", identity);
 
                 // Stop streams and remove from HashMap
@@ -2226,10 +2610,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
 
                 snapshot_sender.send_participants_snapshot();
             }
-            RoomEvent::TrackPublished {
+            TransportEvent::Media(RoomEvent::TrackPublished {
                 publication,
                 participant,
-            } => {
+            }) => {
                 log::info!(
                     "handle_room_events: Track published: {} ({:?}) from {}",
                     publication.name(),
@@ -2237,7 +2621,7 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     participant.identity()
                 );
             }
-            RoomEvent::ActiveSpeakersChanged { speakers } => {
+            TransportEvent::ActiveSpeakersChanged(speakers) => {
                 log::trace!("handle_room_events: Active speakers changed");
                 let mut participants_guard = participants.write().unwrap();
 
@@ -2247,8 +2631,7 @@ async fn handle_room_events(ctx: RoomEventContext) {
                 }
 
                 // Then set active speakers to speaking
-                for speaker in speakers {
-                    let identity = speaker.identity().as_str().to_string();
+                for identity in speakers {
                     if identity == user_identity {
                         if let Some(info) = participants_guard.get_mut("local") {
                             info.set_is_speaking(true);
@@ -2258,10 +2641,23 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     }
                 }
             }
-            RoomEvent::TrackMuted {
+            TransportEvent::AudioMuted { identity, muted } => {
+                if identity == user_identity {
+                    continue;
+                }
+                log::info!("handle_room_events: Audio track muted={muted} for {identity}");
+                {
+                    let mut participants_guard = participants.write().unwrap();
+                    if let Some(info) = participants_guard.get_mut(&identity) {
+                        info.set_muted(muted);
+                    }
+                }
+                snapshot_sender.send_participants_snapshot();
+            }
+            TransportEvent::Media(RoomEvent::TrackMuted {
                 participant,
                 publication,
-            } => {
+            }) => {
                 let identity = participant.identity().as_str().to_string();
                 if identity == user_identity || identity == video_participant_identity {
                     log::info!(
@@ -2272,16 +2668,6 @@ async fn handle_room_events(ctx: RoomEventContext) {
                 }
 
                 match (publication.kind(), publication.source()) {
-                    (livekit::track::TrackKind::Audio, _) => {
-                        log::info!("handle_room_events: Audio track muted for {}", identity);
-                        {
-                            let mut participants_guard = participants.write().unwrap();
-                            if let Some(info) = participants_guard.get_mut(&identity) {
-                                info.set_muted(true);
-                            }
-                        }
-                        snapshot_sender.send_participants_snapshot();
-                    }
                     (livekit::track::TrackKind::Video, TrackSource::Camera) => {
                         log::info!("handle_room_events: Camera track muted for {}", identity);
                         let any_camera_active = {
@@ -2362,10 +2748,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     _ => {}
                 }
             }
-            RoomEvent::TrackUnmuted {
+            TransportEvent::Media(RoomEvent::TrackUnmuted {
                 participant,
                 publication,
-            } => {
+            }) => {
                 let identity = participant.identity().as_str().to_string();
                 if identity == user_identity || identity == video_participant_identity {
                     log::info!(
@@ -2376,16 +2762,6 @@ async fn handle_room_events(ctx: RoomEventContext) {
                 }
 
                 match (publication.kind(), publication.source()) {
-                    (livekit::track::TrackKind::Audio, _) => {
-                        log::info!("handle_room_events: Audio track unmuted for {}", identity);
-                        {
-                            let mut participants_guard = participants.write().unwrap();
-                            if let Some(info) = participants_guard.get_mut(&identity) {
-                                info.set_muted(false);
-                            }
-                        }
-                        snapshot_sender.send_participants_snapshot();
-                    }
                     (livekit::track::TrackKind::Video, TrackSource::Camera) => {
                         log::info!("handle_room_events: Camera track unmuted for {}", identity);
                         let video_track = match publication.track() {
@@ -2430,11 +2806,11 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     _ => {}
                 }
             }
-            RoomEvent::TrackSubscribed {
+            TransportEvent::Media(RoomEvent::TrackSubscribed {
                 track,
                 publication,
                 participant,
-            } => {
+            }) => {
                 log::info!(
                     "handle_room_events: Track subscribed from {}: {} ({:?}) {:?} {:?}",
                     participant.identity(),
@@ -2451,12 +2827,13 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     continue;
                 }
 
-                if insert_participant_if_absent(&participants, &participant_identity, &participant)
+                if insert_participant_if_absent(&participants, &PeerInfo::from_remote(&participant))
                 {
                     log::info!(
                         "handle_room_events: Creating participant {} from track subscription",
                         participant_identity
                     );
+                    resend_snapshot_after_hello_timeout(&snapshot_sender);
                 }
 
                 match track {
@@ -2465,6 +2842,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
                             "handle_room_events: Setting up audio stream for participant: {}",
                             participant_identity
                         );
+                        let Some(mixer) = &mixer else {
+                            log::warn!("handle_room_events: No mixer, not playing remote audio");
+                            continue;
+                        };
 
                         let handle = crate::livekit::audio::play_remote_audio_track(
                             audio_track,
@@ -2529,11 +2910,11 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     },
                 }
             }
-            RoomEvent::TrackUnsubscribed {
+            TransportEvent::Media(RoomEvent::TrackUnsubscribed {
                 track,
                 publication,
                 participant,
-            } => {
+            }) => {
                 log::info!(
                     "handle_room_events: Track unsubscribed from {}: {} ({:?})",
                     participant.identity(),
@@ -2644,10 +3025,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     }
                 }
             }
-            RoomEvent::TrackUnpublished {
+            TransportEvent::Media(RoomEvent::TrackUnpublished {
                 publication,
                 participant,
-            } => {
+            }) => {
                 log::info!(
                     "handle_room_events: Track unpublished from {}: {} ({:?})",
                     participant.identity(),
@@ -2655,10 +3036,10 @@ async fn handle_room_events(ctx: RoomEventContext) {
                     publication.kind()
                 );
             }
-            RoomEvent::ConnectionQualityChanged {
+            TransportEvent::Media(RoomEvent::ConnectionQualityChanged {
                 quality,
                 participant,
-            } if participant.identity().as_str() == user_identity => {
+            }) if participant.identity().as_str() == user_identity => {
                 log::info!("Connection quality changed: {:?}", quality);
                 *connection_quality.lock().unwrap() = Some(quality);
             }
@@ -2667,3 +3048,228 @@ async fn handle_room_events(ctx: RoomEventContext) {
     }
     log::info!("handle_room_events: ended")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hello_from_newer_peer_keeps_known_features() {
+        let payload =
+            r#"{"type":"Hello","payload":{"version":2,"features":["ClipboardSync","Telepathy"]}}"#;
+        let event: ClientEvent = serde_json::from_str(payload).unwrap();
+        let hello = match event {
+            ClientEvent::Hello(hello) => hello,
+            other => panic!("expected Hello, got {other:?}"),
+        };
+        assert_eq!(hello.version, 2);
+        assert!(hello.supports(Feature::ClipboardSync));
+        assert!(!hello.supports(Feature::ClickAnimation));
+    }
+
+    #[test]
+    fn test_protocol_version_unknown_until_hello() {
+        let mut info = ParticipantInfo::new("joining".to_string(), false, false);
+        assert_eq!(info.protocol_version(), None);
+
+        info.set_hello(PeerHello::local());
+        assert_eq!(info.protocol_version(), Some(PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn test_features_need_every_peer() {
+        let mut participants = HashMap::new();
+        participants.insert(
+            "local".to_string(),
+            ParticipantInfo::new("me".to_string(), false, false),
+        );
+        participants.insert(
+            "legacy".to_string(),
+            ParticipantInfo::new("old build".to_string(), false, false),
+        );
+        assert!(all_peers_support(&participants, Feature::ClickAnimation));
+
+        let mut newer = ParticipantInfo::new("new build".to_string(), false, false);
+        newer.set_hello(PeerHello {
+            version: PROTOCOL_VERSION + 1,
+            features: vec![Feature::ClipboardSync],
+        });
+        participants.insert("newer".to_string(), newer);
+        assert!(all_peers_support(&participants, Feature::ClipboardSync));
+        assert!(!all_peers_support(&participants, Feature::ClickAnimation));
+    }
+
+    #[test]
+    fn test_hot_events_stay_json_until_every_peer_reads_binary() {
+        let participants = std::sync::RwLock::new(HashMap::new());
+        let mut current = ParticipantInfo::new("new build".to_string(), false, false);
+        current.set_hello(PeerHello::local());
+        participants
+            .write()
+            .unwrap()
+            .insert("current".to_string(), current);
+        let event = ClientEvent::MouseMove(ClientPoint { x: 0.25, y: 0.75 });
+        assert!(wire::is_binary(&hot_event_payload(&event, &participants)));
+
+        participants.write().unwrap().insert(
+            "legacy".to_string(),
+            ParticipantInfo::new("old build".to_string(), false, false),
+        );
+        let payload = hot_event_payload(&event, &participants);
+        assert!(!wire::is_binary(&payload));
+        assert!(serde_json::from_slice::<ClientEvent>(&payload).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hello_reaches_peers_over_loopback() {
+        let room = crate::livekit::transport::LoopbackRoom::new();
+        let (sharer, _sharer_events) = room.join("a:b:sharer:audio", "Sharer");
+        let (_controller, mut controller_events) = room.join("a:b:controller:audio", "Controller");
+
+        publish_hello(&sharer).await;
+        match controller_events.recv().await.unwrap() {
+            TransportEvent::DataReceived { payload, topic, .. } => {
+                assert_eq!(topic.as_deref(), Some(TOPIC_HELLO));
+                let event: ClientEvent = serde_json::from_slice(&payload).unwrap();
+                assert!(matches!(event, ClientEvent::Hello(hello) if hello == PeerHello::local()));
+            }
+            other => panic!("expected DataReceived, got {other:?}"),
+        }
+    }
+
+    /// A core socket with the shell connected, open as long as this lives.
+    struct CoreSocket {
+        sender: socket_lib::server::ServerSender,
+        _messages: std::sync::mpsc::Receiver<socket_lib::server::ClientMessage>,
+        _shell: (socket_lib::SocketSender, socket_lib::EventSocket),
+    }
+
+    fn core_socket(name: &str) -> CoreSocket {
+        let path = std::env::temp_dir().join(format!("hopp-room-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let path = path.to_str().unwrap().to_string();
+        let server_path = path.clone();
+        let server = std::thread::spawn(move || {
+            socket_lib::server::serve(&server_path, &[], socket_lib::SocketOptions::default())
+        });
+        let shell = loop {
+            match socket_lib::connect(&path, &[]) {
+                Ok(shell) => break shell,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        let (sender, messages) = server.join().unwrap().unwrap();
+        CoreSocket {
+            sender,
+            _messages: messages,
+            _shell: shell,
+        }
+    }
+
+    fn loopback_service(
+        room: &Arc<crate::livekit::transport::LoopbackRoom>,
+        identity: &str,
+        name: &str,
+    ) -> (
+        RoomService,
+        std::sync::mpsc::Receiver<UserEvent>,
+        CoreSocket,
+    ) {
+        let core = core_socket(name);
+        let service = RoomService::new(
+            "ws://unused".to_string(),
+            core.sender.clone(),
+            Arc::new(Timeline::default()),
+        )
+        .unwrap();
+        let (transport, events) = room.join(identity, name);
+        let (tx, rx) = std::sync::mpsc::channel();
+        service
+            .join_transport(JoinTransportParams {
+                transport: Arc::new(transport),
+                events,
+                event_sink: EventSink::Channel(tx),
+            })
+            .unwrap();
+        (service, rx, core)
+    }
+
+    /// The first event `wanted` picks, skipping the others.
+    fn next_event<T>(
+        events: &std::sync::mpsc::Receiver<UserEvent>,
+        wanted: impl Fn(UserEvent) -> Option<T>,
+    ) -> T {
+        loop {
+            let event = events
+                .recv_timeout(Duration::from_secs(5))
+                .expect("no matching event in time");
+            if let Some(found) = wanted(event) {
+                return found;
+            }
+        }
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "condition not met in time");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_sharer_and_controller_services_over_loopback() {
+        let room = crate::livekit::transport::LoopbackRoom::new();
+        let (sharer, sharer_events, _sharer_core) =
+            loopback_service(&room, "a:b:sharer:audio", "Sharer");
+        let (controller, controller_events, _controller_core) =
+            loopback_service(&room, "a:b:controller:audio", "Controller");
+
+        let joined = next_event(&sharer_events, |event| match event {
+            UserEvent::ParticipantConnected(participant) => Some(participant.identity),
+            _ => None,
+        });
+        assert_eq!(joined, "a:b:controller:audio");
+
+        /* Once both said hello, cursor moves take the binary form. */
+        wait_until(|| sharer.peer_supports("a:b:controller:audio", Feature::BinaryEvents));
+        wait_until(|| controller.peer_supports("a:b:sharer:audio", Feature::BinaryEvents));
+        assert!(controller.peers_support(Feature::BinaryEvents));
+
+        /* Cursor moves are throttled from the moment the service starts. */
+        std::thread::sleep(Duration::from_millis(20));
+        controller.publish_cursor_position(0.5, 0.25, true);
+        let (x, y, identity) = next_event(&sharer_events, |event| match event {
+            UserEvent::CursorPosition(x, y, identity) => Some((x, y, identity)),
+            _ => None,
+        });
+        /* The binary form quantizes coordinates to u16. */
+        assert!((x - 0.5).abs() <= 1.0 / u16::MAX as f32);
+        assert!((y - 0.25).abs() <= 1.0 / u16::MAX as f32);
+        assert_eq!(identity, "a:b:controller:audio");
+
+        controller.publish_draw_start(DrawPathPoint {
+            point: ClientPoint { x: 0.1, y: 0.2 },
+            path_id: 7,
+        });
+        let path_id = next_event(&sharer_events, |event| match event {
+            UserEvent::DrawStart(_, path_id, _) => Some(path_id),
+            _ => None,
+        });
+        assert_eq!(path_id, 7);
+
+        controller.request_control();
+        let requester = next_event(&sharer_events, |event| match event {
+            UserEvent::ControlRequested(identity) => Some(identity),
+            _ => None,
+        });
+        assert_eq!(requester, "a:b:controller:audio");
+
+        sharer.grant_control(requester);
+        let granted = next_event(&controller_events, |event| match event {
+            UserEvent::LocalControlGranted(granted) => Some(granted),
+            _ => None,
+        });
+        assert!(granted);
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::livekit::participant::ParticipantInfo;
+use crate::room_service::PROTOCOL_VERSION;
 
 impl std::fmt::Debug for SnapshotSender {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -10,13 +11,13 @@ impl std::fmt::Debug for SnapshotSender {
 }
 
 pub(crate) struct SnapshotSender {
-    socket: socket_lib::SocketSender,
+    socket: socket_lib::server::ServerSender,
     participants: Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
 }
 
 impl SnapshotSender {
     pub fn new(
-        socket: socket_lib::SocketSender,
+        socket: socket_lib::server::ServerSender,
         participants: Arc<std::sync::RwLock<HashMap<String, ParticipantInfo>>>,
     ) -> Self {
         Self {
@@ -54,6 +55,7 @@ impl SnapshotSender {
                         muted: info.muted(),
                         has_camera: info.camera_active(),
                         is_screensharing: info.is_screensharing(),
+                        protocol_mismatch: false,
                     },
                 );
                 continue;
@@ -74,11 +76,16 @@ impl SnapshotSender {
                     muted: false,
                     has_camera: false,
                     is_screensharing: false,
+                    protocol_mismatch: false,
                 }
             });
 
             if track_type == "audio" {
                 entry.muted = info.muted();
+                /* Only the audio participant says hello. */
+                entry.protocol_mismatch = info
+                    .protocol_version()
+                    .is_some_and(|version| version != PROTOCOL_VERSION);
             }
 
             entry.has_camera = entry.has_camera || info.camera_active();
@@ -0,0 +1,564 @@
+//! Opt-in timeline of a screen share's `ClientEvent`s, for looking into
+//! cursor and drawing glitches after the fact.
+//!
+//! With `--timeline-dir` set, every event sent or received while a screen
+//! share is active is written to `timeline-<unix ms>.jsonl.gz` in that
+//! directory, one gzipped JSON object per line:
+//!
+//! ```text
+//! {"elapsed_ms":12,"identity":"room:team:alice:audio","direction":"Received","event":{"type":"DrawAddPoint","payload":{"x":0.5,"y":0.25}}}
+//! ```
+//!
+//! `elapsed_ms` is monotonic time since the screen share started, `identity`
+//! is the sender, which is our own identity for sent events. With `redact`
+//! the keys of keystrokes and the clipboard contents are left out, the
+//! modifiers and key up/down are kept. Lines are flushed in batches, a crash
+//! loses the last one, at most `FLUSH_EVERY_LINES` events. `TimelineReplay`
+//! draws a timeline into a `ReplayTarget` such as the overlay's
+//! `GraphicsContext`.
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use livekit::DataPacket;
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::graphics_context::GraphicsContext;
+use crate::input::control;
+use crate::livekit::transport::{PeerInfo, PublishFuture, RoomTransport};
+use crate::livekit::wire;
+use crate::room_service::{
+    ClientEvent, ClipboardDataPayload, DrawingMode, KeystrokeData, PasteFromClipboardData,
+};
+use crate::utils::geometry::Position;
+
+/// A session flushes after this many lines, or on the first line once
+/// `FLUSH_INTERVAL` passed since the last flush.
+const FLUSH_EVERY_LINES: u32 = 64;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where to write timelines and what to leave out of them.
+#[derive(Debug, Clone)]
+pub struct TimelineOptions {
+    pub dir: PathBuf,
+    /// Leave out the keys of keystrokes and the clipboard contents
+    pub redact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// One line of a timeline.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub elapsed_ms: u64,
+    pub identity: String,
+    pub direction: Direction,
+    pub event: ClientEvent,
+}
+
+/// Borrowing twin of `TimelineEntry`, so recording doesn't clone every event.
+#[derive(Serialize)]
+struct TimelineEntryRef<'a> {
+    elapsed_ms: u64,
+    identity: &'a str,
+    direction: Direction,
+    event: &'a ClientEvent,
+}
+
+#[derive(Debug)]
+struct Session {
+    start: Instant,
+    path: PathBuf,
+    redact: bool,
+    file: GzEncoder<BufWriter<File>>,
+    last_flush: Instant,
+    unflushed: u32,
+}
+
+impl Session {
+    fn write(&mut self, entry: &TimelineEntryRef) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.file, entry)?;
+        self.file.write_all(b"\n")?;
+        self.unflushed += 1;
+        if self.unflushed >= FLUSH_EVERY_LINES || self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.file.flush()?;
+            self.last_flush = Instant::now();
+            self.unflushed = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Records the events of one screen share at a time, shared by the room's
+/// event handler and its transport. Recording does nothing between sessions.
+#[derive(Debug, Default)]
+pub struct Timeline {
+    session: Mutex<Option<Session>>,
+}
+
+impl Timeline {
+    /// Starts a new timeline file in `options.dir`, finishing the one being
+    /// written, and returns its path.
+    pub fn start(&self, options: &TimelineOptions) -> std::io::Result<PathBuf> {
+        self.stop();
+        std::fs::create_dir_all(&options.dir)?;
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let path = options
+            .dir
+            .join(format!("timeline-{timestamp_ms}.jsonl.gz"));
+        let file = File::create(&path)?;
+        log::info!("Recording screen share timeline to {}", path.display());
+        *self.session.lock().unwrap() = Some(Session {
+            start: Instant::now(),
+            path: path.clone(),
+            redact: options.redact,
+            file: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            last_flush: Instant::now(),
+            unflushed: 0,
+        });
+        Ok(path)
+    }
+
+    /// Finishes the timeline being written, if any.
+    pub fn stop(&self) {
+        let Some(session) = self.session.lock().unwrap().take() else {
+            return;
+        };
+        match session.file.finish().and_then(|mut file| file.flush()) {
+            Ok(()) => log::info!("Finished timeline {}", session.path.display()),
+            Err(e) => log::error!("Failed to finish timeline {}: {e}", session.path.display()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.session.lock().unwrap().is_some()
+    }
+
+    /// Writes `event` as the next line. A failing timeline never fails the
+    /// room, the error is only logged.
+    pub fn record(&self, direction: Direction, identity: &str, event: &ClientEvent) {
+        let mut session = self.session.lock().unwrap();
+        let Some(session) = session.as_mut() else {
+            return;
+        };
+        let redacted = if session.redact { redact(event) } else { None };
+        let entry = TimelineEntryRef {
+            elapsed_ms: session.start.elapsed().as_millis() as u64,
+            identity,
+            direction,
+            event: redacted.as_ref().unwrap_or(event),
+        };
+        if let Err(e) = session.write(&entry) {
+            log::error!("Failed to record {direction:?} event: {e}");
+        }
+    }
+}
+
+/// `event` without what `TimelineOptions::redact` leaves out, `None` if
+/// there is nothing to leave out.
+fn redact(event: &ClientEvent) -> Option<ClientEvent> {
+    match event {
+        ClientEvent::Keystroke(keystroke) => Some(ClientEvent::Keystroke(KeystrokeData {
+            key: Vec::new(),
+            meta: keystroke.meta,
+            ctrl: keystroke.ctrl,
+            shift: keystroke.shift,
+            alt: keystroke.alt,
+            down: keystroke.down,
+        })),
+        ClientEvent::PasteFromClipboard(paste) if paste.data.is_some() => {
+            Some(ClientEvent::PasteFromClipboard(PasteFromClipboardData {
+                data: None,
+            }))
+        }
+        ClientEvent::ClipboardData(clipboard) if clipboard.data.is_some() => {
+            Some(ClientEvent::ClipboardData(ClipboardDataPayload {
+                requester_sid: clipboard.requester_sid.clone(),
+                data: None,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Records the `ClientEvent`s published through `inner` as sent by us.
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Arc<dyn RoomTransport>,
+    timeline: Arc<Timeline>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn RoomTransport>, timeline: Arc<Timeline>) -> Self {
+        Self { inner, timeline }
+    }
+}
+
+impl RoomTransport for RecordingTransport {
+    fn local_identity(&self) -> String {
+        self.inner.local_identity()
+    }
+
+    fn local_name(&self) -> String {
+        self.inner.local_name()
+    }
+
+    fn remote_participants(&self) -> Vec<PeerInfo> {
+        self.inner.remote_participants()
+    }
+
+    fn publish_data(&self, packet: DataPacket) -> PublishFuture<'_> {
+        if self.timeline.is_recording() {
+            /* Not every packet is a ClientEvent, e.g. participant_in_control is a raw identity. */
+            let decoded = if wire::is_binary(&packet.payload) {
+                wire::decode(&packet.payload).ok()
+            } else {
+                serde_json::from_slice(&packet.payload).ok()
+            };
+            if let Some(event) = decoded {
+                self.timeline
+                    .record(Direction::Sent, &self.inner.local_identity(), &event);
+            }
+        }
+        self.inner.publish_data(packet)
+    }
+}
+
+/// Reads a timeline back. Lines that don't parse, e.g. an event this version
+/// doesn't know, are logged and skipped. A file cut short by a crash is read
+/// up to where it ends.
+pub fn read_timeline(path: &Path) -> std::io::Result<Vec<TimelineEntry>> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                log::warn!("{} ends early after line {index}", path.display());
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!("Skipping line {} of {}: {e}", index + 1, path.display()),
+        }
+    }
+    Ok(entries)
+}
+
+/// What a replay draws into, the overlay's `GraphicsContext` outside of tests.
+pub trait ReplayTarget {
+    fn add_participant(&mut self, identity: String, name: &str);
+    fn set_cursor_position(&mut self, identity: &str, position: Position);
+    fn set_drawing_mode(&mut self, identity: &str, mode: DrawingMode);
+    fn draw_start(&mut self, identity: &str, point: Position, path_id: u64);
+    fn draw_add_point(&mut self, identity: &str, point: Position);
+    fn draw_end(&mut self, identity: &str, point: Position);
+    fn draw_clear_path(&mut self, identity: &str, path_id: u64);
+    fn draw_clear_all_paths(&mut self, identity: &str);
+    fn trigger_click_animation(&mut self, position: Position);
+}
+
+impl ReplayTarget for GraphicsContext<'_> {
+    fn add_participant(&mut self, identity: String, name: &str) {
+        if let Err(e) = GraphicsContext::add_participant(self, identity.clone(), name, false) {
+            log::error!("TimelineReplay: Failed to add {identity}: {e}");
+        }
+    }
+
+    fn set_cursor_position(&mut self, identity: &str, position: Position) {
+        self.participants_manager_mut()
+            .set_cursor_position(identity, Some(position));
+    }
+
+    fn set_drawing_mode(&mut self, identity: &str, mode: DrawingMode) {
+        GraphicsContext::set_drawing_mode(self, identity, mode);
+    }
+
+    fn draw_start(&mut self, identity: &str, point: Position, path_id: u64) {
+        GraphicsContext::draw_start(self, identity, point, path_id);
+    }
+
+    fn draw_add_point(&mut self, identity: &str, point: Position) {
+        GraphicsContext::draw_add_point(self, identity, point);
+    }
+
+    fn draw_end(&mut self, identity: &str, point: Position) {
+        GraphicsContext::draw_end(self, identity, point);
+    }
+
+    fn draw_clear_path(&mut self, identity: &str, path_id: u64) {
+        GraphicsContext::draw_clear_path(self, identity, path_id);
+    }
+
+    fn draw_clear_all_paths(&mut self, identity: &str) {
+        GraphicsContext::draw_clear_all_paths(self, identity);
+    }
+
+    fn trigger_click_animation(&mut self, position: Position) {
+        GraphicsContext::trigger_click_animation(self, position);
+    }
+}
+
+/// Plays a timeline's cursors, drawings and click animations into a
+/// `ReplayTarget`, step by step so the overlay can be rendered at any
+/// moment of it. Everyone in the timeline becomes a participant the first
+/// time they show up, input and control events draw nothing and are skipped.
+pub struct TimelineReplay {
+    entries: Vec<TimelineEntry>,
+    next: usize,
+    participants: HashSet<String>,
+}
+
+impl TimelineReplay {
+    pub fn new(entries: Vec<TimelineEntry>) -> Self {
+        Self {
+            entries,
+            next: 0,
+            participants: HashSet::new(),
+        }
+    }
+
+    /// Applies the events recorded up to `elapsed_ms`, returns whether there
+    /// are events left.
+    pub fn advance_to(&mut self, elapsed_ms: u64, target: &mut impl ReplayTarget) -> bool {
+        while let Some(entry) = self.entries.get(self.next) {
+            if entry.elapsed_ms > elapsed_ms {
+                return true;
+            }
+            self.next += 1;
+            if self.participants.insert(entry.identity.clone()) {
+                let name = control::user_id(&entry.identity).unwrap_or(&entry.identity);
+                target.add_participant(entry.identity.clone(), name);
+            }
+            apply(target, &entry.identity, &entry.event);
+        }
+        false
+    }
+
+    /// Applies everything left.
+    pub fn finish(&mut self, target: &mut impl ReplayTarget) {
+        self.advance_to(u64::MAX, target);
+    }
+}
+
+fn apply(gfx: &mut impl ReplayTarget, identity: &str, event: &ClientEvent) {
+    match event {
+        ClientEvent::MouseMove(point) => gfx.set_cursor_position(
+            identity,
+            Position {
+                x: point.x,
+                y: point.y,
+            },
+        ),
+        ClientEvent::DrawingMode(mode) => gfx.set_drawing_mode(identity, mode.clone()),
+        ClientEvent::DrawStart(start) => {
+            let position = Position {
+                x: start.point.x,
+                y: start.point.y,
+            };
+            gfx.draw_start(identity, position, start.path_id);
+        }
+        ClientEvent::DrawAddPoint(point) => gfx.draw_add_point(
+            identity,
+            Position {
+                x: point.x,
+                y: point.y,
+            },
+        ),
+        ClientEvent::DrawEnd(point) => gfx.draw_end(
+            identity,
+            Position {
+                x: point.x,
+                y: point.y,
+            },
+        ),
+        ClientEvent::DrawClearPath { path_id } => gfx.draw_clear_path(identity, *path_id),
+        ClientEvent::DrawClearAllPaths => gfx.draw_clear_all_paths(identity),
+        ClientEvent::ClickAnimation(point) => gfx.trigger_click_animation(Position {
+            x: point.x,
+            y: point.y,
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room_service::ClientPoint;
+
+    #[test]
+    fn test_timeline_round_trip_with_redaction() {
+        let dir = std::env::temp_dir().join(format!("hopp-timeline-{}", std::process::id()));
+        let timeline = Timeline::default();
+        timeline.record(Direction::Sent, "dropped", &ClientEvent::DrawClearAllPaths);
+
+        let path = timeline
+            .start(&TimelineOptions {
+                dir: dir.clone(),
+                redact: true,
+            })
+            .unwrap();
+        timeline.record(
+            Direction::Received,
+            "a:b:alice:audio",
+            &ClientEvent::MouseMove(ClientPoint { x: 0.5, y: 0.25 }),
+        );
+        timeline.record(
+            Direction::Sent,
+            "a:b:bob:audio",
+            &ClientEvent::Keystroke(KeystrokeData {
+                key: vec!["p".to_string()],
+                meta: true,
+                ctrl: false,
+                shift: false,
+                alt: false,
+                down: true,
+            }),
+        );
+        timeline.stop();
+        timeline.record(Direction::Sent, "dropped", &ClientEvent::DrawClearAllPaths);
+
+        let entries = read_timeline(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].identity, "a:b:alice:audio");
+        assert_eq!(entries[0].direction, Direction::Received);
+        assert!(matches!(entries[0].event, ClientEvent::MouseMove(point) if point.y == 0.25));
+        assert!(entries[0].elapsed_ms <= entries[1].elapsed_ms);
+        match &entries[1].event {
+            ClientEvent::Keystroke(keystroke) => {
+                assert!(keystroke.key.is_empty());
+                assert!(keystroke.meta && keystroke.down);
+            }
+            other => panic!("expected Keystroke, got {other:?}"),
+        }
+    }
+
+    /// Keeps the participants, cursors and paths a replay leaves behind.
+    #[derive(Default)]
+    struct Overlay {
+        participants: Vec<(String, String)>,
+        cursors: std::collections::HashMap<String, (f64, f64)>,
+        in_progress: std::collections::HashMap<String, (u64, usize)>,
+        completed: std::collections::HashMap<String, Vec<u64>>,
+    }
+
+    impl ReplayTarget for Overlay {
+        fn add_participant(&mut self, identity: String, name: &str) {
+            self.participants.push((identity, name.to_string()));
+        }
+
+        fn set_cursor_position(&mut self, identity: &str, position: Position) {
+            self.cursors
+                .insert(identity.to_string(), (position.x, position.y));
+        }
+
+        fn set_drawing_mode(&mut self, _identity: &str, _mode: DrawingMode) {}
+
+        fn draw_start(&mut self, identity: &str, _point: Position, path_id: u64) {
+            self.in_progress.insert(identity.to_string(), (path_id, 1));
+        }
+
+        fn draw_add_point(&mut self, identity: &str, _point: Position) {
+            if let Some((_, points)) = self.in_progress.get_mut(identity) {
+                *points += 1;
+            }
+        }
+
+        fn draw_end(&mut self, identity: &str, _point: Position) {
+            if let Some((path_id, _)) = self.in_progress.remove(identity) {
+                self.completed
+                    .entry(identity.to_string())
+                    .or_default()
+                    .push(path_id);
+            }
+        }
+
+        fn draw_clear_path(&mut self, identity: &str, path_id: u64) {
+            if let Some(paths) = self.completed.get_mut(identity) {
+                paths.retain(|&id| id != path_id);
+            }
+        }
+
+        fn draw_clear_all_paths(&mut self, identity: &str) {
+            self.in_progress.remove(identity);
+            self.completed.remove(identity);
+        }
+
+        fn trigger_click_animation(&mut self, _position: Position) {}
+    }
+
+    fn entry(elapsed_ms: u64, identity: &str, event: ClientEvent) -> TimelineEntry {
+        TimelineEntry {
+            elapsed_ms,
+            identity: identity.to_string(),
+            direction: Direction::Received,
+            event,
+        }
+    }
+
+    fn draw_start(path_id: u64) -> ClientEvent {
+        ClientEvent::DrawStart(crate::room_service::DrawPathPoint {
+            point: ClientPoint { x: 0.1, y: 0.1 },
+            path_id,
+        })
+    }
+
+    #[test]
+    fn test_replay_applies_entries_up_to_the_given_time() {
+        let alice = "a:b:alice:audio";
+        let bob = "a:b:bob:audio";
+        let point = ClientPoint { x: 0.2, y: 0.2 };
+        let mut replay = TimelineReplay::new(vec![
+            entry(
+                0,
+                alice,
+                ClientEvent::MouseMove(ClientPoint { x: 0.5, y: 0.25 }),
+            ),
+            entry(10, alice, draw_start(1)),
+            entry(20, alice, ClientEvent::DrawAddPoint(point)),
+            entry(30, alice, ClientEvent::DrawEnd(point)),
+            entry(40, bob, draw_start(2)),
+            entry(50, bob, ClientEvent::DrawEnd(point)),
+            entry(60, alice, ClientEvent::DrawClearPath { path_id: 1 }),
+        ]);
+        let mut overlay = Overlay::default();
+
+        assert!(replay.advance_to(25, &mut overlay));
+        assert_eq!(
+            overlay.participants,
+            vec![(alice.to_string(), "alice".to_string())]
+        );
+        assert_eq!(overlay.cursors[alice], (0.5, 0.25));
+        assert_eq!(overlay.in_progress[alice], (1, 2));
+        assert!(overlay.completed.is_empty());
+
+        assert!(replay.advance_to(50, &mut overlay));
+        assert_eq!(overlay.participants.len(), 2);
+        assert_eq!(overlay.completed[alice], vec![1]);
+        assert_eq!(overlay.completed[bob], vec![2]);
+
+        replay.finish(&mut overlay);
+        assert!(overlay.completed[alice].is_empty());
+        assert_eq!(overlay.completed[bob], vec![2]);
+        assert!(!replay.advance_to(u64::MAX, &mut overlay));
+    }
+}
@@ -2,9 +2,12 @@ use crate::utils::geometry::Extent;
 
 #[cfg_attr(target_os = "macos", path = "aspect_ratio/macos.rs")]
 #[cfg_attr(target_os = "windows", path = "aspect_ratio/windows.rs")]
+#[cfg_attr(target_os = "linux", path = "aspect_ratio/linux.rs")]
 mod platform;
 
 pub use platform::AspectRatioEnforcer;
+#[cfg(target_os = "linux")]
+pub(crate) use platform::X11Window;
 
 pub(crate) struct WindowConstant;
 
@@ -65,3 +68,58 @@ pub fn calculate_max_window_size(available: Extent, stream_aspect: f64) -> Optio
         content_h + WindowConstant::SKELETON_H,
     ))
 }
+
+/// Returns the size a window has to be resized to after a resize that broke the
+/// content aspect, or `None` when it is already close enough.
+///
+/// Used on platforms where the window manager can't be relied on to keep the
+/// aspect while resizing. A maximized window is restored and fitted to the
+/// screen area instead.
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn snap_to_aspect(
+    window: &winit::window::Window,
+    logical_width: f64,
+    logical_height: f64,
+    img_aspect: f64,
+    screen_area_extent: Extent,
+    screen_area_position: crate::utils::geometry::Position,
+) -> Option<(f64, f64)> {
+    if img_aspect <= 0.0 {
+        return None;
+    }
+
+    let content_w = logical_width - WindowConstant::SKELETON_W;
+    let expected_h = content_w / img_aspect + WindowConstant::SKELETON_H;
+
+    if (logical_height - expected_h).abs() <= 2.0 {
+        return None;
+    }
+
+    let is_maximized = window.is_maximized();
+
+    let (target_w, target_h) = if is_maximized {
+        window.set_maximized(false);
+        calculate_max_window_size(screen_area_extent, img_aspect)
+            .unwrap_or((logical_width, expected_h))
+    } else {
+        (logical_width, expected_h)
+    };
+
+    log::info!(
+        "AspectRatioEnforcer: aspect correction {:.1}x{:.1} -> {:.1}x{:.1}{}",
+        logical_width,
+        logical_height,
+        target_w,
+        target_h,
+        if is_maximized { " (was maximized)" } else { "" }
+    );
+
+    if is_maximized {
+        window.set_outer_position(winit::dpi::LogicalPosition::new(
+            screen_area_position.x,
+            screen_area_position.y,
+        ));
+    }
+
+    Some((target_w, target_h))
+}
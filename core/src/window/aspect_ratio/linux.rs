@@ -0,0 +1,160 @@
+use winit::window::Window;
+use x11rb::connection::Connection;
+use x11rb::properties::{AspectRatio, WmSizeHints};
+use x11rb::rust_connection::RustConnection;
+
+use super::{min_window_size_for_aspect, snap_to_aspect, WindowConstant};
+use crate::utils::geometry::{Extent, Position};
+
+/* WM_NORMAL_HINTS only takes integer ratios, this keeps four decimals of the aspect. */
+const ASPECT_DENOMINATOR: i32 = 10_000;
+
+/// The X11 window the size hints are written to.
+///
+/// winit keeps its own connection, hints are set on a separate one because
+/// winit doesn't expose the aspect fields of `WM_NORMAL_HINTS`. winit also
+/// rewrites the hints when its min size changes, so a window using this sets
+/// its min size here too.
+pub(crate) struct X11Window {
+    conn: RustConnection,
+    window: u32,
+}
+
+impl X11Window {
+    pub(crate) fn new(window: &Window) -> Option<Self> {
+        use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+        let Ok(raw_handle) = window.window_handle() else {
+            log::warn!("X11Window::new: failed to get window handle");
+            return None;
+        };
+        let window = match raw_handle.as_raw() {
+            RawWindowHandle::Xlib(handle) => handle.window as u32,
+            RawWindowHandle::Xcb(handle) => handle.window.get(),
+            _ => {
+                log::info!("X11Window::new: not an X11 window, snapping after resize");
+                return None;
+            }
+        };
+
+        match x11rb::connect(None) {
+            Ok((conn, _)) => Some(Self { conn, window }),
+            Err(e) => {
+                log::warn!("X11Window::new: failed to connect to the X server: {e}");
+                None
+            }
+        }
+    }
+
+    /// Sets the aspect of the content area in `WM_NORMAL_HINTS`.
+    ///
+    /// ICCCM applies the aspect to the window size minus the base size, so the
+    /// header and padding around the video are passed as the base size.
+    fn set_aspect_ratio(&self, content_aspect: f64, scale: f64) -> Result<(), String> {
+        self.set_size_hints(
+            Some((
+                content_aspect,
+                (WindowConstant::SKELETON_W, WindowConstant::SKELETON_H),
+            )),
+            min_window_size_for_aspect(content_aspect),
+            scale,
+        )
+    }
+
+    /// Sets the min size and, when `aspect` is given, the content aspect with
+    /// the base size it applies above. Sizes are logical.
+    pub(crate) fn set_size_hints(
+        &self,
+        aspect: Option<(f64, (f64, f64))>,
+        (min_w, min_h): (f64, f64),
+        scale: f64,
+    ) -> Result<(), String> {
+        let mut hints = WmSizeHints::get_normal_hints(&self.conn, self.window)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+
+        match aspect {
+            Some((content_aspect, (base_w, base_h))) => {
+                let numerator = (content_aspect * ASPECT_DENOMINATOR as f64).round() as i32;
+                let ratio = AspectRatio::new(numerator, ASPECT_DENOMINATOR);
+                hints.aspect = Some((ratio, ratio));
+                hints.base_size = Some((
+                    (base_w * scale).round() as i32,
+                    (base_h * scale).round() as i32,
+                ));
+            }
+            None => {
+                hints.aspect = None;
+                hints.base_size = None;
+            }
+        }
+        hints.min_size = Some((
+            (min_w * scale).round() as i32,
+            (min_h * scale).round() as i32,
+        ));
+
+        hints
+            .set_normal_hints(&self.conn, self.window)
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+pub struct AspectRatioEnforcer {
+    x11_window: Option<X11Window>,
+}
+
+impl AspectRatioEnforcer {
+    pub fn new(window: &Window) -> Self {
+        let mut enforcer = Self {
+            x11_window: X11Window::new(window),
+        };
+        enforcer.set_aspect_ratio(window, 16.0 / 9.0);
+        enforcer
+    }
+
+    pub fn set_aspect_ratio(&mut self, window: &Window, content_aspect: f64) {
+        /*
+         * winit rewrites WM_NORMAL_HINTS from its own connection when the min size
+         * changes, which could race with the aspect written here. On X11 the min
+         * size is part of the hints set below instead.
+         */
+        if let Some(x11_window) = &self.x11_window {
+            match x11_window.set_aspect_ratio(content_aspect, window.scale_factor()) {
+                Ok(()) => return,
+                Err(e) => log::warn!("set_aspect_ratio: failed to set size hints: {e}"),
+            }
+        }
+
+        let (min_w, min_h) = min_window_size_for_aspect(content_aspect);
+        window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_w, min_h)));
+    }
+
+    pub fn is_zoomed(&self, _window: &Window) -> bool {
+        false
+    }
+
+    /// Window managers that ignore the aspect hints (and Wayland, which has none)
+    /// leave the window at any size, those resizes are snapped back here.
+    pub fn correct_aspect_after_resize(
+        &self,
+        window: &Window,
+        logical_width: f64,
+        logical_height: f64,
+        img_aspect: f64,
+        screen_area_extent: Extent,
+        screen_area_position: Position,
+    ) -> Option<(f64, f64)> {
+        snap_to_aspect(
+            window,
+            logical_width,
+            logical_height,
+            img_aspect,
+            screen_area_extent,
+            screen_area_position,
+        )
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use winit::window::Window;
 
-use super::{calculate_max_window_size, min_window_size_for_aspect, WindowConstant};
+use super::{min_window_size_for_aspect, snap_to_aspect, WindowConstant};
 use crate::utils::geometry::{Extent, Position};
 
 struct WindowAspectState {
@@ -146,44 +146,14 @@ impl AspectRatioEnforcer {
         screen_area_extent: Extent,
         screen_area_position: Position,
     ) -> Option<(f64, f64)> {
-        if img_aspect <= 0.0 {
-            return None;
-        }
-
-        let content_w = logical_width - WindowConstant::SKELETON_W;
-        let expected_h = content_w / img_aspect + WindowConstant::SKELETON_H;
-
-        if (logical_height - expected_h).abs() <= 2.0 {
-            return None;
-        }
-
-        let is_maximized = window.is_maximized();
-
-        let (target_w, target_h) = if is_maximized {
-            window.set_maximized(false);
-            calculate_max_window_size(screen_area_extent, img_aspect)
-                .unwrap_or((logical_width, expected_h))
-        } else {
-            (logical_width, expected_h)
-        };
-
-        log::info!(
-            "AspectRatioEnforcer: aspect correction {:.1}x{:.1} -> {:.1}x{:.1}{}",
+        snap_to_aspect(
+            window,
             logical_width,
             logical_height,
-            target_w,
-            target_h,
-            if is_maximized { " (was maximized)" } else { "" }
-        );
-
-        if is_maximized {
-            window.set_outer_position(winit::dpi::LogicalPosition::new(
-                screen_area_position.x,
-                screen_area_position.y,
-            ));
-        }
-
-        Some((target_w, target_h))
+            img_aspect,
+            screen_area_extent,
+            screen_area_position,
+        )
     }
 }
 
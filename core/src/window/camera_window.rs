@@ -38,6 +38,8 @@ use winit::window::{Window, WindowAttributes, WindowId};
 
 use thiserror::Error;
 
+#[cfg(target_os = "linux")]
+use super::aspect_ratio::X11Window;
 use crate::audio::capturer::list_audio_inputs;
 use crate::camera::capturer::CameraCapturer;
 use crate::components::fonts::{self as fonts_mod, GEIST_MEDIUM, GEIST_REGULAR, ICONS_FONT};
@@ -238,6 +240,12 @@ pub struct CameraWindow {
     screensharing_active: bool,
     resize_timer: Option<Instant>,
     visible: bool,
+    /// Size hints of the X11 window, `None` on Wayland.
+    #[cfg(target_os = "linux")]
+    x11_window: Option<X11Window>,
+    /// Tile count and grid the aspect hint was last set for, `None` while compact.
+    #[cfg(target_os = "linux")]
+    grid_aspect: Option<(usize, usize, usize)>,
 }
 
 pub fn camera_window_attributes() -> WindowAttributes {
@@ -344,6 +352,9 @@ impl CameraWindow {
         let (redraw_tx, redraw_rx) = mpsc::channel();
         let redraw_thread = Some(spawn_redraw_thread(redraw_rx, window.clone()));
 
+        #[cfg(target_os = "linux")]
+        let x11_window = X11Window::new(&window);
+
         Ok(Self {
             window: window.clone(),
             surface,
@@ -365,6 +376,10 @@ impl CameraWindow {
             screensharing_active: false,
             resize_timer: None,
             visible: true,
+            #[cfg(target_os = "linux")]
+            x11_window,
+            #[cfg(target_os = "linux")]
+            grid_aspect: None,
         })
     }
 
@@ -997,7 +1012,7 @@ impl CameraWindow {
                 } else {
                     UserEvent::StartCamera {
                         msg: CameraStartMessage { device_name: None },
-                        from_socket: false,
+                        reply_to: None,
                     }
                 };
                 log::info!("CameraWindow: video toggle -> {:?}", event);
@@ -1038,7 +1053,7 @@ impl CameraWindow {
                 };
                 if let Err(e) = self.event_loop_proxy.send_event(UserEvent::StartCamera {
                     msg,
-                    from_socket: false,
+                    reply_to: None,
                 }) {
                     log::error!("Failed to send StartCamera: {e:?}");
                 }
@@ -1061,7 +1076,7 @@ impl CameraWindow {
                     .event_loop_proxy
                     .send_event(UserEvent::StartAudioCapture {
                         msg,
-                        from_socket: false,
+                        reply_to: None,
                     })
                 {
                     log::error!("Failed to send StartAudioCapture: {e:?}");
@@ -1097,6 +1112,13 @@ impl CameraWindow {
             CAMERA_WINDOW_MIN_HEIGHT
         };
 
+        #[cfg(target_os = "linux")]
+        if self.sync_grid_aspect(is_compact, min_h) {
+            self.state.is_compact = is_compact;
+            self.state.compact_min_height = min_h;
+            return;
+        }
+
         if is_compact != self.state.is_compact || self.state.compact_min_height != min_h {
             self.state.is_compact = is_compact;
             self.state.compact_min_height = min_h;
@@ -1108,6 +1130,78 @@ impl CameraWindow {
         }
     }
 
+    /// Keeps the grid's aspect in the X11 size hints so resizing scales the
+    /// tiles instead of padding them. The grid is picked for the current size
+    /// whenever the tile count changes, then kept while resizing. Compact mode
+    /// is a single column and has no aspect.
+    ///
+    /// Returns `false` when there is no X11 window to set hints on, the min
+    /// size is then set through winit.
+    #[cfg(target_os = "linux")]
+    fn sync_grid_aspect(&mut self, is_compact: bool, min_h: f64) -> bool {
+        let Some(x11_window) = &self.x11_window else {
+            return false;
+        };
+
+        let grid_aspect = if is_compact {
+            None
+        } else {
+            let count = self
+                .participants
+                .read()
+                .map(|p| {
+                    let hidden = self.state.self_hidden && p.contains_key("local");
+                    p.len() - usize::from(hidden)
+                })
+                .unwrap_or(1)
+                .max(1);
+            match self.grid_aspect {
+                Some(grid) if grid.0 == count => Some(grid),
+                _ => {
+                    let size = self.state.viewport_size;
+                    let (cols, rows, _) = best_grid(
+                        size.width - MIN_GRID_PADDING * 2.0,
+                        size.height - HEADER_HEIGHT - MIN_GRID_PADDING * 2.0,
+                        count,
+                    );
+                    Some((count, cols, rows))
+                }
+            }
+        };
+
+        if grid_aspect == self.grid_aspect && self.state.compact_min_height == min_h {
+            return true;
+        }
+
+        /*
+         * Each tile adds its size plus one spacing to the grid, and the two
+         * paddings add one spacing less than that, which goes in the base size.
+         */
+        let aspect = grid_aspect.map(|(_, cols, rows)| {
+            let base = (MIN_GRID_PADDING * 2.0 - TILE_SPACING) as f64;
+            (
+                cols as f64 / rows as f64,
+                (base, HEADER_HEIGHT as f64 + base),
+            )
+        });
+        match x11_window.set_size_hints(
+            aspect,
+            (CAMERA_WINDOW_MIN_WIDTH, min_h),
+            self.window.scale_factor(),
+        ) {
+            Ok(()) => {
+                self.grid_aspect = grid_aspect;
+                true
+            }
+            Err(e) => {
+                log::warn!("CameraWindow::sync_grid_aspect: failed to set size hints: {e}");
+                /* Leave the min size to winit from now on. */
+                self.x11_window = None;
+                false
+            }
+        }
+    }
+
     /// Resize and reposition the window to the top-right corner of the current monitor,
     /// hiding the local participant tile.
     fn pin_to_corner(&mut self) {
@@ -1752,6 +1846,34 @@ fn identity_to_id(identity: &str) -> u64 {
     hasher.finish()
 }
 
+/// Find the grid configuration that maximizes tile size while ensuring ALL
+/// `count` tiles are visible. Returns the columns, rows and tile size.
+fn best_grid(available_width: f32, available_height: f32, count: usize) -> (usize, usize, f32) {
+    let mut best_tile_size = 0.0_f32;
+    let mut best_cols = 1;
+    let mut best_rows = count;
+
+    for cols in 1..=count {
+        let rows = (count as f32 / cols as f32).ceil() as usize;
+
+        // Calculate max tile size for this configuration
+        let max_tile_width = (available_width - (TILE_SPACING * (cols - 1) as f32)) / cols as f32;
+        let max_tile_height = (available_height - (TILE_SPACING * (rows - 1) as f32)) / rows as f32;
+
+        // Tile size is constrained by both width and height (1:1 aspect ratio)
+        let tile_size = max_tile_width.min(max_tile_height);
+
+        // Always pick the configuration with the largest tile size that fits all participants
+        if tile_size > 0.0 && tile_size > best_tile_size {
+            best_tile_size = tile_size;
+            best_cols = cols;
+            best_rows = rows;
+        }
+    }
+
+    (best_cols, best_rows, best_tile_size)
+}
+
 /// Create the responsive participant grid.
 ///
 /// Calculates optimal grid layout based on available size, maximizing tile size
@@ -1806,29 +1928,8 @@ fn create_participant_grid<'a>(
     let is_small_window = available_size.width < SMALL_WIDTH_THRESHOLD
         || available_size.height < SMALL_HEIGHT_THRESHOLD;
 
-    // Find the optimal grid configuration that maximizes tile size
-    // while ensuring ALL participants are visible
-    let mut best_tile_size = 0.0_f32;
-    let mut best_cols = 1;
-    let mut best_rows = participant_count;
-
-    for cols in 1..=participant_count {
-        let rows = (participant_count as f32 / cols as f32).ceil() as usize;
-
-        // Calculate max tile size for this configuration
-        let max_tile_width = (available_width - (TILE_SPACING * (cols - 1) as f32)) / cols as f32;
-        let max_tile_height = (available_height - (TILE_SPACING * (rows - 1) as f32)) / rows as f32;
-
-        // Tile size is constrained by both width and height (1:1 aspect ratio)
-        let tile_size = max_tile_width.min(max_tile_height);
-
-        // Always pick the configuration with the largest tile size that fits all participants
-        if tile_size > 0.0 && tile_size > best_tile_size {
-            best_tile_size = tile_size;
-            best_cols = cols;
-            best_rows = rows;
-        }
-    }
+    let (best_cols, best_rows, best_tile_size) =
+        best_grid(available_width, available_height, participant_count);
 
     // Apply minimum tile size (but don't exceed what fits)
     let tile_size = best_tile_size.max(MIN_TILE_SIZE);
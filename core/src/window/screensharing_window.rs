@@ -58,6 +58,7 @@ use crate::graphics::yuv_renderer::YuvVideoProgram;
 use crate::utils::clock;
 use crate::utils::geometry::{Extent, Position};
 use crate::windows::colors::ColorToken;
+use socket_lib::ControlRole;
 
 pub fn screensharing_window_attributes() -> WindowAttributes {
     let (init_w, init_h) = default_window_size();
@@ -291,6 +292,7 @@ const ICON_PEN: char = '\u{F109}';
 const ICON_CLICK_POINTER: char = '\u{F108}';
 
 // ── Segmented control buttons ────────────────────────────────────────────────
+// Ordered from the tab needing the most permissive role, see `allowed_tabs`.
 const SEGMENTED_BUTTONS: &[SegmentedButton] = &[
     SegmentedButton {
         id: "control",
@@ -309,6 +311,18 @@ const SEGMENTED_BUTTONS: &[SegmentedButton] = &[
     },
 ];
 
+/// The tabs a participant with `role` may use, each role drops the leading
+/// tab of the one above it.
+fn allowed_tabs(role: ControlRole) -> &'static [SegmentedButton] {
+    let first = match role {
+        ControlRole::Full => 0,
+        ControlRole::Draw => 1,
+        ControlRole::Pointer => 2,
+        ControlRole::ViewOnly => SEGMENTED_BUTTONS.len(),
+    };
+    &SEGMENTED_BUTTONS[first..]
+}
+
 #[derive(Error, Debug)]
 pub enum ScreensharingWindowError {
     #[error("Failed to create window")]
@@ -340,6 +354,16 @@ pub(crate) enum ScreenShareTab {
     Point,
 }
 
+/// Where the local participant stands with a sharer that hands out control,
+/// see `Feature::ControlHandoff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ControlHandoff {
+    NotRequested,
+    /// Asked the sharer, waiting for their answer
+    Requested,
+    Granted,
+}
+
 #[derive(Debug)]
 pub(crate) enum ScreenShareInputEvent {
     CursorMoved { x: f64, y: f64 },
@@ -380,6 +404,8 @@ struct ScreensharingState {
     draw_persist: bool,
     /// Whether the sharer currently allows remote control input.
     remote_control_allowed: bool,
+    /// What the sharer lets us do on their screen.
+    control_role: ControlRole,
     /// True after the user manually resizes the window; suppresses auto-maximize.
     user_has_resized: bool,
     /// Multi-click detection state.
@@ -409,6 +435,7 @@ impl Default for ScreensharingState {
             dropdown_open: false,
             draw_persist: false,
             remote_control_allowed: true,
+            control_role: ControlRole::Full,
             user_has_resized: false,
             last_click_count: 0,
             last_click_button: 0,
@@ -421,6 +448,13 @@ impl Default for ScreensharingState {
     }
 }
 
+impl ScreensharingState {
+    /// Whether the control tab forwards our mouse and keyboard.
+    fn can_remote_control(&self) -> bool {
+        self.remote_control_allowed && self.control_role == ControlRole::Full
+    }
+}
+
 // ── ScreensharingWindow ─────────────────────────────────────────────────────
 
 pub struct ScreensharingWindow {
@@ -442,6 +476,8 @@ pub struct ScreensharingWindow {
     mouse_in_participant_area: bool,
     /// True when participant_in_control names the local participant (use OS cursor in control tab).
     local_participant_in_control: bool,
+    /// Whether the sharer granted us control, only used if they hand it out.
+    control_handoff: ControlHandoff,
     screen_area: ScreenArea,
     screen_share_buffer: Arc<crate::livekit::video::VideoBufferManager>,
     participants_manager: ParticipantsManager,
@@ -709,6 +745,7 @@ impl ScreensharingWindow {
             programmatic_resize_target: None,
             mouse_in_participant_area: false,
             local_participant_in_control: false,
+            control_handoff: ControlHandoff::NotRequested,
             screen_share_buffer,
             participants_manager,
             click_animation_renderer: ClickAnimationRenderer::new(clock::default_clock()),
@@ -847,6 +884,25 @@ impl ScreensharingWindow {
         self.state.remote_control_allowed = allowed;
     }
 
+    /// Only offers the tabs `role` allows. Returns the new drawing mode when
+    /// the active tab had to change.
+    pub fn set_control_role(
+        &mut self,
+        role: ControlRole,
+    ) -> Option<crate::room_service::DrawingMode> {
+        log::info!("ScreensharingWindow: control role = {role:?}");
+        self.state.control_role = role;
+        let tabs = allowed_tabs(role);
+        // View only participants sit on the control tab, which sends nothing.
+        let id = tabs.first().unwrap_or(&SEGMENTED_BUTTONS[0]).id;
+        if self.state.active_tab == id || tabs.iter().any(|tab| tab.id == self.state.active_tab) {
+            return None;
+        }
+        let mode = self.select_tab(id);
+        self.update(ScreensharingMessage::TabSelected(id));
+        Some(mode)
+    }
+
     /// Update the window for a new sharer: refresh the display name and swap
     /// the redraw channel so the newly spawned `process_video_stream` can
     /// drive redraws.
@@ -869,6 +925,7 @@ impl ScreensharingWindow {
 
         // Window-level state.
         self.local_participant_in_control = false;
+        self.control_handoff = ControlHandoff::NotRequested;
         self.programmatic_resize_target = None;
         self.mouse_in_participant_area = false;
         self.last_rendered_frame_id = 0;
@@ -889,6 +946,14 @@ impl ScreensharingWindow {
         self.update_cursor();
     }
 
+    pub(crate) fn control_handoff(&self) -> ControlHandoff {
+        self.control_handoff
+    }
+
+    pub(crate) fn set_control_handoff(&mut self, control_handoff: ControlHandoff) {
+        self.control_handoff = control_handoff;
+    }
+
     /// Compute multi-click count using the same logic as Chromium.
     /// Coordinates are in logical pixels so the distance threshold is
     /// resolution-independent.
@@ -1015,7 +1080,7 @@ impl ScreensharingWindow {
                         );
                         input_events
                             .push(ScreenShareInputEvent::DrawAddPoint { x: pct_x, y: pct_y });
-                    } else {
+                    } else if self.state.control_role != ControlRole::ViewOnly {
                         input_events
                             .push(ScreenShareInputEvent::CursorMoved { x: pct_x, y: pct_y });
                     }
@@ -1143,7 +1208,7 @@ impl ScreensharingWindow {
                             input_events
                                 .push(ScreenShareInputEvent::ClickAnimation { x: pct_x, y: pct_y });
                         }
-                    } else if self.state.remote_control_allowed {
+                    } else if self.state.can_remote_control() {
                         // control mode
                         // Use the Web/MDN MouseEvent.button convention
                         // (the receiving side interprets these values):
@@ -1212,7 +1277,7 @@ impl ScreensharingWindow {
             WindowEvent::MouseWheel { delta, .. } => {
                 if self.mouse_in_participant_area
                     && self.state.active_tab == "control"
-                    && self.state.remote_control_allowed
+                    && self.state.can_remote_control()
                 {
                     // Extract delta_x and delta_y from the scroll delta.
                     // Always convert lines to pixels, it seems to work for now, maybe a better approach is
@@ -1259,7 +1324,7 @@ impl ScreensharingWindow {
 
                 if self.mouse_in_participant_area
                     && self.state.active_tab == "control"
-                    && self.state.remote_control_allowed
+                    && self.state.can_remote_control()
                 {
                     // Extract key string to match the format expected by keyboard.rs.
                     // We need strings like "Enter", "Tab", "a", "A", etc., not control characters.
@@ -1428,36 +1493,7 @@ impl ScreensharingWindow {
                 for msg in messages {
                     match &msg {
                         ScreensharingMessage::TabSelected(id) => {
-                            let tab = match *id {
-                                "draw" => ScreenShareTab::Draw,
-                                "point" => ScreenShareTab::Point,
-                                _ => ScreenShareTab::Control,
-                            };
-                            let mode = match tab {
-                                ScreenShareTab::Draw => crate::room_service::DrawingMode::Draw(
-                                    crate::room_service::DrawSettings {
-                                        permanent: self.state.draw_persist,
-                                    },
-                                ),
-                                ScreenShareTab::Point => {
-                                    crate::room_service::DrawingMode::ClickAnimation
-                                }
-                                ScreenShareTab::Control => {
-                                    crate::room_service::DrawingMode::Disabled
-                                }
-                            };
-                            if mode == crate::room_service::DrawingMode::Disabled
-                                || mode == crate::room_service::DrawingMode::ClickAnimation
-                            {
-                                self.participants_manager.draw_clear_all_paths(
-                                    drawing_helpers::LOCAL_PARTICIPANT_IDENTITY,
-                                );
-                            }
-                            self.participants_manager.set_drawing_mode(
-                                drawing_helpers::LOCAL_PARTICIPANT_IDENTITY,
-                                mode.clone(),
-                            );
-                            self.state.left_mouse_pressed = false;
+                            let mode = self.select_tab(id);
                             input_events.push(ScreenShareInputEvent::DrawingModeChanged(mode));
                         }
                         ScreensharingMessage::DropdownItemClicked(index) => {
@@ -1613,12 +1649,18 @@ impl ScreensharingWindow {
             left: 0.0,
         });
 
-        let seg_ctrl = seg_ctrl_mod::segmented_control(
-            SEGMENTED_BUTTONS,
-            state.active_tab,
-            &state.tab_anim,
-            ScreensharingMessage::TabSelected,
-        );
+        let tabs = allowed_tabs(state.control_role);
+        let seg_ctrl: iced::Element<'a, ScreensharingMessage, Theme, iced::Renderer> =
+            if tabs.is_empty() {
+                Space::new().into()
+            } else {
+                seg_ctrl_mod::segmented_control(
+                    tabs,
+                    state.active_tab,
+                    &state.tab_anim,
+                    ScreensharingMessage::TabSelected,
+                )
+            };
 
         // ── Header: stack-based layout so the segmented control is truly
         //    centered across the full window width, independent of name width.
@@ -1709,10 +1751,15 @@ impl ScreensharingWindow {
             ScreensharingMessage,
             Theme,
             iced::Renderer,
-        > = if !state.remote_control_allowed && state.active_tab == "control" {
+        > = if !state.can_remote_control() && state.active_tab == "control" {
+            let label = if state.control_role == ControlRole::ViewOnly {
+                "You can only view this screen"
+            } else {
+                "Remote control is disabled"
+            };
             container(
                 container(
-                    text("Remote control is disabled")
+                    text(label)
                         .size(12)
                         .font(GEIST_REGULAR)
                         .color(Color::from_rgba(0.0, 0.0, 0.0, 0.9)),
@@ -1838,12 +1885,44 @@ impl ScreensharingWindow {
         }
     }
 
+    /// Switches the local participant to the drawing mode of tab `id`, the
+    /// tab itself changes in `update`.
+    fn select_tab(&mut self, id: &str) -> crate::room_service::DrawingMode {
+        let tab = match id {
+            "draw" => ScreenShareTab::Draw,
+            "point" => ScreenShareTab::Point,
+            _ => ScreenShareTab::Control,
+        };
+        let mode = match tab {
+            ScreenShareTab::Draw => {
+                crate::room_service::DrawingMode::Draw(crate::room_service::DrawSettings {
+                    permanent: self.state.draw_persist,
+                })
+            }
+            ScreenShareTab::Point => crate::room_service::DrawingMode::ClickAnimation,
+            ScreenShareTab::Control => crate::room_service::DrawingMode::Disabled,
+        };
+        if mode == crate::room_service::DrawingMode::Disabled
+            || mode == crate::room_service::DrawingMode::ClickAnimation
+        {
+            self.participants_manager
+                .draw_clear_all_paths(drawing_helpers::LOCAL_PARTICIPANT_IDENTITY);
+        }
+        self.participants_manager
+            .set_drawing_mode(drawing_helpers::LOCAL_PARTICIPANT_IDENTITY, mode.clone());
+        self.state.left_mouse_pressed = false;
+        mode
+    }
+
     /// Handle a screensharing UI message (state update).
     fn update(&mut self, message: ScreensharingMessage) {
         match message {
             ScreensharingMessage::TabSelected(id) => {
-                self.state.tab_anim =
-                    seg_ctrl_mod::start_animation(SEGMENTED_BUTTONS, self.state.active_tab, id);
+                self.state.tab_anim = seg_ctrl_mod::start_animation(
+                    allowed_tabs(self.state.control_role),
+                    self.state.active_tab,
+                    id,
+                );
                 self.state.active_tab = id;
                 self.state.queued_modifier_events.clear();
                 self.update_cursor();
@@ -1,5 +1,6 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use std::io;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 
 pub static SOCKET_PATH: OnceLock<String> = OnceLock::new();
@@ -14,8 +15,10 @@ mod remote_clipboard;
 mod remote_cursor;
 mod remote_drawing;
 mod remote_keyboard;
+mod replay;
 mod screenshare_client;
 mod screensharing;
+mod timeline;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -107,6 +110,25 @@ enum Commands {
     },
     /// Reproduce screenshare deadlock hang
     HangRepro,
+    /// Replay a recording made with HOPP_IPC_RECORDING against the core
+    Replay {
+        /// JSONL recording written by the shell
+        file: PathBuf,
+        /// Keep the recorded gaps between messages instead of sending back to back
+        #[arg(long)]
+        realtime: bool,
+        /// Token the core was started with via --auth-token, if any
+        #[arg(long)]
+        auth_token: Option<String>,
+    },
+    /// Replay a timeline the core recorded with --timeline-dir as remote participants
+    Timeline {
+        /// Gzipped JSONL timeline written by the core
+        file: PathBuf,
+        /// Keep the recorded gaps between events instead of sending back to back
+        #[arg(long)]
+        realtime: bool,
+    },
 }
 
 #[derive(Clone, ValueEnum, Debug)]
@@ -461,6 +483,18 @@ async fn main() -> io::Result<()> {
             hang_repro::test_screenshare_reconnect_hang().await?;
             println!("Hang reproduction test finished.");
         }
+        Commands::Replay {
+            file,
+            realtime,
+            auth_token,
+        } => {
+            replay::replay(&file, realtime, auth_token)?;
+            println!("Replay finished.");
+        }
+        Commands::Timeline { file, realtime } => {
+            timeline::replay_timeline(&file, realtime).await?;
+            println!("Timeline replay finished.");
+        }
         Commands::LocalDrawing { test_type } => {
             match test_type {
                 LocalDrawingTest::Permanent => {
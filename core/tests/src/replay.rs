@@ -0,0 +1,78 @@
+use socket_lib::record::{self, Direction};
+use socket_lib::{Message, SocketOptions};
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to keep printing the core's messages after the last one is sent.
+const TRAILING_WAIT: Duration = Duration::from_secs(5);
+
+/// Replays a recording the shell made with `HOPP_IPC_RECORDING` against a
+/// running core, acting as the shell. Messages the shell sent are sent again
+/// in order, back to back or at their recorded pace with `realtime`. The
+/// core's `QueryPreferredCamera` is answered with the recorded answers.
+pub fn replay(path: &Path, realtime: bool, auth_token: Option<String>) -> io::Result<()> {
+    let entries = record::read_recording(path)?;
+    let mut to_send = Vec::new();
+    let mut preferred_cameras = VecDeque::new();
+    for entry in entries {
+        if entry.direction != Direction::Sent {
+            continue;
+        }
+        match entry.message {
+            Message::PreferredCamera(camera) => preferred_cameras.push_back(camera),
+            message => to_send.push((entry.elapsed_ms, message)),
+        }
+    }
+    println!(
+        "Replaying {} messages from {}{}",
+        to_send.len(),
+        path.display(),
+        if realtime { " in real time" } else { "" }
+    );
+
+    let socket_path = crate::SOCKET_PATH
+        .get()
+        .expect("SOCKET_PATH not initialized");
+    println!("Connecting to socket: {socket_path}");
+    let options = SocketOptions {
+        auth_token,
+        ..Default::default()
+    };
+    let (sender, mut event_socket) = socket_lib::connect_with_options(socket_path, &[], options)?;
+
+    let events = event_socket.take_events();
+    let preferred_cameras = Arc::new(Mutex::new(preferred_cameras));
+    let answer_sender = sender.clone();
+    std::thread::spawn(move || {
+        for event in events {
+            println!("<- {event:?}");
+            if matches!(event, Message::QueryPreferredCamera) {
+                let camera = preferred_cameras.lock().unwrap().pop_front().flatten();
+                let _ = answer_sender.send(Message::PreferredCamera(camera));
+            }
+        }
+    });
+    let responses = event_socket.take_responses();
+    std::thread::spawn(move || {
+        for response in responses {
+            println!("<- {response:?}");
+        }
+    });
+
+    let start = Instant::now();
+    for (elapsed_ms, message) in to_send {
+        if realtime {
+            let due = Duration::from_millis(elapsed_ms);
+            std::thread::sleep(due.saturating_sub(start.elapsed()));
+        }
+        println!("-> {message:?}");
+        sender.send(message)?;
+    }
+
+    println!("Replay finished, waiting for trailing messages...");
+    std::thread::sleep(TRAILING_WAIT);
+    Ok(())
+}
@@ -13,7 +13,7 @@ pub fn connect_socket() -> io::Result<(SocketSender, EventSocket)> {
         .get()
         .expect("SOCKET_PATH not initialized");
     println!("Connecting to socket: {socket_path}");
-    socket_lib::connect(socket_path)
+    socket_lib::connect(socket_path, &[])
 }
 
 /// Returns the screen content id to capture, read from the `HOPP_TEST_SCREEN_ID`
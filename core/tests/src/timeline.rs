@@ -0,0 +1,103 @@
+use crate::livekit_utils;
+use crate::screenshare_client;
+use flate2::read::GzDecoder;
+use livekit::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// One line of a timeline, the event is kept as JSON so events this crate
+/// has no type for are replayed too.
+#[derive(Debug, Deserialize)]
+struct Entry {
+    elapsed_ms: u64,
+    identity: String,
+    direction: String,
+    event: serde_json::Value,
+}
+
+fn read_timeline(path: &Path) -> io::Result<Vec<Entry>> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            /* The core crashed while writing it, keep what made it to disk. */
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => println!("Skipping line {}: {e}", index + 1),
+        }
+    }
+    Ok(entries)
+}
+
+/// Replays a timeline the core recorded with `--timeline-dir` against a
+/// running core. A screen share is started and every participant whose
+/// events the recording core received joins the room under their recorded
+/// identity and sends them again, back to back or at their recorded pace
+/// with `realtime`.
+pub async fn replay_timeline(path: &Path, realtime: bool) -> io::Result<()> {
+    let entries: Vec<Entry> = read_timeline(path)?
+        .into_iter()
+        .filter(|entry| entry.direction == "Received")
+        .collect();
+    println!(
+        "Replaying {} events from {}{}",
+        entries.len(),
+        path.display(),
+        if realtime { " in real time" } else { "" }
+    );
+
+    let (sender, _event_socket) = screenshare_client::start_screenshare_session()?;
+
+    let url = std::env::var("LIVEKIT_URL").expect("LIVEKIT_URL environment variable not set");
+    let mut rooms = HashMap::new();
+    for entry in &entries {
+        if rooms.contains_key(&entry.identity) {
+            continue;
+        }
+        println!("Connecting as {}", entry.identity);
+        let token = livekit_utils::generate_token(&entry.identity);
+        let (room, _rx) = Room::connect(&url, &token, RoomOptions::default())
+            .await
+            .map_err(io::Error::other)?;
+        rooms.insert(entry.identity.clone(), room);
+    }
+
+    println!("Participants connected. Waiting for setup...");
+    sleep(Duration::from_secs(3)).await;
+
+    let start = Instant::now();
+    for entry in entries {
+        if realtime {
+            let due = Duration::from_millis(entry.elapsed_ms);
+            sleep(due.saturating_sub(start.elapsed())).await;
+        }
+        println!("{} -> {}", entry.identity, entry.event);
+        let payload = serde_json::to_vec(&entry.event).map_err(io::Error::other)?;
+        rooms[&entry.identity]
+            .local_participant()
+            .publish_data(DataPacket {
+                payload,
+                reliable: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(io::Error::other)?;
+    }
+
+    println!("Timeline replayed, waiting before stopping the screen share...");
+    sleep(Duration::from_secs(2)).await;
+    screenshare_client::stop_screenshare_session(&sender)?;
+    Ok(())
+}
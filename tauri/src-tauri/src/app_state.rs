@@ -12,6 +12,7 @@ fn default_screen_share_resolution() -> ScreenShareResolution {
 /// User-facing settings exposed in the Settings window.
 /// All fields are non-optional with sensible defaults.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "bindings", derive(ts_rs::TS, schemars::JsonSchema), ts(export))]
 pub struct UserSettings {
     pub call_feedback_popup: bool,
     pub show_dock_icon_in_call: bool,
@@ -34,6 +35,9 @@ pub struct UserSettings {
     pub telemetry_enabled: bool,
     #[serde(default = "default_true")]
     pub auto_update_enabled: bool,
+    /// User ids of teammates granted remote control without a prompt.
+    #[serde(default)]
+    pub trusted_controllers: Vec<String>,
 }
 
 impl Default for UserSettings {
@@ -54,6 +58,7 @@ impl Default for UserSettings {
             shortcut_end_call: None,
             telemetry_enabled: true,
             auto_update_enabled: true,
+            trusted_controllers: Vec::new(),
         }
     }
 }
@@ -543,3 +548,18 @@ impl AppState {
         false
     }
 }
+
+#[cfg(all(test, feature = "bindings"))]
+mod tests {
+    use super::*;
+
+    /// Writes the JSON Schema of `UserSettings` next to the TypeScript
+    /// bindings, like `socket_lib` does for `Message`.
+    #[test]
+    fn export_bindings_json_schema() {
+        let dir = std::env::var("TS_RS_EXPORT_DIR").unwrap_or_else(|_| "./bindings".to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let schema = serde_json::to_string_pretty(&schemars::schema_for!(UserSettings)).unwrap();
+        fs::write(Path::new(&dir).join("UserSettings.schema.json"), schema + "\n").unwrap();
+    }
+}
@@ -0,0 +1,493 @@
+//! Local JSON-RPC endpoint for editor integrations and scripts.
+//!
+//! On launch the shell listens on a Unix socket in the app data dir, or on a
+//! random localhost port on Windows, and writes the address together with a
+//! fresh token to `control-api.json` next to it, readable by the user only.
+//! Requests and responses are JSON-RPC 2.0 objects, one per line, and the
+//! first request on a connection has to be `authenticate`:
+//!
+//! ```text
+//! -> {"jsonrpc":"2.0","id":1,"method":"authenticate","params":{"token":"..."}}
+//! <- {"jsonrpc":"2.0","id":1,"result":null}
+//! -> {"jsonrpc":"2.0","id":2,"method":"get_call_state"}
+//! <- {"jsonrpc":"2.0","id":2,"result":{"call_active":true,"is_camera_on":false,...}}
+//! ```
+//!
+//! After `subscribe` the connection also gets call events as notifications,
+//! e.g. `{"jsonrpc":"2.0","method":"call_state","params":{...}}`.
+use hopp::AppData;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use socket_lib::{Content, Message, ScreenShareMessage};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+#[cfg(windows)]
+use std::net::{TcpListener as Listener, TcpStream as Stream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+
+/// File in the app data dir that tells clients where to connect and how.
+const INFO_FILE_NAME: &str = "control-api.json";
+
+#[cfg(unix)]
+const SOCKET_FILE_NAME: &str = "control.sock";
+
+/// Runs a method other than `authenticate` and `subscribe`.
+type Dispatch = dyn Fn(&str, Value) -> Result<Value, RpcError> + Send + Sync;
+
+#[derive(Debug, Serialize, thiserror::Error)]
+#[error("{message} ({code})")]
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn parse_error(e: serde_json::Error) -> Self {
+        Self::new(-32700, format!("Parse error: {e}"))
+    }
+
+    fn method_not_found(method: &str) -> Self {
+        Self::new(-32601, format!("Method not found: {method}"))
+    }
+
+    fn invalid_params(e: serde_json::Error) -> Self {
+        Self::new(-32602, format!("Invalid params: {e}"))
+    }
+
+    fn unauthenticated() -> Self {
+        Self::new(-32001, "Not authenticated")
+    }
+
+    fn failed(message: impl Into<String>) -> Self {
+        Self::new(-32000, message)
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthenticateParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct StartCameraParams {
+    #[serde(default)]
+    device_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EnabledParams {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct StartScreenShareParams {
+    /// Shares this display or window right away, without it the picker opens.
+    #[serde(default)]
+    content: Option<Content>,
+}
+
+/// What `get_call_state` returns and `call_state` notifications carry.
+#[derive(Debug, Serialize)]
+pub struct CallState {
+    pub call_active: bool,
+    pub is_camera_on: bool,
+    pub is_screensharing: bool,
+    pub remote_control_enabled: bool,
+}
+
+impl CallState {
+    pub fn of(data: &AppData) -> Self {
+        Self {
+            call_active: data.call_active,
+            is_camera_on: data.is_camera_on,
+            is_screensharing: data.is_screensharing,
+            remote_control_enabled: data.app_state.user_settings().remote_control_enabled,
+        }
+    }
+}
+
+/// Connections that called `subscribe`, managed as tauri state.
+#[derive(Default)]
+pub struct ControlApi {
+    subscribers: Mutex<Vec<mpsc::Sender<String>>>,
+}
+
+impl ControlApi {
+    fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let line = json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string();
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(line.clone()).is_ok());
+    }
+}
+
+/// Sends the `method` notification to the subscribed clients, a no-op
+/// before the endpoint is up.
+pub fn notify(app: &AppHandle, method: &str, params: impl Serialize) {
+    let Some(api) = app.try_state::<Arc<ControlApi>>() else {
+        return;
+    };
+    match serde_json::to_value(params) {
+        Ok(params) => api.notify(method, params),
+        Err(e) => log::error!("control_api::notify: failed to serialize {method}: {e}"),
+    }
+}
+
+/// Notifies the subscribed clients of the current call state. Takes the
+/// already locked `data` so callers can notify right where they change it.
+pub fn notify_call_state(app: &AppHandle, data: &AppData) {
+    notify(app, "call_state", CallState::of(data));
+}
+
+/// Binds the endpoint, writes `control-api.json` to `dir` and serves
+/// clients on a background thread.
+pub fn start(app: &AppHandle, dir: &Path) -> io::Result<()> {
+    let token = hopp::create_auth_token();
+
+    #[cfg(unix)]
+    let (listener, info) = {
+        let socket_path = dir.join(SOCKET_FILE_NAME);
+        /* A previous run may have left its socket behind. */
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = Listener::bind(&socket_path)?;
+        {
+            use std::os::unix::fs::PermissionsExt;
+            /* Only our user may connect, the token is a second line of defence. */
+            std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        let info = json!({ "transport": "unix", "address": socket_path, "token": token });
+        (listener, info)
+    };
+    #[cfg(windows)]
+    let (listener, info) = {
+        let listener = Listener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?.to_string();
+        let info = json!({ "transport": "tcp", "address": address, "token": token });
+        (listener, info)
+    };
+    write_info_file(&dir.join(INFO_FILE_NAME), &info)?;
+    log::info!("control_api: listening on {}", info["address"]);
+
+    let api = Arc::new(ControlApi::default());
+    app.manage(api.clone());
+    let dispatch_app = app.clone();
+    let dispatch: Arc<Dispatch> =
+        Arc::new(move |method: &str, params: Value| dispatch(&dispatch_app, method, params));
+    std::thread::spawn(move || accept_loop(listener, token, dispatch, api));
+    Ok(())
+}
+
+/// Writes the connection info so that only the current user can read it.
+fn write_info_file(path: &Path, info: &Value) -> io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        /* `mode` only applies to new files, tighten an existing one too. */
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    file.write_all(info.to_string().as_bytes())
+}
+
+fn accept_loop(listener: Listener, token: String, dispatch: Arc<Dispatch>, api: Arc<ControlApi>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::error!("control_api: failed to accept client: {e}");
+                continue;
+            }
+        };
+        let token = token.clone();
+        let dispatch = dispatch.clone();
+        let api = api.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &token, dispatch.as_ref(), &api) {
+                log::warn!("control_api: connection closed: {e}");
+            }
+        });
+    }
+}
+
+fn handle_connection(
+    stream: Stream,
+    token: &str,
+    dispatch: &Dispatch,
+    api: &ControlApi,
+) -> io::Result<()> {
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let reader = BufReader::new(stream);
+    let mut authenticated = false;
+    let mut subscribed = false;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                write_line(
+                    &writer,
+                    &Response::new(Value::Null, Err(RpcError::parse_error(e))),
+                )?;
+                continue;
+            }
+        };
+
+        let result = match request.method.as_str() {
+            "authenticate" => match parse_params::<AuthenticateParams>(request.params) {
+                Ok(params)
+                    if socket_lib::tokens_match(params.token.as_bytes(), token.as_bytes()) =>
+                {
+                    authenticated = true;
+                    Ok(Value::Null)
+                }
+                Ok(_) => Err(RpcError::unauthenticated()),
+                Err(e) => Err(e),
+            },
+            _ if !authenticated => Err(RpcError::unauthenticated()),
+            "subscribe" => {
+                if !subscribed {
+                    subscribed = true;
+                    forward_notifications(api.subscribe(), writer.clone());
+                }
+                Ok(Value::Null)
+            }
+            method => dispatch(method, request.params),
+        };
+
+        /* A wrong token ends the connection, no guessing on the same one. */
+        let rejected = !authenticated && request.method == "authenticate";
+        if let Some(id) = request.id {
+            write_line(&writer, &Response::new(id, result))?;
+        }
+        if rejected {
+            log::warn!("control_api: rejected client with a wrong token");
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the subscription's notifications to the client until either side
+/// goes away.
+fn forward_notifications(notifications: mpsc::Receiver<String>, writer: Arc<Mutex<Stream>>) {
+    std::thread::spawn(move || {
+        for line in notifications {
+            let mut stream = writer.lock().unwrap();
+            if writeln!(stream, "{line}").is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn write_line(writer: &Mutex<Stream>, response: &Response) -> io::Result<()> {
+    let line = serde_json::to_string(response)?;
+    let mut stream = writer.lock().unwrap();
+    writeln!(stream, "{line}")
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    /* Methods whose params are all optional can be called without any. */
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(RpcError::invalid_params)
+}
+
+/// The shell side of each method, reusing the frontend's commands.
+fn dispatch(app: &AppHandle, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "toggle_mic" => crate::toggle_mic(app.clone()),
+        "mute_mic" => crate::mute_mic(app.clone()),
+        "unmute_mic" => crate::unmute_mic(app.clone()),
+        "start_camera" => {
+            let params: StartCameraParams = parse_params(params)?;
+            crate::start_camera(app.clone(), params.device_name).map_err(RpcError::failed)?;
+        }
+        "stop_camera" => crate::stop_camera(app.clone()),
+        "start_screen_share" => {
+            let params: StartScreenShareParams = parse_params(params)?;
+            match params.content {
+                Some(content) => {
+                    let data = app.state::<Mutex<AppData>>();
+                    let data = data.lock().unwrap();
+                    let resolution = data.app_state.user_settings().screen_share_resolution;
+                    let message = ScreenShareMessage {
+                        content,
+                        resolution: resolution.extent(),
+                    };
+                    data.sender
+                        .send(Message::StartScreenShare(message))
+                        .map_err(|e| RpcError::failed(e.to_string()))?;
+                }
+                None => tauri::async_runtime::block_on(crate::get_available_content(app.clone()))
+                    .map_err(RpcError::failed)?,
+            }
+        }
+        "stop_screen_share" => tauri::async_runtime::block_on(crate::stop_sharing(app.clone())),
+        "end_call" => crate::end_call(app.clone()),
+        "set_remote_control_enabled" => {
+            let params: EnabledParams = parse_params(params)?;
+            crate::set_remote_control_enabled(app.clone(), params.enabled);
+        }
+        "get_call_state" => {
+            let data = app.state::<Mutex<AppData>>();
+            let data = data.lock().unwrap();
+            return serde_json::to_value(CallState::of(&data))
+                .map_err(|e| RpcError::failed(e.to_string()));
+        }
+        _ => return Err(RpcError::method_not_found(method)),
+    }
+    Ok(Value::Null)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn connect(api: Arc<ControlApi>) -> (BufReader<Stream>, Stream) {
+        let (server, client) = Stream::pair().unwrap();
+        std::thread::spawn(move || {
+            let dispatch = |method: &str, _params: Value| match method {
+                "get_call_state" => Ok(json!({ "call_active": true })),
+                _ => Err(RpcError::method_not_found(method)),
+            };
+            let _ = handle_connection(server, "secret", &dispatch, &api);
+        });
+        (BufReader::new(client.try_clone().unwrap()), client)
+    }
+
+    fn request(reader: &mut BufReader<Stream>, writer: &mut Stream, request: Value) -> Value {
+        writeln!(writer, "{request}").unwrap();
+        read(reader)
+    }
+
+    fn read(reader: &mut BufReader<Stream>) -> Value {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn test_methods_require_authentication() {
+        let (mut reader, mut writer) = connect(Arc::default());
+
+        let response = request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "get_call_state" }),
+        );
+        assert_eq!(response["error"]["code"], -32001);
+
+        let response = request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "authenticate", "params": { "token": "secret" } }),
+        );
+        assert_eq!(response["result"], Value::Null);
+        assert!(response.get("error").is_none());
+
+        let response = request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "get_call_state" }),
+        );
+        assert_eq!(response["id"], 3);
+        assert_eq!(response["result"]["call_active"], true);
+    }
+
+    #[test]
+    fn test_wrong_token_closes_the_connection() {
+        let (mut reader, mut writer) = connect(Arc::default());
+
+        let response = request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": { "token": "guess" } }),
+        );
+        assert_eq!(response["error"]["code"], -32001);
+        let mut line = String::new();
+        assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_subscribers_get_notifications() {
+        let api = Arc::new(ControlApi::default());
+        let (mut reader, mut writer) = connect(api.clone());
+
+        request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": { "token": "secret" } }),
+        );
+        request(
+            &mut reader,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "subscribe" }),
+        );
+        api.notify("call_ended", Value::Null);
+
+        let notification = read(&mut reader);
+        assert_eq!(notification["method"], "call_ended");
+        assert!(notification.get("id").is_none());
+    }
+}
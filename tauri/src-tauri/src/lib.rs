@@ -2,7 +2,7 @@
 pub mod app_activation;
 pub mod app_state;
 pub mod permissions;
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 pub mod sleep_prevention;
 pub mod sounds;
 pub mod tray;
@@ -27,7 +27,7 @@ use tauri::{Rect, TitleBarStyle, WebviewWindow};
 use tauri_plugin_autostart::AutoLaunchManager;
 use tauri_plugin_shell::{process::CommandChild, process::CommandEvent, ShellExt};
 
-use socket_lib::{EventSocket, Message, SocketSender};
+use socket_lib::{EventSocket, HandshakeError, Message, SocketSender};
 #[cfg(target_os = "macos")]
 use tauri::{LogicalPosition, PhysicalPosition, PhysicalSize};
 
@@ -44,6 +44,8 @@ pub enum CoreProcessCreationError {
     SocketCreationFailed,
     #[error("Failed to send message to core process")]
     SendMessageFailed,
+    #[error("Core process is incompatible: {0}")]
+    IncompatibleCore(String),
 }
 
 /// Wrapper for the core process child handle.
@@ -57,7 +59,8 @@ pub struct AppData {
     /// Send half of the socket connection to the core process.
     pub sender: SocketSender,
 
-    /// Receive half that routes messages into `events` and `responses` channels.
+    /// Receive half that routes messages into `events` and `responses` channels,
+    /// responses to `call_core` go straight to the caller.
     pub event_socket: EventSocket,
 
     /// Active sound entries currently being played by the application.
@@ -102,8 +105,9 @@ pub struct AppData {
     #[cfg(target_os = "macos")]
     pub activation_policy_regular: bool,
 
-    /// macOS sleep prevention state — holds an activity assertion while a call is active.
-    #[cfg(target_os = "macos")]
+    /// Sleep prevention state — holds an activity assertion (macOS) or D-Bus
+    /// inhibitors (Linux) while a call is active.
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
     pub sleep_prevention: sleep_prevention::SleepPrevention,
 }
 
@@ -132,42 +136,35 @@ impl AppData {
             call_active: false,
             is_camera_on: false,
             is_screensharing: false,
-            #[cfg(target_os = "macos")]
+            #[cfg(any(target_os = "macos", target_os = "linux"))]
             sleep_prevention: sleep_prevention::SleepPrevention::new(),
         }
     }
 }
 
-/// Receive a response from core, retrying if a stale response from a
-/// previous timed-out request arrives first. The `extract` closure returns
-/// `Ok(T)` for the expected variant or `Err(Message)` for unexpected ones.
-pub fn recv_expected_response<T>(
-    event_socket: &EventSocket,
-    extract: impl Fn(Message) -> Result<T, Message>,
-) -> Result<T, std::sync::mpsc::RecvTimeoutError> {
+#[derive(Debug, thiserror::Error)]
+pub enum CoreCallError {
+    #[error(transparent)]
+    Call(#[from] socket_lib::CallError),
+    #[error("Unexpected response from core: {0}")]
+    UnexpectedResponse(String),
+}
+
+/// Send `request` to core and wait for its response. The `extract` closure
+/// returns `Ok(T)` for the expected variant or `Err(Message)` for unexpected ones.
+pub fn call_core<T>(
+    sender: &SocketSender,
+    request: Message,
+    extract: impl FnOnce(Message) -> Result<T, Message>,
+) -> Result<T, CoreCallError> {
     // TODO: 10 seconds is too much. We should make our calls to be as fast as possible
-    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
-    loop {
-        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
-        if remaining.is_zero() {
-            return Err(std::sync::mpsc::RecvTimeoutError::Timeout);
-        }
-        match event_socket.responses.recv_timeout(remaining) {
-            Ok(msg) => match extract(msg) {
-                Ok(val) => return Ok(val),
-                Err(other) => {
-                    let t = std::any::type_name::<T>();
-                    log::error!(
-                        "recv_expected_response<{t}>: unexpected response (stale?): {other:?}"
-                    );
-                    sentry_utils::upload_logs_event(format!(
-                        "recv_expected_response<{t}>: unexpected response: {other:?}"
-                    ));
-                }
-            },
-            Err(e) => return Err(e),
-        }
-    }
+    let response = sender.call(request, std::time::Duration::from_secs(10))?;
+    extract(response).map_err(|other| {
+        let t = std::any::type_name::<T>();
+        log::error!("call_core<{t}>: unexpected response: {other:?}");
+        sentry_utils::upload_logs_event(format!("call_core<{t}>: unexpected response: {other:?}"));
+        CoreCallError::UnexpectedResponse(format!("{other:?}"))
+    })
 }
 
 /// Monitors core process output and emits crash events.
@@ -235,6 +232,13 @@ async fn show_stdout(mut receiver: Receiver<CommandEvent>, app_handle: AppHandle
     }
     log::info!("show_stdout: Finished");
 
+    // The call can't outlive the core process, don't keep the display awake for it.
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        let data = app_handle.state::<Mutex<AppData>>();
+        data.lock().unwrap().sleep_prevention.disable();
+    }
+
     // Communicate to the frontend that the core process has crashed.
     let res = app_handle.emit("core_process_crashed", crash_msg);
     if let Err(e) = res {
@@ -246,6 +250,7 @@ async fn show_stdout(mut receiver: Receiver<CommandEvent>, app_handle: AppHandle
 fn start_sidecar(
     app: &tauri::AppHandle,
     socket_path: &str,
+    auth_token: &str,
 ) -> (Receiver<CommandEvent>, CommandChild) {
     log::info!("start_sidecar:");
 
@@ -262,7 +267,7 @@ fn start_sidecar(
         }
     }
 
-    let mut args = vec!["--socket-path", socket_path];
+    let mut args = vec!["--socket-path", socket_path, "--auth-token", auth_token];
 
     let sentry_dsn = get_sentry_dsn();
     if !cfg!(debug_assertions) {
@@ -270,6 +275,16 @@ fn start_sidecar(
         args.push(&sentry_dsn);
     }
 
+    /* Opt-in, for looking into cursor and drawing glitches after a screen share. */
+    let timeline_dir = env::var("HOPP_TIMELINE_DIR").ok();
+    if let Some(dir) = &timeline_dir {
+        args.push("--timeline-dir");
+        args.push(dir);
+        if env::var_os("HOPP_TIMELINE_REDACT").is_some() {
+            args.push("--timeline-redact");
+        }
+    }
+
     let mut hopp_core_name = "hopp_core".to_string();
     if cfg!(debug_assertions) {
         hopp_core_name = format!("hopp_core{}", env::var("HOPP_SUFFIX").unwrap_or_default());
@@ -282,12 +297,25 @@ fn start_sidecar(
 /// Creates a socket connection to communicate with the core process.
 fn create_core_process_socket(
     socket_path: &str,
+    auth_token: &str,
 ) -> Result<(SocketSender, EventSocket), CoreProcessCreationError> {
     let max_tries = 20;
     let mut tries = 0;
     loop {
-        match socket_lib::connect(socket_path) {
+        /* The shell has no optional features the core needs to know about. */
+        let options = socket_lib::SocketOptions {
+            auth_token: Some(auth_token.to_string()),
+            /* Opt-in, for reproducing reports with `hopp_core_tests replay`. */
+            record_path: env::var_os("HOPP_IPC_RECORDING").map(PathBuf::from),
+            ..Default::default()
+        };
+        match socket_lib::connect_with_options(socket_path, &[], options) {
             Ok(pair) => return Ok(pair),
+            /* Retrying won't help when the core speaks a different protocol. */
+            Err(e) if e.get_ref().is_some_and(|e| e.is::<HandshakeError>()) => {
+                log::error!("create_core_process_socket: {e}");
+                return Err(CoreProcessCreationError::IncompatibleCore(e.to_string()));
+            }
             Err(_) => {
                 log::debug!("create_core_process_socket: Failed to connect, retrying in 1 second");
                 std::thread::sleep(std::time::Duration::from_secs(1));
@@ -321,6 +349,25 @@ async fn send_ping(sender: SocketSender) {
     log::info!("send_ping: Finished");
 }
 
+/// Lets `hoppctl` find the core, see `socket_lib::discovery`.
+fn publish_core_address(app: &tauri::AppHandle, socket_path: &str, auth_token: &str) {
+    let app_data_dir = match app.path().app_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::error!("publish_core_address: Failed to get app data dir: {e:?}");
+            return;
+        }
+    };
+    let address = socket_lib::discovery::CoreAddress {
+        socket_path: socket_path.to_string(),
+        auth_token: Some(auth_token.to_string()),
+    };
+    let path = app_data_dir.join(socket_lib::discovery::FILE_NAME);
+    if let Err(e) = socket_lib::discovery::write(&path, &address) {
+        log::error!("publish_core_address: Failed to write {}: {e:?}", path.display());
+    }
+}
+
 /// Creates and initializes the core process with socket communication.
 pub fn create_core_process(
     app: &tauri::AppHandle,
@@ -330,9 +377,13 @@ pub fn create_core_process(
     let socket_name = format!("core-socket-{}", create_random_suffix());
     let socket_path = format!("{}/{socket_name}", tmp_dir.display());
 
-    let (rx, core_process) = start_sidecar(app, &socket_path);
+    /* Only we and the core know it, other local processes can't connect. */
+    let auth_token = create_auth_token();
+
+    let (rx, core_process) = start_sidecar(app, &socket_path, &auth_token);
     tauri::async_runtime::spawn(show_stdout(rx, app.clone()));
-    let (sender, event_socket) = create_core_process_socket(&socket_path)?;
+    let (sender, event_socket) = create_core_process_socket(&socket_path, &auth_token)?;
+    publish_core_address(app, &socket_path, &auth_token);
     let ping_sender = sender.clone();
     tauri::async_runtime::spawn(send_ping(ping_sender));
     Ok((
@@ -739,6 +790,15 @@ fn create_random_suffix() -> String {
         .collect()
 }
 
+/// Random token for authenticating local clients, new on every launch.
+pub fn create_auth_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
 pub struct MediaWindowConfig<'a> {
     pub label: &'a str,
     pub title: &'a str,
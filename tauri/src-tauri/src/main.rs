@@ -4,7 +4,8 @@
 use hopp::sounds::{self, SoundConfig};
 use log::LevelFilter;
 use socket_lib::{
-    AudioCaptureMessage, AudioDevice, CameraDevice, DrawingEnabled, Message, ScreenSharePickerMode,
+    AudioCaptureMessage, AudioDevice, CameraDevice, Capability, ControlRole,
+    ControlRoleAssignment, DrawingEnabled, Message, Request, ScreenSharePickerMode,
     ScreenShareResolution, SentryMetadata,
 };
 use std::sync::mpsc as std_mpsc;
@@ -20,9 +21,10 @@ use tauri_plugin_log::{Target, TargetKind};
 
 use hopp::{
     app_state::{AppState, UserSettings},
-    create_core_process, get_log_level, get_log_path, get_sentry_dsn, permissions, ping_frontend,
-    recv_expected_response, setup_start_on_launch, setup_tray_icon, AppData,
+    call_core, create_core_process, get_log_level, get_log_path, get_sentry_dsn, permissions,
+    ping_frontend, setup_start_on_launch, setup_tray_icon, AppData,
 };
+mod control_api;
 mod shortcuts;
 #[cfg(target_os = "macos")]
 use hopp::{disable_app_nap, set_window_corner_radius_and_decorations, CORNER_RADIUS};
@@ -304,6 +306,27 @@ fn get_camera_permission(_app: tauri::AppHandle) -> bool {
     res
 }
 
+#[tauri::command(async)]
+fn get_permission_issues(_app: tauri::AppHandle) -> Vec<permissions::PermissionIssue> {
+    let issues = permissions::issues();
+    log::info!("get_permission_issues: {issues:?}");
+    issues
+}
+
+/// Capabilities the core advertised in the socket handshake.
+#[tauri::command]
+fn get_core_capabilities(app: tauri::AppHandle) -> Vec<Capability> {
+    let data = app.state::<Mutex<AppData>>();
+    let data = data.lock().unwrap();
+    data.sender
+        .peer()
+        .capabilities
+        .iter()
+        .copied()
+        .filter(|capability| *capability != Capability::Unknown)
+        .collect()
+}
+
 #[tauri::command(async)]
 fn skip_tray_notification_selection_window(app: tauri::AppHandle) {
     log::info!("executing skip_tray_notification_selection_window");
@@ -511,22 +534,17 @@ fn call_started(
     // Resolve the audio device name: last used → default → first → ""
     let audio_device_name = {
         let last_used = data.app_state.last_used_mic();
-        let devices: Vec<AudioDevice> = if let Err(e) = data.sender.send(Message::ListAudioDevices)
-        {
-            log::error!("call_started: failed to list audio devices: {e:?}");
-            vec![]
-        } else {
-            match recv_expected_response(&data.event_socket, |msg| match msg {
+        let devices: Vec<AudioDevice> =
+            match call_core(&data.sender, Message::ListAudioDevices, |msg| match msg {
                 Message::AudioDeviceList(d) => Ok(d),
                 other => Err(other),
             }) {
                 Ok(d) => d,
                 Err(e) => {
-                    log::error!("call_started: failed to receive audio device list: {e:?}");
+                    log::error!("call_started: failed to list audio devices: {e}");
                     vec![]
                 }
-            }
-        };
+            };
         if let Some(last) = last_used {
             if devices.iter().any(|d| d.name == last) {
                 last
@@ -548,27 +566,21 @@ fn call_started(
         }
     };
     log::info!("call_started: resolved audio_device_name={audio_device_name:?}");
-    if let Err(e) = data
-        .sender
-        .send(Message::CallStart(socket_lib::CallStartMessage {
-            audio_token: audio_token.clone(),
-            video_token: video_token.clone(),
-            audio_device_name,
-            start_mic_on_call: Some(user_settings.start_mic_on_call),
-            start_camera_on_call: Some(user_settings.start_camera_on_call),
-        }))
-    {
-        log::error!("call_started: failed to send: {e:?}");
-        return Err("Failed to send message to hopp_core".to_string());
-    }
-    let result = match recv_expected_response(&data.event_socket, |msg| match msg {
+    let request = Message::CallStart(socket_lib::CallStartMessage {
+        audio_token: audio_token.clone(),
+        video_token: video_token.clone(),
+        audio_device_name,
+        start_mic_on_call: Some(user_settings.start_mic_on_call),
+        start_camera_on_call: Some(user_settings.start_camera_on_call),
+    });
+    let result = match call_core(&data.sender, request, |msg| match msg {
         Message::CallStartResult(r) => Ok(r),
         other => Err(other),
     }) {
         Ok(result) => result,
         Err(e) => {
-            log::error!("call_started: recv failed: {e:?}");
-            Err("Failed to receive message from hopp_core".to_string())
+            log::error!("call_started: call failed: {e}");
+            Err("Failed to communicate with hopp_core".to_string())
         }
     };
     if result.is_ok() {
@@ -576,6 +588,7 @@ fn call_started(
         data.is_camera_on = false;
         data.is_screensharing = false;
         shortcuts::register_call_shortcuts(&app, resolved_call_shortcuts(&data.app_state));
+        control_api::notify_call_state(&app, &data);
     }
     result
 }
@@ -754,6 +767,7 @@ fn set_is_camera_on(app: tauri::AppHandle, value: bool) {
     let data = app.state::<Mutex<AppData>>();
     let mut data = data.lock().unwrap();
     data.is_camera_on = value;
+    control_api::notify_call_state(&app, &data);
 }
 
 #[tauri::command(async)]
@@ -761,6 +775,7 @@ fn set_is_screensharing(app: tauri::AppHandle, value: bool) {
     let data = app.state::<Mutex<AppData>>();
     let mut data = data.lock().unwrap();
     data.is_screensharing = value;
+    control_api::notify_call_state(&app, &data);
 }
 
 #[tauri::command(async)]
@@ -786,6 +801,31 @@ fn set_telemetry_enabled(app: tauri::AppHandle, enabled: bool) {
     let _ = app.emit("telemetry_enabled_changed", enabled);
 }
 
+#[tauri::command(async)]
+fn set_trusted_controllers(app: tauri::AppHandle, user_ids: Vec<String>) {
+    log::info!("set_trusted_controllers: {}", user_ids.len());
+    let data = app.state::<Mutex<AppData>>();
+    let mut data = data.lock().unwrap();
+    data.app_state
+        .update_user_setting(|s| s.trusted_controllers = user_ids.clone());
+    if let Err(e) = data.sender.send(Message::SetTrustedControllers(user_ids)) {
+        log::error!("set_trusted_controllers: failed to send: {e:?}");
+    }
+}
+
+#[tauri::command(async)]
+fn set_control_role(app: tauri::AppHandle, user_id: String, role: Option<ControlRole>) {
+    log::info!("set_control_role: {user_id} {role:?}");
+    let data = app.state::<Mutex<AppData>>();
+    let data = data.lock().unwrap();
+    if let Err(e) = data
+        .sender
+        .send(Message::SetControlRole(ControlRoleAssignment { user_id, role }))
+    {
+        log::error!("set_control_role: failed to send: {e:?}");
+    }
+}
+
 #[tauri::command(async)]
 fn set_show_dock_icon_in_call(app: tauri::AppHandle, enabled: bool) {
     log::info!("set_show_dock_icon_in_call: {enabled}");
@@ -829,6 +869,7 @@ fn set_remote_control_enabled(app: tauri::AppHandle, enabled: bool) {
     let mut data = data.lock().unwrap();
     data.app_state
         .update_user_setting(|s| s.remote_control_enabled = enabled);
+    control_api::notify_call_state(&app, &data);
     drop(data);
     set_controller_cursor(app, enabled);
 }
@@ -893,6 +934,12 @@ fn set_screen_share_picker_mode(app: tauri::AppHandle, mode: ScreenSharePickerMo
     let mut data = data.lock().unwrap();
     data.app_state
         .update_user_setting(|settings| settings.screen_share_picker_mode = mode);
+    if mode == ScreenSharePickerMode::Window
+        && !data.sender.peer_supports(Capability::WindowPicking)
+    {
+        log::warn!("set_screen_share_picker_mode: core doesn't support window picking");
+        return;
+    }
     if let Err(e) = data.sender.send(Message::SetScreenSharePickerMode(mode)) {
         log::error!("set_screen_share_picker_mode: failed to send: {e:?}");
     }
@@ -902,23 +949,15 @@ fn set_screen_share_picker_mode(app: tauri::AppHandle, mode: ScreenSharePickerMo
 fn start_camera(app: tauri::AppHandle, device_name: Option<String>) -> Result<(), String> {
     let data = app.state::<Mutex<AppData>>();
     let data = data.lock().unwrap();
-    if let Err(e) = data
-        .sender
-        .send(Message::StartCamera(socket_lib::CameraStartMessage {
-            device_name,
-        }))
-    {
-        log::error!("start_camera: failed to send: {e:?}");
-        return Err("Failed to send message to hopp_core".to_string());
-    }
-    match recv_expected_response(&data.event_socket, |msg| match msg {
+    let request = Message::StartCamera(socket_lib::CameraStartMessage { device_name });
+    match call_core(&data.sender, request, |msg| match msg {
         Message::StartCameraResult(r) => Ok(r),
         other => Err(other),
     }) {
         Ok(result) => result,
         Err(e) => {
-            log::error!("start_camera: recv failed: {e:?}");
-            Err("Failed to receive message from hopp_core".to_string())
+            log::error!("start_camera: call failed: {e}");
+            Err("Failed to communicate with hopp_core".to_string())
         }
     }
 }
@@ -963,17 +1002,13 @@ fn close_screenshare_viewer(app: tauri::AppHandle) {
 fn list_microphones(app: tauri::AppHandle) -> Vec<AudioDevice> {
     let data = app.state::<Mutex<AppData>>();
     let data = data.lock().unwrap();
-    if let Err(e) = data.sender.send(Message::ListAudioDevices) {
-        log::error!("list_microphones: failed to send: {e:?}");
-        return vec![];
-    }
-    match recv_expected_response(&data.event_socket, |msg| match msg {
+    match call_core(&data.sender, Message::ListAudioDevices, |msg| match msg {
         Message::AudioDeviceList(d) => Ok(d),
         other => Err(other),
     }) {
         Ok(devices) => devices,
         Err(e) => {
-            log::error!("list_microphones: recv failed: {e:?}");
+            log::error!("list_microphones: call failed: {e}");
             vec![]
         }
     }
@@ -983,21 +1018,13 @@ fn list_microphones(app: tauri::AppHandle) -> Vec<AudioDevice> {
 fn select_microphone(app: tauri::AppHandle, device_name: String) {
     let data = app.state::<Mutex<AppData>>();
     let data = data.lock().unwrap();
-    if let Err(e) = data
-        .sender
-        .send(Message::StartAudioCapture(AudioCaptureMessage {
-            device_name,
-        }))
-    {
-        log::error!("select_microphone: failed to send: {e:?}");
-        return;
-    }
-    match recv_expected_response(&data.event_socket, |msg| match msg {
+    let request = Message::StartAudioCapture(AudioCaptureMessage { device_name });
+    match call_core(&data.sender, request, |msg| match msg {
         Message::StartAudioCaptureResult(r) => Ok(r),
         other => Err(other),
     }) {
         Ok(Err(e)) => log::error!("select_microphone: core failed: {e}"),
-        Err(e) => log::error!("select_microphone: no result: {e:?}"),
+        Err(e) => log::error!("select_microphone: no result: {e}"),
         Ok(Ok(())) => {}
     }
 }
@@ -1006,17 +1033,13 @@ fn select_microphone(app: tauri::AppHandle, device_name: String) {
 fn list_webcams(app: tauri::AppHandle) -> Vec<CameraDevice> {
     let data = app.state::<Mutex<AppData>>();
     let data = data.lock().unwrap();
-    if let Err(e) = data.sender.send(Message::ListCameras) {
-        log::error!("list_webcams: failed to send: {e:?}");
-        return vec![];
-    }
-    match recv_expected_response(&data.event_socket, |msg| match msg {
+    match call_core(&data.sender, Message::ListCameras, |msg| match msg {
         Message::CameraList(d) => Ok(d),
         other => Err(other),
     }) {
         Ok(devices) => devices,
         Err(e) => {
-            log::error!("list_webcams: recv failed: {e:?}");
+            log::error!("list_webcams: call failed: {e}");
             vec![]
         }
     }
@@ -1027,17 +1050,17 @@ fn bring_windows_to_front(app: tauri::AppHandle) -> bool {
     log::info!("bring_windows_to_front");
     let data = app.state::<Mutex<AppData>>();
     let data = data.lock().unwrap();
-    if let Err(e) = data.sender.send(Message::BringWindowsToFront) {
-        log::error!("bring_windows_to_front: failed to send: {e:?}");
-        return false;
-    }
-    match recv_expected_response(&data.event_socket, |msg| match msg {
-        Message::BringWindowsToFrontResult(f) => Ok(f),
-        other => Err(other),
-    }) {
+    match call_core(
+        &data.sender,
+        Message::BringWindowsToFront,
+        |msg| match msg {
+            Message::BringWindowsToFrontResult(f) => Ok(f),
+            other => Err(other),
+        },
+    ) {
         Ok(focused) => focused,
         Err(e) => {
-            log::error!("bring_windows_to_front: recv failed: {e:?}");
+            log::error!("bring_windows_to_front: call failed: {e}");
             false
         }
     }
@@ -1054,9 +1077,10 @@ fn end_call(app: tauri::AppHandle) {
     data.call_active = false;
     data.is_camera_on = false;
     data.is_screensharing = false;
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    data.sleep_prevention.disable();
     #[cfg(target_os = "macos")]
     {
-        data.sleep_prevention.disable();
         let suppress = data.suppress_hide_on_call_end.clone();
         suppress.store(true, Ordering::Relaxed);
         let _ = app.set_activation_policy(tauri::ActivationPolicy::Accessory);
@@ -1071,7 +1095,7 @@ fn end_call(app: tauri::AppHandle) {
 
 #[tauri::command(async)]
 fn toggle_call_sleep_prevention(app: tauri::AppHandle, enabled: bool) {
-    #[cfg(target_os = "macos")]
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
     {
         let data = app.state::<Mutex<AppData>>();
         let mut data = data.lock().unwrap();
@@ -1081,7 +1105,7 @@ fn toggle_call_sleep_prevention(app: tauri::AppHandle, enabled: bool) {
             data.sleep_prevention.disable();
         }
     }
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
     {
         let _ = (app, enabled);
     }
@@ -1099,12 +1123,14 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
                 if let Err(e) = app.emit("core_participants_snapshot", &snapshot) {
                     log::error!("forward_core_events: failed to emit participants snapshot: {e:?}");
                 }
+                control_api::notify(&app, "participants", &snapshot);
             }
             Message::RoleChange(event) => {
                 log::info!("forward_core_events: role change: {event:?}");
                 if let Err(e) = app.emit("core_role_change", &event) {
                     log::error!("forward_core_events: failed to emit role change: {e:?}");
                 }
+                control_api::notify(&app, "role_change", &event);
             }
             Message::CameraFailed(error) => {
                 log::error!("forward_core_events: camera failed: {error}");
@@ -1119,10 +1145,12 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
                 data.call_active = false;
                 data.is_camera_on = false;
                 data.is_screensharing = false;
+                control_api::notify_call_state(&app, &data);
+                #[cfg(any(target_os = "macos", target_os = "linux"))]
+                data.sleep_prevention.disable();
                 #[cfg(target_os = "macos")]
                 {
                     let _ = app.set_activation_policy(tauri::ActivationPolicy::Accessory);
-                    data.sleep_prevention.disable();
                     data.activation_policy_regular = false;
                     let suppress = data.suppress_hide_on_call_end.clone();
                     drop(data);
@@ -1159,15 +1187,6 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
                     );
                 }
             }
-            Message::QueryPreferredCamera => {
-                log::info!("forward_core_events: query preferred camera");
-                let data = app.state::<Mutex<AppData>>();
-                let data = data.lock().unwrap();
-                let preferred = data.app_state.last_used_camera();
-                if let Err(e) = data.sender.send(Message::PreferredCamera(preferred)) {
-                    log::error!("forward_core_events: failed to send preferred camera: {e:?}");
-                }
-            }
             Message::ActiveMicChanged(device_name) => {
                 log::info!("forward_core_events: active mic changed to: {device_name}");
                 let data = app.state::<Mutex<AppData>>();
@@ -1179,6 +1198,7 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
                         "forward_core_events: failed to emit core_active_mic_changed: {e:?}"
                     );
                 }
+                control_api::notify(&app, "active_mic_changed", &device_name);
             }
             Message::ActiveCameraChanged(device_name) => {
                 log::info!("forward_core_events: active camera changed to: {device_name}");
@@ -1191,6 +1211,7 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
                         "forward_core_events: failed to emit core_active_camera_changed: {e:?}"
                     );
                 }
+                control_api::notify(&app, "active_camera_changed", &device_name);
             }
             Message::MicrophoneAudioLevel(level) => {
                 if let Err(e) = app.emit("core_mic_audio_level", &level) {
@@ -1238,6 +1259,29 @@ fn forward_core_events(events_rx: std_mpsc::Receiver<Message>, app: tauri::AppHa
     log::info!("forward_core_events: event forwarding thread exiting");
 }
 
+fn answer_core_requests(requests_rx: std_mpsc::Receiver<Request>, app: tauri::AppHandle) {
+    for request in requests_rx.iter() {
+        match request.message {
+            Message::QueryPreferredCamera => {
+                log::info!("answer_core_requests: query preferred camera");
+                let data = app.state::<Mutex<AppData>>();
+                let data = data.lock().unwrap();
+                let preferred = data.app_state.last_used_camera();
+                if let Err(e) = data
+                    .sender
+                    .respond(request.id, Message::PreferredCamera(preferred))
+                {
+                    log::error!("answer_core_requests: failed to send preferred camera: {e:?}");
+                }
+            }
+            other => {
+                log::error!("answer_core_requests: unhandled request: {other:?}");
+            }
+        }
+    }
+    log::info!("answer_core_requests: request thread exiting");
+}
+
 fn main() {
     let _guard = sentry_utils::init_sentry("Tauri backend".to_string(), Some(get_sentry_dsn()));
 
@@ -1352,6 +1396,7 @@ fn main() {
                 create_core_process(app.handle()).expect("Failed to create core process");
 
             let core_events_rx = event_socket.take_events();
+            let core_requests_rx = event_socket.take_requests();
 
             let app_state = AppState::new(&app_data_dir);
             let noise_cancellation_enabled = app_state.user_settings().noise_cancellation_enabled;
@@ -1381,6 +1426,10 @@ fn main() {
                     log::error!("Failed to send initial telemetry_enabled: {e:?}");
                 }
             }
+            let trusted_controllers = app_state.user_settings().trusted_controllers;
+            if let Err(e) = sender.send(Message::SetTrustedControllers(trusted_controllers)) {
+                log::error!("Failed to send initial trusted_controllers: {e:?}");
+            }
             let data = Mutex::new(AppData::new(
                 sender,
                 event_socket,
@@ -1390,11 +1439,20 @@ fn main() {
             ));
             app.manage(data);
 
+            /* Local JSON-RPC endpoint for editor integrations, see control_api.rs. */
+            if let Err(e) = control_api::start(app.handle(), &app_data_dir) {
+                log::error!("Failed to start the control API: {e:?}");
+            }
+
             // Background thread to forward core events to the frontend
             let event_app_handle = app.handle().clone();
             std::thread::spawn(move || {
                 forward_core_events(core_events_rx, event_app_handle);
             });
+            let request_app_handle = app.handle().clone();
+            std::thread::spawn(move || {
+                answer_core_requests(core_requests_rx, request_app_handle);
+            });
 
             std::thread::spawn(|| {
                 sentry_utils::upload_latest_crash();
@@ -1663,6 +1721,8 @@ fn main() {
             set_livekit_url,
             get_livekit_url,
             get_camera_permission,
+            get_permission_issues,
+            get_core_capabilities,
             open_camera_settings,
             set_sentry_metadata,
             call_started,
@@ -1674,6 +1734,8 @@ fn main() {
             get_user_settings,
             set_call_feedback_popup,
             set_telemetry_enabled,
+            set_trusted_controllers,
+            set_control_role,
             set_show_dock_icon_in_call,
             set_auto_update_enabled,
             set_start_camera_on_call,
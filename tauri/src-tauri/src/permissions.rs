@@ -7,18 +7,40 @@
 //! # Platform Support
 //!
 //! - **macOS**: Uses native system APIs (Core Graphics, Accessibility, AVFoundation)
+//! - **Linux**: Probes device nodes, the audio server, desktop portals and XTest
 //! - **Other platforms**: Default implementation that returns `true` for all permissions
 // Platform-specific modules
 #[cfg(target_os = "macos")]
 #[path = "permissions/macos.rs"]
 mod platform;
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(target_os = "linux")]
+#[path = "permissions/linux.rs"]
+mod platform;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
 #[path = "permissions/default.rs"]
 mod platform;
 
 pub use platform::PlatformPermissions;
 
+/// The permissions the app checks, named after the checks below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Screenshare,
+    Accessibility,
+    Microphone,
+    Camera,
+}
+
+/// A permission that isn't granted, with a message telling the user how to fix it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PermissionIssue {
+    pub permission: Permission,
+    pub message: String,
+}
+
 /// Trait defining the permission checking interface for all platforms.
 ///
 /// Platform-specific modules must implement this trait to provide actual
@@ -44,6 +66,13 @@ trait PermissionsTrait {
 
     /// Requests microphone access permission.
     fn request_microphone();
+
+    /// Explains every permission that isn't granted.
+    ///
+    /// Platforms whose checks can't tell why a permission is missing report nothing.
+    fn issues() -> Vec<PermissionIssue> {
+        Vec::new()
+    }
 }
 
 /// Checks if any of the required permissions are not granted.
@@ -69,6 +98,7 @@ pub fn has_ungranted_permissions() -> bool {
 /// # Platform Implementation
 ///
 /// - **macOS**: Uses Core Graphics `ScreenCaptureAccess::preflight()`
+/// - **Linux**: On Wayland, checks that the ScreenCast portal is available
 /// - **Others**: Returns `true` (no restriction)
 pub fn screenshare() -> bool {
     PlatformPermissions::screenshare()
@@ -92,6 +122,7 @@ pub fn request_screenshare() -> bool {
 /// # Platform Implementation
 ///
 /// - **macOS**: Uses Accessibility API `AXIsProcessTrusted()`
+/// - **Linux**: Checks for XTest on X11 and the RemoteDesktop portal on Wayland
 /// - **Others**: Returns `true` (no restriction)
 pub fn accessibility() -> bool {
     PlatformPermissions::accessibility()
@@ -104,6 +135,7 @@ pub fn accessibility() -> bool {
 /// # Platform Implementation
 ///
 /// - **macOS**: Uses AVFoundation `AVCaptureDevice::authorization_status_for_media_type()`
+/// - **Linux**: Checks that a PulseAudio or PipeWire server accepts connections
 /// - **Others**: Returns `true` (no restriction)
 pub fn microphone() -> bool {
     PlatformPermissions::microphone()
@@ -116,6 +148,7 @@ pub fn microphone() -> bool {
 /// # Platform Implementation
 ///
 /// - **macOS**: Uses AVFoundation `AVCaptureDevice::authorization_status_for_media_type()`
+/// - **Linux**: Checks that a `/dev/video*` device can be opened
 /// - **Others**: Returns `true` (no restriction)
 pub fn camera() -> bool {
     PlatformPermissions::camera()
@@ -140,3 +173,13 @@ pub fn request_camera() {
 pub fn request_microphone() {
     PlatformPermissions::request_microphone()
 }
+
+/// Returns an explanation for every permission that isn't granted.
+///
+/// # Platform Implementation
+///
+/// - **Linux**: Reports missing camera devices, audio server, portals or XTest
+/// - **Others**: Returns an empty list
+pub fn issues() -> Vec<PermissionIssue> {
+    PlatformPermissions::issues()
+}
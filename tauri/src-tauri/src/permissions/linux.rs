@@ -0,0 +1,247 @@
+//! Linux has no permission prompts, access is decided by device node
+//! permissions, running services and what the session exposes. Each check
+//! reports why a capability is unavailable so the frontend can tell the user
+//! what to fix.
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use x11rb::connection::RequestConnection;
+use zbus::blocking::{Connection, Proxy};
+
+use super::{Permission, PermissionIssue, PermissionsTrait};
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREENCAST_INTERFACE: &str = "org.freedesktop.portal.ScreenCast";
+const REMOTE_DESKTOP_INTERFACE: &str = "org.freedesktop.portal.RemoteDesktop";
+
+fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Checks that at least one V4L2 device node exists and can be opened.
+fn check_camera(dev: &Path) -> Result<(), String> {
+    let mut devices: Vec<PathBuf> = std::fs::read_dir(dev)
+        .map_err(|e| format!("Failed to list {}: {e}", dev.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("video"))
+        })
+        .collect();
+    devices.sort();
+
+    if devices.is_empty() {
+        return Err("No camera was found, no /dev/video* device exists.".to_string());
+    }
+    for device in &devices {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(device)
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => log::warn!("check_camera: {} is not accessible: {e}", device.display()),
+        }
+    }
+    Err(format!(
+        "Camera devices can't be opened ({}). Add your user to the \"video\" group and log in again.",
+        devices
+            .iter()
+            .map(|device| device.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// Checks that a PulseAudio server, or PipeWire with its pulse compatibility
+/// layer, accepts connections.
+fn check_audio_server(
+    runtime_dir: Option<&Path>,
+    pulse_server: Option<&str>,
+) -> Result<(), String> {
+    let mut sockets = Vec::new();
+    if let Some(server) = pulse_server {
+        /* Only local sockets are probed, "tcp:host" servers are assumed reachable. */
+        match server.strip_prefix("unix:") {
+            Some(path) => sockets.push(PathBuf::from(path)),
+            None if server.starts_with('/') => sockets.push(PathBuf::from(server)),
+            None => return Ok(()),
+        }
+    }
+    if let Some(runtime_dir) = runtime_dir {
+        sockets.push(runtime_dir.join("pulse/native"));
+        sockets.push(runtime_dir.join("pipewire-0"));
+    }
+
+    for socket in &sockets {
+        if UnixStream::connect(socket).is_ok() {
+            return Ok(());
+        }
+    }
+    Err("No audio server is running. Start PipeWire (with pipewire-pulse) or PulseAudio to use the microphone.".to_string())
+}
+
+/// Checks that the desktop portal implements `interface`, its `version`
+/// property is only readable when it does.
+fn check_portal_interface(interface: &str, feature: &str) -> Result<(), String> {
+    let missing = |e: zbus::Error| {
+        log::warn!("check_portal_interface: {interface}: {e}");
+        format!(
+            "{feature} needs xdg-desktop-portal with {interface} support. Install the portal backend for your desktop (e.g. xdg-desktop-portal-gnome or xdg-desktop-portal-kde)."
+        )
+    };
+    let connection = Connection::session().map_err(missing)?;
+    let proxy =
+        Proxy::new(&connection, PORTAL_DESTINATION, PORTAL_PATH, interface).map_err(missing)?;
+    proxy.get_property::<u32>("version").map_err(missing)?;
+    Ok(())
+}
+
+fn check_xtest() -> Result<(), String> {
+    let (conn, _) = x11rb::connect(None)
+        .map_err(|e| format!("Remote control can't connect to the X server: {e}"))?;
+    match conn.extension_information(x11rb::protocol::xtest::X11_EXTENSION_NAME) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(
+            "Remote control needs the XTEST extension, which is disabled on this X server."
+                .to_string(),
+        ),
+        Err(e) => Err(format!("Failed to query the X server extensions: {e}")),
+    }
+}
+
+fn check_screenshare() -> Result<(), String> {
+    if is_wayland_session() {
+        check_portal_interface(SCREENCAST_INTERFACE, "Screen sharing on Wayland")
+    } else {
+        Ok(())
+    }
+}
+
+fn check_remote_control() -> Result<(), String> {
+    if is_wayland_session() {
+        check_portal_interface(REMOTE_DESKTOP_INTERFACE, "Remote control on Wayland")
+    } else {
+        check_xtest()
+    }
+}
+
+fn check_microphone() -> Result<(), String> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let pulse_server = std::env::var("PULSE_SERVER").ok();
+    check_audio_server(runtime_dir.as_deref(), pulse_server.as_deref())
+}
+
+fn log_result(name: &str, result: &Result<(), String>) -> bool {
+    match result {
+        Ok(()) => log::info!("Linux {name} permission check: ok"),
+        Err(e) => log::warn!("Linux {name} permission check: {e}"),
+    }
+    result.is_ok()
+}
+
+pub struct PlatformPermissions;
+
+impl PermissionsTrait for PlatformPermissions {
+    fn screenshare() -> bool {
+        log_result("screenshare", &check_screenshare())
+    }
+
+    fn request_screenshare() -> bool {
+        /* The portal asks for consent when a capture starts, there's nothing to request up front. */
+        Self::screenshare()
+    }
+
+    fn accessibility() -> bool {
+        log_result("accessibility", &check_remote_control())
+    }
+
+    fn microphone() -> bool {
+        log_result("microphone", &check_microphone())
+    }
+
+    fn camera() -> bool {
+        log_result("camera", &check_camera(Path::new("/dev")))
+    }
+
+    fn request_camera() {
+        log::info!("Linux camera permission request, nothing to request");
+    }
+
+    fn request_microphone() {
+        log::info!("Linux microphone permission request, nothing to request");
+    }
+
+    fn issues() -> Vec<PermissionIssue> {
+        [
+            (Permission::Screenshare, check_screenshare()),
+            (Permission::Accessibility, check_remote_control()),
+            (Permission::Microphone, check_microphone()),
+            (Permission::Camera, check_camera(Path::new("/dev"))),
+        ]
+        .into_iter()
+        .filter_map(|(permission, result)| {
+            result.err().map(|message| PermissionIssue {
+                permission,
+                message,
+            })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("hopp-permissions-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_audio_server_reachable_through_pulse_socket() {
+        let runtime_dir = temp_dir("pulse");
+        assert!(check_audio_server(Some(&runtime_dir), None).is_err());
+
+        std::fs::create_dir_all(runtime_dir.join("pulse")).unwrap();
+        let _listener = UnixListener::bind(runtime_dir.join("pulse/native")).unwrap();
+        assert!(check_audio_server(Some(&runtime_dir), None).is_ok());
+
+        std::fs::remove_dir_all(&runtime_dir).unwrap();
+    }
+
+    #[test]
+    fn test_audio_server_from_pulse_server_env() {
+        let runtime_dir = temp_dir("pulse-server");
+        let socket = runtime_dir.join("custom");
+        let server = format!("unix:{}", socket.display());
+        assert!(check_audio_server(None, Some(&server)).is_err());
+
+        let _listener = UnixListener::bind(&socket).unwrap();
+        assert!(check_audio_server(None, Some(&server)).is_ok());
+        assert!(check_audio_server(None, Some("tcp:192.168.1.2")).is_ok());
+
+        std::fs::remove_dir_all(&runtime_dir).unwrap();
+    }
+
+    #[test]
+    fn test_camera_missing_devices() {
+        let dev = temp_dir("dev");
+        let error = check_camera(&dev).unwrap_err();
+        assert!(error.contains("No camera"));
+
+        std::fs::write(dev.join("video0"), b"").unwrap();
+        assert!(check_camera(&dev).is_ok());
+
+        std::fs::remove_dir_all(&dev).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedFd;
+
+const APP_NAME: &str = "Hopp";
+const REASON: &str = "Hopp pairing call in progress";
+
+const SCREENSAVER_DESTINATION: &str = "org.freedesktop.ScreenSaver";
+const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+const SCREENSAVER_INTERFACE: &str = "org.freedesktop.ScreenSaver";
+
+const LOGIND_DESTINATION: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+/// Manages Linux sleep prevention during active calls.
+///
+/// When enabled, takes an `org.freedesktop.ScreenSaver` inhibitor on the
+/// session bus, which keeps the display from blanking, and a logind `idle`
+/// inhibitor on the system bus, which keeps the machine from suspending on
+/// idle. Either may be missing depending on the desktop, each one is taken
+/// independently.
+///
+/// Both inhibitors are tied to the bus connection, so they are also released
+/// by the bus if the app exits without disabling them.
+#[derive(Default)]
+pub struct SleepPrevention {
+    session_bus: Option<Connection>,
+    system_bus: Option<Connection>,
+    connected: bool,
+    screensaver_cookie: Option<u32>,
+    logind_inhibitor: Option<OwnedFd>,
+}
+
+impl SleepPrevention {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the given connections instead of the user's session and system buses.
+    pub fn with_buses(session_bus: Option<Connection>, system_bus: Option<Connection>) -> Self {
+        Self {
+            session_bus,
+            system_bus,
+            connected: true,
+            screensaver_cookie: None,
+            logind_inhibitor: None,
+        }
+    }
+
+    /// The buses are connected on first use, as most sessions never start a call.
+    fn connect(&mut self) {
+        if self.connected {
+            return;
+        }
+        self.connected = true;
+        self.session_bus = Connection::session()
+            .map_err(|e| log::warn!("sleep_prevention: no session bus: {e}"))
+            .ok();
+        self.system_bus = Connection::system()
+            .map_err(|e| log::warn!("sleep_prevention: no system bus: {e}"))
+            .ok();
+    }
+
+    fn screensaver_proxy(&self) -> Option<Proxy<'_>> {
+        let bus = self.session_bus.as_ref()?;
+        Proxy::new(
+            bus,
+            SCREENSAVER_DESTINATION,
+            SCREENSAVER_PATH,
+            SCREENSAVER_INTERFACE,
+        )
+        .map_err(|e| log::error!("sleep_prevention: ScreenSaver proxy failed: {e}"))
+        .ok()
+    }
+
+    fn logind_proxy(&self) -> Option<Proxy<'_>> {
+        let bus = self.system_bus.as_ref()?;
+        Proxy::new(bus, LOGIND_DESTINATION, LOGIND_PATH, LOGIND_INTERFACE)
+            .map_err(|e| log::error!("sleep_prevention: logind proxy failed: {e}"))
+            .ok()
+    }
+
+    /// Prevents display blanking and idle suspend. Idempotent: repeated calls are no-ops.
+    pub fn enable(&mut self) {
+        if self.is_enabled() {
+            log::info!("sleep_prevention: already enabled, skipping");
+            return;
+        }
+        self.connect();
+
+        let screensaver_cookie = self.screensaver_proxy().and_then(|proxy| {
+            proxy
+                .call::<_, _, u32>("Inhibit", &(APP_NAME, REASON))
+                .map_err(|e| log::warn!("sleep_prevention: ScreenSaver.Inhibit failed: {e}"))
+                .ok()
+        });
+        self.screensaver_cookie = screensaver_cookie;
+
+        let logind_inhibitor = self.logind_proxy().and_then(|proxy| {
+            proxy
+                .call::<_, _, OwnedFd>("Inhibit", &("idle", APP_NAME, REASON, "block"))
+                .map_err(|e| log::warn!("sleep_prevention: logind Inhibit failed: {e}"))
+                .ok()
+        });
+        self.logind_inhibitor = logind_inhibitor;
+
+        if self.is_enabled() {
+            log::info!(
+                "sleep_prevention: enabled — screensaver: {}, logind: {}",
+                self.screensaver_cookie.is_some(),
+                self.logind_inhibitor.is_some()
+            );
+        } else {
+            log::error!("sleep_prevention: no inhibitor could be taken");
+        }
+    }
+
+    /// Releases the inhibitors. Idempotent: safe to call when already disabled.
+    pub fn disable(&mut self) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        if let Some(cookie) = self.screensaver_cookie.take() {
+            if let Some(proxy) = self.screensaver_proxy() {
+                if let Err(e) = proxy.call::<_, _, ()>("UnInhibit", &(cookie,)) {
+                    log::error!("sleep_prevention: ScreenSaver.UnInhibit failed: {e}");
+                }
+            }
+        }
+        /* logind releases the inhibitor once every copy of the fd is closed. */
+        self.logind_inhibitor = None;
+
+        log::info!("sleep_prevention: disabled — normal sleep behavior restored");
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.screensaver_cookie.is_some() || self.logind_inhibitor.is_some()
+    }
+}
+
+impl Drop for SleepPrevention {
+    fn drop(&mut self) {
+        self.disable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+
+    /// Cookies and application names of the inhibitors currently held.
+    type Inhibitors = Arc<Mutex<Vec<(u32, String)>>>;
+
+    /// Stand-in for the desktop's screensaver service, records the active cookies.
+    struct MockScreenSaver {
+        next_cookie: u32,
+        active: Inhibitors,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.ScreenSaver")]
+    impl MockScreenSaver {
+        fn inhibit(&mut self, application_name: &str, _reason: &str) -> u32 {
+            self.next_cookie += 1;
+            self.active
+                .lock()
+                .unwrap()
+                .push((self.next_cookie, application_name.to_string()));
+            self.next_cookie
+        }
+
+        fn un_inhibit(&mut self, cookie: u32) {
+            self.active.lock().unwrap().retain(|(c, _)| *c != cookie);
+        }
+    }
+
+    /// Connects a client to a peer-to-peer bus serving `MockScreenSaver`.
+    #[allow(deprecated)] // `unix_stream` only goes away on non-unix tokio builds
+    fn mock_session_bus() -> (Connection, Connection, Inhibitors) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let active = Arc::new(Mutex::new(Vec::new()));
+        let screensaver = MockScreenSaver {
+            next_cookie: 0,
+            active: active.clone(),
+        };
+
+        /* Both ends authenticate during build, so they have to be built concurrently. */
+        let server = std::thread::spawn(move || {
+            zbus::blocking::connection::Builder::unix_stream(server_stream)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(SCREENSAVER_PATH, screensaver)
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let client = zbus::blocking::connection::Builder::unix_stream(client_stream)
+            .p2p()
+            .build()
+            .unwrap();
+        (server.join().unwrap(), client, active)
+    }
+
+    #[test]
+    fn test_enable_takes_and_disable_releases_inhibitor() {
+        let (_server, client, active) = mock_session_bus();
+        let mut sleep_prevention = SleepPrevention::with_buses(Some(client), None);
+
+        sleep_prevention.enable();
+        assert!(sleep_prevention.is_enabled());
+        assert_eq!(active.lock().unwrap().len(), 1);
+        assert_eq!(active.lock().unwrap()[0].1, APP_NAME);
+
+        /* A second enable must not stack another inhibitor. */
+        sleep_prevention.enable();
+        assert_eq!(active.lock().unwrap().len(), 1);
+
+        sleep_prevention.disable();
+        assert!(!sleep_prevention.is_enabled());
+        assert!(active.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_drop_releases_inhibitor() {
+        let (_server, client, active) = mock_session_bus();
+        let mut sleep_prevention = SleepPrevention::with_buses(Some(client), None);
+
+        sleep_prevention.enable();
+        assert_eq!(active.lock().unwrap().len(), 1);
+
+        drop(sleep_prevention);
+        assert!(active.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_buses_are_tolerated() {
+        let mut sleep_prevention = SleepPrevention::with_buses(None, None);
+        sleep_prevention.enable();
+        assert!(!sleep_prevention.is_enabled());
+        sleep_prevention.disable();
+    }
+}
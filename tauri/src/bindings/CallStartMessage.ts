@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CallStartMessage = { audio_token: string, video_token: string, audio_device_name: string, start_mic_on_call: boolean | null, start_camera_on_call: boolean | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Optional features a peer supports, exchanged in the `Hello` so commands
+ * for a missing feature aren't sent at all.
+ */
+export type Capability = "WindowPicking" | "NoiseCancellation" | "Camera" | "RemoteControl" | "MessagePack";
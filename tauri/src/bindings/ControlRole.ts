@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What a participant may do on the sharer's screen, each role allows
+ * everything the ones before it do.
+ */
+export type ControlRole = "ViewOnly" | "Pointer" | "Draw" | "Full";
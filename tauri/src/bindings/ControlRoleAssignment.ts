@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ControlRole } from "./ControlRole";
+
+/**
+ * The sharer assigns `role` to a user, `None` puts them back on the default.
+ */
+export type ControlRoleAssignment = { user_id: string, role: ControlRole | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CoreParticipantState = { identity: string, name: string, connected: boolean, muted: boolean, has_camera: boolean, is_screensharing: boolean, 
+/**
+ * The participant's build speaks another in-room protocol version, so
+ * features it doesn't support are off for everyone.
+ */
+protocol_mismatch: boolean, };
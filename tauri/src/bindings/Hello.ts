@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Capability } from "./Capability";
+import type { Topic } from "./Topic";
+
+/**
+ * First message sent in both directions on a new connection.
+ */
+export type Hello = { protocol_version: number, capabilities: Array<Capability>, 
+/**
+ * The client's copy of `SocketOptions::auth_token`, never kept after
+ * the handshake.
+ */
+auth_token?: string, 
+/**
+ * The topics a client wants broadcast to it, all of them if `None`.
+ */
+subscriptions?: Array<Topic>, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioCaptureMessage } from "./AudioCaptureMessage";
+import type { AudioDevice } from "./AudioDevice";
+import type { CallStartMessage } from "./CallStartMessage";
+import type { CameraDevice } from "./CameraDevice";
+import type { CameraStartMessage } from "./CameraStartMessage";
+import type { ControlRoleAssignment } from "./ControlRoleAssignment";
+import type { CoreParticipantState } from "./CoreParticipantState";
+import type { CoreRoleEvent } from "./CoreRoleEvent";
+import type { DrawingEnabled } from "./DrawingEnabled";
+import type { Hello } from "./Hello";
+import type { ScreenShareMessage } from "./ScreenShareMessage";
+import type { ScreenSharePickerMode } from "./ScreenSharePickerMode";
+import type { ScreenShareResolution } from "./ScreenShareResolution";
+import type { SentryMetadata } from "./SentryMetadata";
+import type { StoredMode } from "./StoredMode";
+
+export type Message = { "Hello": Hello } | "GetAvailableContent" | { "CallStart": CallStartMessage } | { "CallStartResult": { Ok : null } | { Err : string } } | "CallEnd" | { "StartScreenShare": ScreenShareMessage } | { "StartScreenShareResult": { Ok : null } | { Err : string } } | "StopScreenshare" | "Ping" | { "ControllerCursorEnabled": boolean } | { "LivekitServerUrl": string } | { "SentryMetadata": SentryMetadata } | { "DrawingEnabled": DrawingEnabled } | "ListAudioDevices" | { "AudioDeviceList": Array<AudioDevice> } | { "StartAudioCapture": AudioCaptureMessage } | { "StartAudioCaptureResult": { Ok : null } | { Err : string } } | "StopAudioCapture" | "MuteAudio" | "UnmuteAudio" | "ListCameras" | { "CameraList": Array<CameraDevice> } | { "StartCamera": CameraStartMessage } | { "StartCameraResult": { Ok : null } | { Err : string } } | "StopCamera" | { "CameraFailed": string } | "OpenCamera" | "OpenScreensharing" | "ToggleMic" | "OpenScreenShareWindow" | "CloseScreenShareWindow" | "OpenStatsWindow" | "BringWindowsToFront" | { "BringWindowsToFrontResult": boolean } | "QueryPreferredCamera" | { "PreferredCamera": string | null } | { "ParticipantsSnapshot": Array<CoreParticipantState> } | { "RoleChange": CoreRoleEvent } | "CallEnded" | { "RoomConnectionFailed": string } | { "ControllerDrawPersistChanged": boolean } | { "SharerDrawPersistChanged": boolean } | { "LastModeChanged": StoredMode } | { "ActiveMicChanged": string } | { "ActiveCameraChanged": string } | "DrawingDisabled" | "ExitRequested" | { "SetNoiseCancellation": boolean } | { "SetScreenShareResolution": ScreenShareResolution } | { "SetScreenSharePickerMode": ScreenSharePickerMode } | { "SetTelemetryEnabled": boolean } | { "SetTrustedControllers": Array<string> } | { "SetControlRole": ControlRoleAssignment } | { "MicrophoneAudioLevel": number };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Content } from "./Content";
+import type { Extent } from "./Extent";
+
+export type ScreenShareMessage = { content: Content, resolution: Extent, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Represents the user's preferred interaction mode for screen sharing sessions.
+ * This is stored persistently and restored when the user joins a new session.
+ */
+export type StoredMode = { "type": "RemoteControl" } | { "type": "ClickAnimation" } | { "type": "Draw", 
+/**
+ * If true, drawings persist until manually cleared; if false, they auto-expire
+ */
+permanent: boolean, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Groups of events the core broadcasts, a client lists the ones it wants in
+ * `Hello::subscriptions`. Events outside every topic, like `CallEnded`, go to
+ * all clients.
+ */
+export type Topic = "Participants" | "AudioLevel" | "Devices" | "Preferences";
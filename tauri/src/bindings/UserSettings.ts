@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ScreenSharePickerMode } from "./ScreenSharePickerMode";
+import type { ScreenShareResolution } from "./ScreenShareResolution";
+
+/**
+ * User-facing settings exposed in the Settings window.
+ * All fields are non-optional with sensible defaults.
+ */
+export type UserSettings = { call_feedback_popup: boolean, show_dock_icon_in_call: boolean, start_camera_on_call: boolean, start_mic_on_call: boolean, remote_control_enabled: boolean, noise_cancellation_enabled: boolean, screen_share_resolution: ScreenShareResolution, screen_share_picker_mode: ScreenSharePickerMode, hopp_server_url: string | null, shortcut_toggle_mic: string | null, shortcut_toggle_camera: string | null, shortcut_toggle_screenshare: string | null, shortcut_end_call: string | null, telemetry_enabled: boolean, auto_update_enabled: boolean, 
+/**
+ * User ids of teammates granted remote control without a prompt.
+ */
+trusted_controllers: Array<string>, };
@@ -1,5 +1,5 @@
 import { formatDistanceToNow } from "date-fns";
-import { LuMicOff, LuVideo, LuVideoOff, LuScreenShare, LuScreenShareOff } from "react-icons/lu";
+import { LuMicOff, LuVideo, LuVideoOff, LuScreenShare, LuScreenShareOff, LuTriangleAlert } from "react-icons/lu";
 import { PiScribbleLoopBold, PiCursorBold } from "react-icons/pi";
 import useStore, { CallState, ParticipantRole } from "@/store/store";
 import { Separator } from "@/components/ui/separator";
@@ -309,6 +309,7 @@ function CallParticipants() {
           user,
           isLocal: true,
           isMicrophoneEnabled: callTokens?.hasAudioEnabled ?? true,
+          protocolMismatch: false,
         }
       : null;
 
@@ -327,6 +328,7 @@ function CallParticipants() {
           user: findUser(participantId),
           isLocal: false,
           isMicrophoneEnabled: !p.muted,
+          protocolMismatch: p.protocol_mismatch,
         };
       });
 
@@ -367,6 +369,12 @@ function CallParticipants() {
                       <span className="mt-0.5">Muted</span>
                     </span>
                   )}
+                  {participant.protocolMismatch && (
+                    <span className="flex items-center gap-1 text-xs font-medium text-amber-600">
+                      <LuTriangleAlert className="size-3" />
+                      <span className="mt-0.5">Different Hopp version, some features are off</span>
+                    </span>
+                  )}
                 </div>
               </>
             : <>
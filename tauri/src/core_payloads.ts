@@ -1,114 +1,39 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { AudioDevice } from "./bindings/AudioDevice";
+import type { CameraDevice } from "./bindings/CameraDevice";
+import type { Capability } from "./bindings/Capability";
+import type { ControlRole } from "./bindings/ControlRole";
+import type { ScreenSharePickerMode } from "./bindings/ScreenSharePickerMode";
+import type { ScreenShareResolution } from "./bindings/ScreenShareResolution";
+import type { UserSettings } from "./bindings/UserSettings";
 
 /**
- * Socket lib types are defined in (core/socket_lib/src/lib.rs).
- *
- * At some point we could use:
- * https://github.com/Aleph-Alpha/ts-rs
- *
- * But skipping for now, as it would need quite
- * many changes to the socket code, and tweaking.
+ * Socket lib types are generated from core/socket_lib/src/lib.rs and
+ * UserSettings from tauri/src-tauri/src/app_state.rs with ts-rs, run
+ * `task bindings` after changing them.
  */
-export interface WindowFrameMessage {
-  origin_x: number;
-  origin_y: number;
-  width: number;
-  height: number;
-}
-
-export interface CursorPositionMessage {
-  x: number;
-  y: number;
-}
-
-export interface MouseClickMessage {
-  x: number;
-  y: number;
-  button: number;
-  clicks: number;
-  shift_key: boolean;
-}
-
-export interface ScrollMessage {
-  x: number;
-  y: number;
-}
-
-export interface KeystrokeMessage {
-  key: string;
-  meta: boolean;
-  shift: boolean;
-  ctrl: boolean;
-  alt: boolean;
-  down: boolean;
-}
-
-export interface CallStartMessage {
-  token: string;
-}
-
-export interface SentryMetadata {
-  user_id: string;
-  app_version: string;
-}
-
-export interface DrawingEnabled {
-  permanent: boolean;
-}
-
-export interface AudioDevice {
-  name: string;
-  default: boolean;
-}
-
-export interface AudioCaptureMessage {
-  device_name: string;
-}
-
-export interface CameraDevice {
-  name: string;
-  id: string;
-  default: boolean;
-}
-
-export interface CameraStartMessage {
-  device_name: string;
-}
-
-export interface CoreParticipantState {
-  identity: string;
-  name: string;
-  connected: boolean;
-  muted: boolean;
-  has_camera: boolean;
-  is_screensharing: boolean;
-}
-
-export type ScreenShareResolution = "P1080" | "P1440" | "P4K";
-export type ScreenSharePickerMode = "Screen" | "Window";
-
-export interface UserSettings {
-  call_feedback_popup: boolean;
-  show_dock_icon_in_call: boolean;
-  start_camera_on_call: boolean;
-  start_mic_on_call: boolean;
-  remote_control_enabled: boolean;
-  noise_cancellation_enabled: boolean;
-  screen_share_resolution: ScreenShareResolution;
-  screen_share_picker_mode: ScreenSharePickerMode;
-  hopp_server_url: string | null;
-  shortcut_toggle_mic: string;
-  shortcut_toggle_camera: string;
-  shortcut_toggle_screenshare: string;
-  shortcut_end_call: string;
-  telemetry_enabled: boolean;
-  auto_update_enabled: boolean;
-}
-
-export type CoreRoleChange = "Sharer" | "Controller" | "None";
-
-export interface CoreRoleEvent {
-  role: CoreRoleChange;
+export type { AudioCaptureMessage } from "./bindings/AudioCaptureMessage";
+export type { AudioDevice } from "./bindings/AudioDevice";
+export type { CallStartMessage } from "./bindings/CallStartMessage";
+export type { CameraDevice } from "./bindings/CameraDevice";
+export type { CameraStartMessage } from "./bindings/CameraStartMessage";
+export type { Capability } from "./bindings/Capability";
+export type { CoreParticipantState } from "./bindings/CoreParticipantState";
+export type { CoreRoleChange } from "./bindings/CoreRoleChange";
+export type { CoreRoleEvent } from "./bindings/CoreRoleEvent";
+export type { DrawingEnabled } from "./bindings/DrawingEnabled";
+export type { Message } from "./bindings/Message";
+export type { ScreenSharePickerMode } from "./bindings/ScreenSharePickerMode";
+export type { ScreenShareResolution } from "./bindings/ScreenShareResolution";
+export type { SentryMetadata } from "./bindings/SentryMetadata";
+export type { StoredMode } from "./bindings/StoredMode";
+export type { UserSettings } from "./bindings/UserSettings";
+
+export type Permission = "screenshare" | "accessibility" | "microphone" | "camera";
+
+export interface PermissionIssue {
+  permission: Permission;
+  message: string;
 }
 
 /**
@@ -132,6 +57,7 @@ export interface CommandMap {
 
   // Core process
   reset_core_process: { args: void; return: void };
+  get_core_capabilities: { args: void; return: Capability[] };
 
   // Logs
   get_logs: { args: void; return: string };
@@ -154,6 +80,7 @@ export interface CommandMap {
   get_microphone_permission: { args: void; return: boolean };
   get_screenshare_permission: { args: void; return: boolean };
   get_camera_permission: { args: void; return: boolean };
+  get_permission_issues: { args: void; return: PermissionIssue[] };
   skip_tray_notification_selection_window: { args: void; return: void };
 
   // Preferences
@@ -194,11 +121,13 @@ export interface CommandMap {
   get_user_settings: { args: void; return: UserSettings };
   set_call_feedback_popup: { args: { enabled: boolean }; return: void };
   set_telemetry_enabled: { args: { enabled: boolean }; return: void };
+  set_trusted_controllers: { args: { userIds: string[] }; return: void };
   set_show_dock_icon_in_call: { args: { enabled: boolean }; return: void };
   set_auto_update_enabled: { args: { enabled: boolean }; return: void };
   set_start_camera_on_call: { args: { enabled: boolean }; return: void };
   set_start_mic_on_call: { args: { enabled: boolean }; return: void };
   set_remote_control_enabled: { args: { enabled: boolean }; return: void };
+  set_control_role: { args: { userId: string; role: ControlRole | null }; return: void };
   set_shortcut_toggle_mic: { args: { accel: string }; return: void };
   set_shortcut_toggle_camera: { args: { accel: string }; return: void };
   set_shortcut_toggle_screenshare: { args: { accel: string }; return: void };
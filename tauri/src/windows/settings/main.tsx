@@ -374,25 +374,25 @@ function SettingsWindow() {
               <ShortcutRow
                 title="Mute / unmute mic"
                 description="Toggle microphone during call"
-                value={settings.shortcut_toggle_mic}
+                value={settings.shortcut_toggle_mic ?? ""}
                 onCommit={(accel) => commitShortcut("mic", accel)}
               />
               <ShortcutRow
                 title="Toggle camera"
                 description="Turn camera on or off during call"
-                value={settings.shortcut_toggle_camera}
+                value={settings.shortcut_toggle_camera ?? ""}
                 onCommit={(accel) => commitShortcut("camera", accel)}
               />
               <ShortcutRow
                 title="Toggle screen share"
                 description="Start or stop screen sharing"
-                value={settings.shortcut_toggle_screenshare}
+                value={settings.shortcut_toggle_screenshare ?? ""}
                 onCommit={(accel) => commitShortcut("screenshare", accel)}
               />
               <ShortcutRow
                 title="End call"
                 description="Leave the current call"
-                value={settings.shortcut_end_call}
+                value={settings.shortcut_end_call ?? ""}
                 onCommit={(accel) => commitShortcut("end_call", accel)}
               />
             </div>
@@ -1,6 +1,7 @@
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { invoke } from "@tauri-apps/api/core";
 import { getVersion } from "@tauri-apps/api/app";
+import type { Capability, PermissionIssue } from "@/core_payloads";
 const isTauri = typeof window !== "undefined" && window.__TAURI_INTERNALS__ !== undefined;
 
 export let appVersion: null | string = null;
@@ -125,6 +126,14 @@ const getCameraPermission = async () => {
   return await invoke<boolean>("get_camera_permission");
 };
 
+const getPermissionIssues = async () => {
+  return await invoke<PermissionIssue[]>("get_permission_issues");
+};
+
+const getCoreCapabilities = async () => {
+  return await invoke<Capability[]>("get_core_capabilities");
+};
+
 const hideTrayIconInstruction = async () => {
   await invoke("skip_tray_notification_selection_window");
 };
@@ -315,6 +324,8 @@ export const tauriUtils = {
   getMicPermission,
   getScreenSharePermission,
   getCameraPermission,
+  getPermissionIssues,
+  getCoreCapabilities,
   getLastUsedMic,
   setLastUsedMic,
   getLastUsedCamera,